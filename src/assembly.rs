@@ -0,0 +1,198 @@
+//! parallel assembly of the element matrices and vectors to the sparse matrix and the right-hand side.
+//! Each thread owns a range of the rows, and visits the elements around these rows in the increasing order of the index,
+//! computing the element matrices on the fly and adding only the rows it owns. No element matrix is stored.
+//! Hence, the result is identical to the sequential merge regardless of the number of the threads.
+
+/// split `vals` into the parts of the lengths `part2len`
+fn split_parts<'a, T>(mut vals: &'a mut [T], part2len: &[usize]) -> Vec<&'a mut [T]> {
+    let mut parts = Vec::with_capacity(part2len.len());
+    for &len in part2len {
+        let (part, rest) = vals.split_at_mut(len);
+        vals = rest;
+        parts.push(part);
+    }
+    parts
+}
+
+/// assemble the element matrices and vectors concurrently, as `Matrix::merge` with `node2row == node2col`
+/// is called for each element in the order of the index
+/// * `a_mat` - sparse matrix with the non-zero pattern including all the element matrices. The values are added
+/// * `rhs_vec` - right-hand side vector. The values are added
+/// * `elements` - node lists of the elements in the order of the index,
+///   e.g., `elem2idx.windows(2).map(|w| &idx2node[w[0]..w[1]])` for the elements in the CRS arrays.
+///   They are collected once because each thread looks up the elements around its rows
+/// * `element` - `element(i_elem, nodes, emat, evec)` sets the element matrix (row major) and vector of the `i_elem`-th element.
+///   The buffers `emat` and `evec` are zero on input.
+///   It is called more than once for the elements whose nodes are in the rows of the different threads
+/// * `num_thread` - number of the threads. Computed on the current thread if it is one
+pub fn par_assemble<T, I, F>(
+    a_mat: &mut crate::sparse_square::Matrix<T>,
+    rhs_vec: &mut [T],
+    elements: I,
+    element: F,
+    num_thread: usize,
+) where
+    T: num_traits::Zero + std::ops::AddAssign + Copy + Send,
+    I: IntoIterator,
+    I::Item: AsRef<[usize]>,
+    F: Fn(usize, &[usize], &mut [T], &mut [T]) + Sync,
+{
+    let num_blk = a_mat.num_blk;
+    assert_eq!(rhs_vec.len(), num_blk);
+    let num_thread = num_thread.max(1);
+    let mut elem2idx = vec![0_usize];
+    let mut idx2node = Vec::<usize>::new();
+    for nodes in elements {
+        idx2node.extend_from_slice(nodes.as_ref());
+        elem2idx.push(idx2node.len());
+    }
+    let (elem2idx, idx2node) = (&elem2idx, &idx2node);
+    let (node2idx, idx2elem) = crate::coloring::node2elem(elem2idx, idx2node, num_blk);
+    let part2row = crate::sparse_square::partition_rows(&a_mat.row2idx, num_thread);
+    let row_lens: Vec<usize> = part2row.windows(2).map(|w| w[1] - w[0]).collect();
+    let idx_lens: Vec<usize> = part2row
+        .windows(2)
+        .map(|w| a_mat.row2idx[w[1]] - a_mat.row2idx[w[0]])
+        .collect();
+    let (row2idx, idx2col) = (&a_mat.row2idx, &a_mat.idx2col);
+    let idx2val_parts = split_parts(&mut a_mat.idx2val, &idx_lens);
+    let row2val_parts = split_parts(&mut a_mat.row2val, &row_lens);
+    let rhs_parts = split_parts(rhs_vec, &row_lens);
+    // compute the elements around the rows `i_row0..` and add them to these rows
+    let merge_rows = |i_row0: usize, idx2val: &mut [T], row2val: &mut [T], rhs: &mut [T]| {
+        let rows = i_row0..i_row0 + row2val.len();
+        let idx0 = row2idx[i_row0];
+        let mut elems = idx2elem[node2idx[rows.start]..node2idx[rows.end]].to_vec();
+        elems.sort();
+        elems.dedup();
+        let mut col2idx = vec![usize::MAX; num_blk];
+        let (mut emat, mut evec) = (Vec::<T>::new(), Vec::<T>::new());
+        for i_elem in elems {
+            let nodes = &idx2node[elem2idx[i_elem]..elem2idx[i_elem + 1]];
+            let num_node = nodes.len();
+            emat.clear();
+            emat.resize(num_node * num_node, T::zero());
+            evec.clear();
+            evec.resize(num_node, T::zero());
+            element(i_elem, nodes, &mut emat, &mut evec);
+            for (inode, &i_row) in nodes.iter().enumerate() {
+                if !rows.contains(&i_row) {
+                    continue;
+                }
+                for ij_idx in row2idx[i_row]..row2idx[i_row + 1] {
+                    col2idx[idx2col[ij_idx]] = ij_idx;
+                }
+                for (jnode, &j_col) in nodes.iter().enumerate() {
+                    let v = emat[inode * num_node + jnode];
+                    if j_col == i_row {
+                        row2val[i_row - i_row0] += v;
+                    } else {
+                        let ij_idx = col2idx[j_col];
+                        assert!(ij_idx != usize::MAX, "the pattern lacks the entry");
+                        idx2val[ij_idx - idx0] += v;
+                    }
+                }
+                rhs[i_row - i_row0] += evec[inode];
+                for ij_idx in row2idx[i_row]..row2idx[i_row + 1] {
+                    col2idx[idx2col[ij_idx]] = usize::MAX;
+                }
+            }
+        }
+    };
+    std::thread::scope(|s| {
+        let parts = idx2val_parts.into_iter().zip(row2val_parts).zip(rhs_parts);
+        for (i_part, ((idx2val, row2val), rhs)) in parts.enumerate() {
+            let i_row0 = part2row[i_part];
+            if num_thread == 1 {
+                merge_rows(i_row0, idx2val, row2val, rhs);
+            } else {
+                let merge_rows = &merge_rows;
+                s.spawn(move || merge_rows(i_row0, idx2val, row2val, rhs));
+            }
+        }
+    });
+}
+
+#[test]
+fn test_par_assemble() {
+    // triangle elements of a grid with the Laplacian and the mass
+    let (num_x, num_y) = (30, 20);
+    let num_node = (num_x + 1) * (num_y + 1);
+    let node2xy: Vec<[f64; 2]> = (0..num_node)
+        .map(|i| {
+            [
+                (i % (num_x + 1)) as f64,
+                (i / (num_x + 1)) as f64 + 0.1 * (i as f64).sin(),
+            ]
+        })
+        .collect();
+    let mut elem2idx = vec![0_usize];
+    let mut idx2node = Vec::<usize>::new();
+    for iy in 0..num_y {
+        for ix in 0..num_x {
+            let i0 = iy * (num_x + 1) + ix;
+            idx2node.extend([i0, i0 + 1, i0 + num_x + 2]);
+            elem2idx.push(idx2node.len());
+            idx2node.extend([i0, i0 + num_x + 2, i0 + num_x + 1]);
+            elem2idx.push(idx2node.len());
+        }
+    }
+    let element = |_i_elem: usize, nodes: &[usize], emat: &mut [f64], evec: &mut [f64]| {
+        let p: Vec<[f64; 2]> = nodes.iter().map(|&i| node2xy[i]).collect();
+        let area = 0.5
+            * ((p[1][0] - p[0][0]) * (p[2][1] - p[0][1])
+                - (p[2][0] - p[0][0]) * (p[1][1] - p[0][1]));
+        let grad: Vec<[f64; 2]> = (0..3)
+            .map(|i| {
+                let (p1, p2) = (p[(i + 1) % 3], p[(i + 2) % 3]);
+                [(p1[1] - p2[1]) * 0.5 / area, (p2[0] - p1[0]) * 0.5 / area]
+            })
+            .collect();
+        for i in 0..3 {
+            for j in 0..3 {
+                let mass = if i == j { area / 6. } else { area / 12. };
+                emat[i * 3 + j] = area * (grad[i][0] * grad[j][0] + grad[i][1] * grad[j][1]) + mass;
+            }
+            evec[i] = area / 3.;
+        }
+    };
+    let (a0, rhs0) = crate::coloring::merge_mesh(&elem2idx, &idx2node, num_node, element);
+    let elements: Vec<&[usize]> = elem2idx.windows(2).map(|w| &idx2node[w[0]..w[1]]).collect();
+    for num_thread in [1, 3, 8] {
+        let mut a1 = a0.clone();
+        a1.set_zero();
+        let mut rhs1 = vec![0.; num_node];
+        par_assemble(&mut a1, &mut rhs1, &elements, element, num_thread);
+        // bitwise identical to the sequential merge
+        assert_eq!(a0.idx2val, a1.idx2val);
+        assert_eq!(a0.row2val, a1.row2val);
+        assert_eq!(rhs0, rhs1);
+    }
+}
+
+#[test]
+fn test_par_assemble_irregular() {
+    let num_node = 300;
+    let (elem2idx, idx2node) = crate::coloring::irregular_mesh(num_node, 500);
+    let element = |i_elem: usize, nodes: &[usize], emat: &mut [f64], evec: &mut [f64]| {
+        let num_node = nodes.len();
+        for i in 0..num_node {
+            for j in 0..num_node {
+                emat[i * num_node + j] = ((i_elem * 7 + i * 3 + j) as f64).sin();
+            }
+            evec[i] = ((i_elem + i) as f64).cos();
+        }
+    };
+    let (a0, rhs0) = crate::coloring::merge_mesh(&elem2idx, &idx2node, num_node, element);
+    for num_thread in [1, 3, 8] {
+        let mut a1 = a0.clone();
+        a1.set_zero();
+        let mut rhs1 = vec![0.; num_node];
+        // element node lists given by an iterator of the arrays
+        let elements = elem2idx.windows(2).map(|w| idx2node[w[0]..w[1]].to_vec());
+        par_assemble(&mut a1, &mut rhs1, elements, element, num_thread);
+        assert_eq!(a0.idx2val, a1.idx2val);
+        assert_eq!(a0.row2val, a1.row2val);
+        assert_eq!(rhs0, rhs1);
+    }
+}
@@ -0,0 +1,308 @@
+//! Chebyshev polynomial smoother and preconditioner with the Jacobi (diagonal) scaling.
+//! It uses only the sparse matrix-vector product and the bounds of the eigenvalues of `[D]^{-1}[A]`,
+//! hence it runs in parallel unlike the Gauss-Seidel smoother.
+
+use crate::sparse_square;
+use num_traits::AsPrimitive;
+
+/// largest eigenvalue of the symmetric tridiagonal matrix by the bisection with the Sturm sequence
+/// * `alpha` - diagonal entries
+/// * `beta` - sub-diagonal entries (length is one less than `alpha`)
+fn max_eigenvalue_tridiagonal<T>(alpha: &[T], beta: &[T]) -> T
+where
+    T: 'static + num_traits::Float,
+    f32: AsPrimitive<T>,
+{
+    // Gershgorin interval
+    let mut lower = T::infinity();
+    let mut upper = T::neg_infinity();
+    for (i, &a) in alpha.iter().enumerate() {
+        let b0 = if i > 0 { beta[i - 1].abs() } else { T::zero() };
+        let b1 = if i < beta.len() {
+            beta[i].abs()
+        } else {
+            T::zero()
+        };
+        lower = lower.min(a - b0 - b1);
+        upper = upper.max(a + b0 + b1);
+    }
+    let tiny = T::epsilon() * (upper - lower).max(T::min_positive_value());
+    // number of the eigenvalues smaller than `x`
+    let count = |x: T| {
+        let mut num = 0;
+        let mut d = T::one();
+        for (i, &a) in alpha.iter().enumerate() {
+            let b2 = if i > 0 {
+                beta[i - 1] * beta[i - 1]
+            } else {
+                T::zero()
+            };
+            d = a - x - if i > 0 { b2 / d } else { T::zero() };
+            if d == T::zero() {
+                d = tiny;
+            }
+            if d < T::zero() {
+                num += 1;
+            }
+        }
+        num
+    };
+    for _ in 0..100 {
+        let mid = (lower + upper) * 0.5_f32.as_();
+        if mid <= lower || mid >= upper {
+            break;
+        }
+        if count(mid) == alpha.len() {
+            upper = mid;
+        } else {
+            lower = mid;
+        }
+    }
+    upper
+}
+
+/// largest eigenvalue of `[D]^{-1}[A]` estimated by the Lanczos iteration,
+/// where `[A]` is symmetric positive definite and `[D]` is its diagonal.
+/// The iteration is applied to the symmetric matrix `[D]^{-1/2}[A][D]^{-1/2}` with the same eigenvalues,
+/// and the largest Ritz value is returned, which is a lower bound of the exact value.
+/// It panics if a diagonal entry is not positive.
+/// * `num_iteration` - number of the Lanczos iterations (10 is typically enough for the smoother)
+pub fn estimate_max_eigenvalue<T>(a: &sparse_square::Matrix<T>, num_iteration: usize) -> T
+where
+    T: 'static + num_traits::Float + std::ops::MulAssign + std::ops::AddAssign,
+    f32: AsPrimitive<T>,
+    usize: AsPrimitive<T>,
+{
+    use crate::slice::dot;
+    let num_blk = a.num_blk;
+    assert!(
+        a.row2val.iter().all(|&d| d > T::zero()),
+        "the diagonal entries must be positive"
+    );
+    let row2scale: Vec<T> = a.row2val.iter().map(|&d| T::one() / d.sqrt()).collect();
+    // deterministic initial vector that is not orthogonal to the eigenvectors
+    let mut q_vec: Vec<T> = (0..num_blk)
+        .map(|i| 1_f32.as_() + (i.as_() * 0.7_f32.as_()).sin() * 0.5_f32.as_())
+        .collect();
+    let norm = dot(&q_vec, &q_vec).sqrt();
+    q_vec.iter_mut().for_each(|q| *q = *q / norm);
+    let mut q_vec_prev = vec![T::zero(); num_blk];
+    let mut x_vec = vec![T::zero(); num_blk];
+    let mut w_vec = vec![T::zero(); num_blk];
+    let mut alpha = Vec::<T>::with_capacity(num_iteration);
+    let mut beta = Vec::<T>::with_capacity(num_iteration);
+    for _ in 0..num_iteration {
+        // {w} = [D]^{-1/2}[A][D]^{-1/2}{q} - beta {q_prev}
+        for ((x, &q), &s) in x_vec.iter_mut().zip(&q_vec).zip(&row2scale) {
+            *x = q * s;
+        }
+        sparse_square::mult_vec(&mut w_vec, T::zero(), T::one(), a, &x_vec);
+        let beta_prev = beta.last().copied().unwrap_or(T::zero());
+        for ((w, &s), &q_prev) in w_vec.iter_mut().zip(&row2scale).zip(&q_vec_prev) {
+            *w = *w * s - beta_prev * q_prev;
+        }
+        let alpha_j = dot(&w_vec, &q_vec);
+        crate::slice::add_scaled_vector(&mut w_vec, -alpha_j, &q_vec);
+        alpha.push(alpha_j);
+        let beta_j = dot(&w_vec, &w_vec).sqrt();
+        if beta_j <= T::epsilon() * alpha_j.abs() {
+            break; // invariant subspace
+        }
+        beta.push(beta_j);
+        std::mem::swap(&mut q_vec_prev, &mut q_vec);
+        for (q, &w) in q_vec.iter_mut().zip(&w_vec) {
+            *q = w / beta_j;
+        }
+    }
+    if alpha.is_empty() {
+        return T::zero();
+    }
+    max_eigenvalue_tridiagonal(&alpha, &beta[..alpha.len() - 1])
+}
+
+/// parameters of the Chebyshev polynomial for `[D]^{-1}[A]`
+/// * `lambda_min`, `lambda_max` - the interval of the eigenvalues damped by the polynomial
+/// * `degree` - degree of the polynomial, i.e., the number of the matrix-vector products
+/// * `num_thread` - number of the threads for the matrix-vector products (one by default)
+pub struct Chebyshev<T> {
+    pub lambda_min: T,
+    pub lambda_max: T,
+    pub degree: usize,
+    pub num_thread: usize,
+}
+
+impl<T> Chebyshev<T>
+where
+    T: 'static + num_traits::Float + std::ops::MulAssign + std::ops::AddAssign,
+    f32: AsPrimitive<T>,
+    usize: AsPrimitive<T>,
+{
+    /// set the interval from the estimated largest eigenvalue as `[lambda_max / eig_ratio, lambda_max]`.
+    /// The estimation is enlarged by 10% because the Lanczos iteration underestimates it.
+    /// * `eig_ratio` - ratio of the interval. About 30 for the smoother of the multigrid, and larger for the preconditioner
+    /// * `num_lanczos_iteration` - number of the Lanczos iterations for the estimation
+    pub fn new(
+        a: &sparse_square::Matrix<T>,
+        degree: usize,
+        eig_ratio: T,
+        num_lanczos_iteration: usize,
+    ) -> Self {
+        let lambda_max = estimate_max_eigenvalue(a, num_lanczos_iteration) * 1.1_f32.as_();
+        Chebyshev {
+            lambda_min: lambda_max / eig_ratio,
+            lambda_max,
+            degree,
+            num_thread: 1,
+        }
+    }
+}
+
+/// Chebyshev iterations for `[A]{x} = {b}` with the polynomial of `cheb.degree`
+/// * `x_vec` - initial guess and the result
+pub fn smooth<T>(x_vec: &mut [T], a: &sparse_square::Matrix<T>, b_vec: &[T], cheb: &Chebyshev<T>)
+where
+    T: 'static + num_traits::Float + std::ops::MulAssign + std::ops::AddAssign + Send + Sync,
+    f32: AsPrimitive<T>,
+{
+    let num_blk = a.num_blk;
+    assert_eq!(x_vec.len(), num_blk);
+    assert_eq!(b_vec.len(), num_blk);
+    let num_thread = cheb.num_thread;
+    let two: T = 2_f32.as_();
+    let theta = (cheb.lambda_max + cheb.lambda_min) / two;
+    let delta = (cheb.lambda_max - cheb.lambda_min) / two;
+    let sigma = theta / delta;
+    let mut rho = T::one() / sigma;
+    // {r} = {b} - [A]{x}
+    let mut r_vec = b_vec.to_vec();
+    sparse_square::par_mult_vec(&mut r_vec, T::one(), -T::one(), a, x_vec, num_thread);
+    // {d} = [D]^{-1}{r} / theta
+    let mut d_vec: Vec<T> = r_vec
+        .iter()
+        .zip(&a.row2val)
+        .map(|(&r, &d)| r / (d * theta))
+        .collect();
+    for i_degree in 0..cheb.degree {
+        crate::slice::add_scaled_vector(x_vec, T::one(), &d_vec);
+        if i_degree + 1 == cheb.degree {
+            break;
+        }
+        // {r} = {r} - [A]{d}
+        sparse_square::par_mult_vec(&mut r_vec, T::one(), -T::one(), a, &d_vec, num_thread);
+        let rho_new = T::one() / (two * sigma - rho);
+        let (c0, c1) = (rho_new * rho, two * rho_new / delta);
+        for ((d, &r), &a_ii) in d_vec.iter_mut().zip(&r_vec).zip(&a.row2val) {
+            *d = c0 * *d + c1 * r / a_ii;
+        }
+        rho = rho_new;
+    }
+}
+
+/// `{vec} <- [M]^{-1}{vec}` where `[M]^{-1}` is the Chebyshev polynomial of `[D]^{-1}[A]` times `[D]^{-1}`.
+/// It is symmetric, so that it can be used as the preconditioner of the conjugate gradient method.
+pub fn solve_preconditioning_vec<T>(
+    vec: &mut [T],
+    a: &sparse_square::Matrix<T>,
+    cheb: &Chebyshev<T>,
+) where
+    T: 'static + num_traits::Float + std::ops::MulAssign + std::ops::AddAssign + Send + Sync,
+    f32: AsPrimitive<T>,
+{
+    let b_vec = vec.to_vec();
+    crate::slice::set_zero(vec);
+    smooth(vec, a, &b_vec, cheb);
+}
+
+#[test]
+fn test_chebyshev() {
+    use crate::slice::dot;
+    let sparse = crate::sparse_square::laplacian_grid(24, 20, 0.1);
+    let num_blk = sparse.num_blk;
+    // the eigenvalues of [D]^{-1}[A] are less than 2 by the Gershgorin theorem
+    let lambda_max = estimate_max_eigenvalue(&sparse, 30);
+    assert!(lambda_max > 1.9 && lambda_max < 2.);
+    // smoothing of the oscillatory error
+    let cheb = Chebyshev::new(&sparse, 4, 30., 10);
+    assert!(cheb.lambda_max > lambda_max && cheb.lambda_max < 2.2);
+    assert_eq!(cheb.num_thread, 1);
+    let b_vec = vec![0.; num_blk];
+    let mut x_vec: Vec<f64> = (0..num_blk)
+        .map(|i| if (i + i / 24) % 2 == 0 { 1. } else { -1. })
+        .collect();
+    let norm0 = dot(&x_vec, &x_vec).sqrt();
+    let mut x_vec1 = x_vec.clone();
+    smooth(&mut x_vec, &sparse, &b_vec, &cheb);
+    // the threaded products give the same result
+    let cheb1 = Chebyshev {
+        num_thread: 4,
+        ..cheb
+    };
+    smooth(&mut x_vec1, &sparse, &b_vec, &cheb1);
+    assert_eq!(x_vec, x_vec1);
+    // the reduction factor in the interval is bounded by 1 / T_4(31/29) < 0.45
+    assert!(dot(&x_vec, &x_vec).sqrt() < norm0 * 0.45);
+    // the preconditioner is symmetric
+    let cheb = Chebyshev::new(&sparse, 5, 100., 10);
+    let u_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    let v_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64 * 0.3).cos()).collect();
+    let mut mu_vec = u_vec.clone();
+    solve_preconditioning_vec(&mut mu_vec, &sparse, &cheb);
+    let mut mv_vec = v_vec.clone();
+    solve_preconditioning_vec(&mut mv_vec, &sparse, &cheb);
+    let (vmu, umv) = (dot(&v_vec, &mu_vec), dot(&u_vec, &mv_vec));
+    assert!((vmu - umv).abs() < 1.0e-10 * vmu.abs().max(1.));
+    // and reduces the residual of the solution
+    let mut r_vec = u_vec.clone();
+    sparse_square::mult_vec(&mut r_vec, 1., -1., &sparse, &mu_vec);
+    assert!(dot(&r_vec, &r_vec) < dot(&u_vec, &u_vec));
+}
+
+#[test]
+#[should_panic(expected = "the diagonal entries must be positive")]
+fn test_estimate_max_eigenvalue_non_positive_diagonal() {
+    let mut sparse = crate::sparse_square::laplacian_grid(4, 3, 0.1);
+    sparse.row2val[5] = 0.;
+    estimate_max_eigenvalue(&sparse, 10);
+}
+
+#[test]
+fn test_chebyshev_pcg_irregular() {
+    use crate::solver_sparse::preconditioned_conjugate_gradient_with_operators;
+    use std::ops::ControlFlow;
+    // the eigenvalues of [D]^{-1}[A] do not change by the symmetric diagonal scaling
+    let sparse = crate::sparse_square::irregular_test_matrix(500, true, 100.);
+    let num_blk = sparse.num_blk;
+    let lambda_max = estimate_max_eigenvalue(&sparse, 30);
+    assert!(lambda_max > 1. && lambda_max < 2.);
+    // the diagonally dominant matrix is well conditioned, so the narrow interval is used
+    let cheb = Chebyshev::new(&sparse, 4, 4., 10);
+    let b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    let criterion = crate::stopping_criterion::Criterion::RelativeResidual(1.0e-10);
+    let mut num_itrs = vec![];
+    for is_chebyshev in [true, false] {
+        let mut r_vec = b_vec.clone();
+        let (mut x_vec, mut pr_vec, mut p_vec) = (vec![], vec![], vec![]);
+        let conv_hist = preconditioned_conjugate_gradient_with_operators(
+            &mut r_vec,
+            &mut x_vec,
+            &mut pr_vec,
+            &mut p_vec,
+            &criterion,
+            1000,
+            |y: &mut [f64], x: &[f64]| sparse_square::mult_mat(y, 0., 1., &sparse, x),
+            |v: &mut [f64]| {
+                if is_chebyshev {
+                    solve_preconditioning_vec(v, &sparse, &cheb);
+                } else {
+                    // Jacobi
+                    v.iter_mut().zip(&sparse.row2val).for_each(|(v, d)| *v /= d);
+                }
+            },
+            |_, _, _| ControlFlow::Continue(()),
+        );
+        let err = crate::sparse_square::componentwise_backward_error(&sparse, &x_vec, &b_vec);
+        assert!(err < 1.0e-8, "{}", err);
+        num_itrs.push(conv_hist.len());
+    }
+    assert!(num_itrs[0] * 2 < num_itrs[1]);
+}
@@ -0,0 +1,421 @@
+//! coloring of the elements such that the elements with the same color do not share nodes.
+//! The element matrices of the same color can be merged to the sparse matrix concurrently without conflicts (`par_merge`).
+//! The elements are given as the CRS format where the nodes of the `i`-th element are `idx2node[elem2idx[i]..elem2idx[i+1]]`.
+
+/// elements connected to each node in the CRS format
+/// * return - `node2idx`, `idx2elem`
+pub fn node2elem(
+    elem2idx: &[usize],
+    idx2node: &[usize],
+    num_node: usize,
+) -> (Vec<usize>, Vec<usize>) {
+    let mut node2idx = vec![0_usize; num_node + 1];
+    for &i_node in idx2node {
+        node2idx[i_node + 1] += 1;
+    }
+    for i_node in 0..num_node {
+        node2idx[i_node + 1] += node2idx[i_node];
+    }
+    let mut idx2elem = vec![0_usize; idx2node.len()];
+    let mut node2pos = node2idx[..num_node].to_vec();
+    for i_elem in 0..elem2idx.len() - 1 {
+        for &i_node in &idx2node[elem2idx[i_elem]..elem2idx[i_elem + 1]] {
+            idx2elem[node2pos[i_node]] = i_elem;
+            node2pos[i_node] += 1;
+        }
+    }
+    (node2idx, idx2elem)
+}
+
+/// greedy (first-fit) coloring of the elements visited in the order of the index
+/// * `num_node` - number of the nodes
+/// * return - number of the colors and the color of each element
+pub fn greedy(elem2idx: &[usize], idx2node: &[usize], num_node: usize) -> (usize, Vec<usize>) {
+    let num_elem = elem2idx.len() - 1;
+    let (node2idx, idx2elem) = node2elem(elem2idx, idx2node, num_node);
+    let mut elem2color = vec![usize::MAX; num_elem];
+    let mut color2stamp = Vec::<usize>::new();
+    let mut num_color = 0;
+    for i_elem in 0..num_elem {
+        // colors used by the neighbouring elements
+        for &i_node in &idx2node[elem2idx[i_elem]..elem2idx[i_elem + 1]] {
+            for &j_elem in &idx2elem[node2idx[i_node]..node2idx[i_node + 1]] {
+                let j_color = elem2color[j_elem];
+                if j_color != usize::MAX {
+                    color2stamp[j_color] = i_elem;
+                }
+            }
+        }
+        let i_color = (0..num_color)
+            .find(|&i_color| color2stamp[i_color] != i_elem)
+            .unwrap_or(num_color);
+        if i_color == num_color {
+            num_color += 1;
+            color2stamp.push(usize::MAX);
+        }
+        elem2color[i_elem] = i_color;
+    }
+    (num_color, elem2color)
+}
+
+/// coloring with the balanced number of the elements among the colors.
+/// After the greedy coloring, the elements of the colors larger than the average are moved to
+/// the smallest color that does not conflict, keeping the number of the colors.
+/// * return - number of the colors and the color of each element
+pub fn balanced(elem2idx: &[usize], idx2node: &[usize], num_node: usize) -> (usize, Vec<usize>) {
+    let num_elem = elem2idx.len() - 1;
+    let (num_color, mut elem2color) = greedy(elem2idx, idx2node, num_node);
+    if num_color == 0 {
+        return (num_color, elem2color);
+    }
+    let (node2idx, idx2elem) = node2elem(elem2idx, idx2node, num_node);
+    let mut color2size = vec![0_usize; num_color];
+    for &i_color in &elem2color {
+        color2size[i_color] += 1;
+    }
+    let target = num_elem.div_ceil(num_color);
+    let mut color2stamp = vec![usize::MAX; num_color];
+    for i_elem in 0..num_elem {
+        let i_color = elem2color[i_elem];
+        if color2size[i_color] <= target {
+            continue;
+        }
+        for &i_node in &idx2node[elem2idx[i_elem]..elem2idx[i_elem + 1]] {
+            for &j_elem in &idx2elem[node2idx[i_node]..node2idx[i_node + 1]] {
+                color2stamp[elem2color[j_elem]] = i_elem;
+            }
+        }
+        let j_color = (0..num_color)
+            .filter(|&j_color| color2stamp[j_color] != i_elem)
+            .min_by_key(|&j_color| color2size[j_color]);
+        if let Some(j_color) = j_color {
+            if color2size[j_color] < target {
+                color2size[i_color] -= 1;
+                color2size[j_color] += 1;
+                elem2color[i_elem] = j_color;
+            }
+        }
+    }
+    (num_color, elem2color)
+}
+
+/// elements grouped by the colors in the CRS format
+/// * return - `color2idx`, `idx2elem`
+pub fn color2elem(elem2color: &[usize], num_color: usize) -> (Vec<usize>, Vec<usize>) {
+    let mut color2idx = vec![0_usize; num_color + 1];
+    for &i_color in elem2color {
+        color2idx[i_color + 1] += 1;
+    }
+    for i_color in 0..num_color {
+        color2idx[i_color + 1] += color2idx[i_color];
+    }
+    let mut idx2elem = vec![0_usize; elem2color.len()];
+    let mut color2pos = color2idx[..num_color].to_vec();
+    for (i_elem, &i_color) in elem2color.iter().enumerate() {
+        idx2elem[color2pos[i_color]] = i_elem;
+        color2pos[i_color] += 1;
+    }
+    (color2idx, idx2elem)
+}
+
+/// merge the element matrices and vectors concurrently one color at a time.
+/// The elements of a color do not share nodes, so they are split among the threads
+/// and each thread adds its element matrices to the rows of their nodes without conflicts.
+/// The result does not depend on the number of the threads, but the order of the summation
+/// differs from the sequential merge in the order of the element index.
+/// * `a_mat` - sparse matrix with the non-zero pattern including all the element matrices. The values are added
+/// * `rhs_vec` - right-hand side vector. The values are added
+/// * `color2idx`, `idx2elem` - elements grouped by the colors (see `color2elem`)
+/// * `element` - `element(i_elem, nodes, emat, evec)` sets the element matrix (row major) and vector of the `i_elem`-th element.
+///   The buffers `emat` and `evec` are zero on input
+/// * `num_thread` - number of the threads. Computed on the current thread if it is one
+#[allow(clippy::too_many_arguments)]
+pub fn par_merge<T, F>(
+    a_mat: &mut crate::sparse_square::Matrix<T>,
+    rhs_vec: &mut [T],
+    elem2idx: &[usize],
+    idx2node: &[usize],
+    color2idx: &[usize],
+    idx2elem: &[usize],
+    element: F,
+    num_thread: usize,
+) where
+    T: num_traits::Zero + std::ops::AddAssign + Copy + Send,
+    F: Fn(usize, &[usize], &mut [T], &mut [T]) + Sync,
+{
+    let num_blk = a_mat.num_blk;
+    assert_eq!(rhs_vec.len(), num_blk);
+    let num_thread = num_thread.max(1);
+    let (row2idx, idx2col) = (&a_mat.row2idx, &a_mat.idx2col);
+    // off-diagonal values, diagonal and right-hand side of each row
+    let mut row2vals = Vec::<&mut [T]>::with_capacity(num_blk);
+    let mut idx2val = a_mat.idx2val.as_mut_slice();
+    for i_row in 0..num_blk {
+        let (vals, rest) = idx2val.split_at_mut(row2idx[i_row + 1] - row2idx[i_row]);
+        row2vals.push(vals);
+        idx2val = rest;
+    }
+    let mut rows: Vec<(&mut [T], &mut T, &mut T)> = row2vals
+        .into_iter()
+        .zip(a_mat.row2val.iter_mut())
+        .zip(rhs_vec.iter_mut())
+        .map(|((vals, diag), rhs)| (vals, diag, rhs))
+        .collect();
+    // `rows` are the rows of the nodes of `elems` in the order of `idx2node`
+    let merge_elems = |elems: &[usize], rows: Vec<Option<(&mut [T], &mut T, &mut T)>>| {
+        let mut col2idx = vec![usize::MAX; num_blk];
+        let (mut emat, mut evec) = (Vec::<T>::new(), Vec::<T>::new());
+        let mut rows = rows.into_iter();
+        for &i_elem in elems {
+            let nodes = &idx2node[elem2idx[i_elem]..elem2idx[i_elem + 1]];
+            let num_node = nodes.len();
+            emat.clear();
+            emat.resize(num_node * num_node, T::zero());
+            evec.clear();
+            evec.resize(num_node, T::zero());
+            element(i_elem, nodes, &mut emat, &mut evec);
+            let mut elem_rows: Vec<_> = rows.by_ref().take(num_node).collect();
+            for (inode, &i_row) in nodes.iter().enumerate() {
+                // the node may appear more than once in the element
+                let inode0 = nodes.iter().position(|&n| n == i_row).unwrap();
+                let (vals, diag, rhs) = elem_rows[inode0].as_mut().unwrap();
+                for ij_idx in row2idx[i_row]..row2idx[i_row + 1] {
+                    col2idx[idx2col[ij_idx]] = ij_idx - row2idx[i_row];
+                }
+                for (jnode, &j_col) in nodes.iter().enumerate() {
+                    let v = emat[inode * num_node + jnode];
+                    if j_col == i_row {
+                        **diag += v;
+                    } else {
+                        assert!(col2idx[j_col] != usize::MAX, "the pattern lacks the entry");
+                        vals[col2idx[j_col]] += v;
+                    }
+                }
+                **rhs += evec[inode];
+                for ij_idx in row2idx[i_row]..row2idx[i_row + 1] {
+                    col2idx[idx2col[ij_idx]] = usize::MAX;
+                }
+            }
+        }
+    };
+    for i_color in 0..color2idx.len() - 1 {
+        let elems = &idx2elem[color2idx[i_color]..color2idx[i_color + 1]];
+        let part2elem: Vec<usize> = (0..=num_thread)
+            .map(|i_part| elems.len() * i_part / num_thread)
+            .collect();
+        // hand the rows to the thread merging the element of the node
+        let mut color_rows: Vec<Option<(&mut [T], &mut T, &mut T)>> = rows
+            .iter_mut()
+            .map(|(vals, diag, rhs)| Some((&mut **vals, &mut **diag, &mut **rhs)))
+            .collect();
+        let mut part2rows = Vec::with_capacity(num_thread);
+        for w in part2elem.windows(2) {
+            let mut part_rows = vec![];
+            for &i_elem in &elems[w[0]..w[1]] {
+                let nodes = &idx2node[elem2idx[i_elem]..elem2idx[i_elem + 1]];
+                for (inode, &i_node) in nodes.iter().enumerate() {
+                    let row = color_rows[i_node].take();
+                    assert!(
+                        row.is_some() || nodes[..inode].contains(&i_node),
+                        "the elements of the same color share the node"
+                    );
+                    part_rows.push(row);
+                }
+            }
+            part2rows.push(part_rows);
+        }
+        std::thread::scope(|s| {
+            for (w, part_rows) in part2elem.windows(2).zip(part2rows) {
+                let elems = &elems[w[0]..w[1]];
+                if num_thread == 1 {
+                    merge_elems(elems, part_rows);
+                } else {
+                    let merge_elems = &merge_elems;
+                    s.spawn(move || merge_elems(elems, part_rows));
+                }
+            }
+        });
+    }
+}
+
+#[test]
+fn test_coloring() {
+    // triangle elements of a grid
+    let (num_x, num_y) = (9, 7);
+    let num_node = (num_x + 1) * (num_y + 1);
+    let mut elem2idx = vec![0_usize];
+    let mut idx2node = Vec::<usize>::new();
+    for iy in 0..num_y {
+        for ix in 0..num_x {
+            let i0 = iy * (num_x + 1) + ix;
+            idx2node.extend([i0, i0 + 1, i0 + num_x + 2]);
+            elem2idx.push(idx2node.len());
+            idx2node.extend([i0, i0 + num_x + 2, i0 + num_x + 1]);
+            elem2idx.push(idx2node.len());
+        }
+    }
+    let num_elem = elem2idx.len() - 1;
+    // scatter the order of the elements
+    let idx2node: Vec<usize> = (0..num_elem)
+        .flat_map(|i| {
+            let i_elem = (i * 17) % num_elem;
+            idx2node[i_elem * 3..i_elem * 3 + 3].to_vec()
+        })
+        .collect();
+    let mut color2size_max = vec![];
+    for (num_color, elem2color) in [
+        greedy(&elem2idx, &idx2node, num_node),
+        balanced(&elem2idx, &idx2node, num_node),
+    ] {
+        let (color2idx, idx2elem) = color2elem(&elem2color, num_color);
+        assert_eq!(color2idx[num_color], num_elem);
+        for i_color in 0..num_color {
+            let mut node2flag = vec![false; num_node];
+            for &i_elem in &idx2elem[color2idx[i_color]..color2idx[i_color + 1]] {
+                assert_eq!(elem2color[i_elem], i_color);
+                for &i_node in &idx2node[elem2idx[i_elem]..elem2idx[i_elem + 1]] {
+                    assert!(!node2flag[i_node]); // no shared node in the same color
+                    node2flag[i_node] = true;
+                }
+            }
+        }
+        let size_max = (0..num_color)
+            .map(|i| color2idx[i + 1] - color2idx[i])
+            .max();
+        color2size_max.push(size_max.unwrap());
+    }
+    // the balanced coloring has the same number of colors with the smaller maximum size
+    assert!(color2size_max[1] < color2size_max[0]);
+    let num_color = greedy(&elem2idx, &idx2node, num_node).0;
+    assert!(color2size_max[1] <= num_elem.div_ceil(num_color));
+}
+
+/// elements with two to four pseudo-random nodes for the tests. Some elements have a duplicated node
+#[cfg(test)]
+pub(crate) fn irregular_mesh(num_node: usize, num_elem: usize) -> (Vec<usize>, Vec<usize>) {
+    let mut seed = 0x9e37_79b9_7f4a_7c15_u64;
+    let mut random = || {
+        // xorshift
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        (seed % num_node as u64) as usize
+    };
+    let mut elem2idx = vec![0_usize];
+    let mut idx2node = Vec::<usize>::new();
+    for i_elem in 0..num_elem {
+        let i_node0 = random();
+        idx2node.push(i_node0);
+        for _ in 0..(1 + i_elem % 3) {
+            // near nodes mostly and a far node sometimes
+            let i_node = if random() % 5 == 0 {
+                random()
+            } else {
+                (i_node0 + 1 + random() % 7) % num_node
+            };
+            idx2node.push(i_node);
+        }
+        if i_elem % 50 == 0 {
+            idx2node.push(i_node0);
+        }
+        elem2idx.push(idx2node.len());
+    }
+    (elem2idx, idx2node)
+}
+
+/// non-zero pattern of the sparse matrix merging all the element matrices (without the diagonal)
+#[cfg(test)]
+pub(crate) fn pattern_of_mesh(
+    elem2idx: &[usize],
+    idx2node: &[usize],
+    num_node: usize,
+) -> (Vec<usize>, Vec<usize>) {
+    let (node2idx, idx2elem) = node2elem(elem2idx, idx2node, num_node);
+    let mut row2idx = vec![0_usize; num_node + 1];
+    let mut idx2col = Vec::<usize>::new();
+    for i_node in 0..num_node {
+        let mut cols: Vec<usize> = idx2elem[node2idx[i_node]..node2idx[i_node + 1]]
+            .iter()
+            .flat_map(|&i_elem| idx2node[elem2idx[i_elem]..elem2idx[i_elem + 1]].to_vec())
+            .filter(|&j_node| j_node != i_node)
+            .collect();
+        cols.sort();
+        cols.dedup();
+        idx2col.extend(cols);
+        row2idx[i_node + 1] = idx2col.len();
+    }
+    (row2idx, idx2col)
+}
+
+/// reference of the tests merging the element matrices and vectors sequentially by `Matrix::merge`
+/// * `element` - same as `par_merge`
+/// * return - matrix with the pattern of `pattern_of_mesh` and the right-hand side vector
+#[cfg(test)]
+pub(crate) fn merge_mesh<F>(
+    elem2idx: &[usize],
+    idx2node: &[usize],
+    num_node: usize,
+    element: F,
+) -> (crate::sparse_square::Matrix<f64>, Vec<f64>)
+where
+    F: Fn(usize, &[usize], &mut [f64], &mut [f64]),
+{
+    let (row2idx, idx2col) = pattern_of_mesh(elem2idx, idx2node, num_node);
+    let mut a_mat = crate::sparse_square::Matrix::<f64>::new();
+    a_mat.symbolic_initialization(&row2idx, &idx2col);
+    a_mat.set_zero();
+    let mut rhs_vec = vec![0.; num_node];
+    let mut merge_buffer = vec![];
+    for i_elem in 0..elem2idx.len() - 1 {
+        let nodes = &idx2node[elem2idx[i_elem]..elem2idx[i_elem + 1]];
+        let mut emat = vec![0.; nodes.len() * nodes.len()];
+        let mut evec = vec![0.; nodes.len()];
+        element(i_elem, nodes, &mut emat, &mut evec);
+        a_mat.merge(nodes, nodes, &emat, &mut merge_buffer);
+        for (&i_node, &v) in nodes.iter().zip(&evec) {
+            rhs_vec[i_node] += v;
+        }
+    }
+    (a_mat, rhs_vec)
+}
+
+#[test]
+fn test_par_merge() {
+    let num_node = 300;
+    let (elem2idx, idx2node) = irregular_mesh(num_node, 500);
+    // unsymmetric element matrices of the various sizes
+    let element = |i_elem: usize, nodes: &[usize], emat: &mut [f64], evec: &mut [f64]| {
+        let num_node = nodes.len();
+        for i in 0..num_node {
+            for j in 0..num_node {
+                emat[i * num_node + j] = ((i_elem * 7 + i * 3 + j) as f64).sin();
+            }
+            evec[i] = ((i_elem + i) as f64).cos();
+        }
+    };
+    let (a0, rhs0) = merge_mesh(&elem2idx, &idx2node, num_node, element);
+    let (num_color, elem2color) = balanced(&elem2idx, &idx2node, num_node);
+    let (color2idx, idx2elem) = color2elem(&elem2color, num_color);
+    let mut a1 = a0.clone();
+    let mut rhs1 = vec![];
+    for num_thread in [1, 3, 8] {
+        let mut a2 = a0.clone();
+        a2.set_zero();
+        let mut rhs2 = vec![0.; num_node];
+        par_merge(
+            &mut a2, &mut rhs2, &elem2idx, &idx2node, &color2idx, &idx2elem, element, num_thread,
+        );
+        let diff = |v0: &[f64], v1: &[f64]| v0.iter().zip(v1).all(|(a, b)| (a - b).abs() < 1.0e-12);
+        assert!(diff(&a0.idx2val, &a2.idx2val));
+        assert!(diff(&a0.row2val, &a2.row2val));
+        assert!(diff(&rhs0, &rhs2));
+        if num_thread == 1 {
+            (a1, rhs1) = (a2, rhs2);
+        } else {
+            // independent of the number of the threads
+            assert_eq!(a1.idx2val, a2.idx2val);
+            assert_eq!(a1.row2val, a2.row2val);
+            assert_eq!(rhs1, rhs2);
+        }
+    }
+}
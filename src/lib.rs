@@ -1,8 +1,21 @@
+pub mod assembly;
+pub mod chebyshev;
+pub mod coloring;
 pub mod linearsystem;
+pub mod multicolor;
+pub mod ordering;
+pub mod schur_complement;
+pub mod schwarz;
 pub mod slice;
 pub mod solver_sparse;
+pub mod sparse_approximate_inverse;
+pub mod sparse_cholesky;
 pub mod sparse_ilu;
+pub mod sparse_ldlt;
+pub mod sparse_lu;
 pub mod sparse_matrix_multiplication;
 pub mod sparse_square;
+pub mod stationary;
+pub mod stopping_criterion;
 
 pub mod nalgebra;
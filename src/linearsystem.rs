@@ -27,7 +27,9 @@ where
         + std::ops::AddAssign
         + std::fmt::Display
         + std::ops::MulAssign
-        + std::ops::SubAssign,
+        + std::ops::SubAssign
+        + Send
+        + Sync,
     f32: num_traits::AsPrimitive<T>,
 {
     pub fn new() -> Self {
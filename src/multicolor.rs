@@ -0,0 +1,403 @@
+//! multicolor ILU(0) and Gauss-Seidel.
+//! The rows are reordered by the colors such that the rows with the same color are not coupled.
+//! Then, the rows of the same color can be factorized, solved and relaxed concurrently.
+
+use num_traits::AsPrimitive;
+
+/// coloring of the graph of the matrix such that the adjacent nodes have different colors.
+/// Each node takes the least used color that does not conflict with its neighbours among at least `num_color` colors.
+/// Fewer colors give more parallelism, while more colors give the ordering closer to the original one.
+/// * `row2idx`, `idx2col` - non-zero pattern of the square matrix (`A + A^T` is used)
+/// * `num_color` - requested number of the colors. More colors are used if necessary
+/// * return - number of the colors and the color of each node
+pub fn coloring(row2idx: &[usize], idx2col: &[usize], num_color: usize) -> (usize, Vec<usize>) {
+    let (sym_row2idx, sym_idx2col) = crate::ordering::symmetrize_pattern(row2idx, idx2col);
+    let num_node = sym_row2idx.len() - 1;
+    let mut node2color = vec![usize::MAX; num_node];
+    let mut color2size = vec![0_usize; num_color.max(1)];
+    let mut color2stamp = vec![usize::MAX; num_color.max(1)];
+    for i_node in 0..num_node {
+        for &j_node in &sym_idx2col[sym_row2idx[i_node]..sym_row2idx[i_node + 1]] {
+            let j_color = node2color[j_node];
+            if j_color != usize::MAX {
+                color2stamp[j_color] = i_node;
+            }
+        }
+        let i_color = (0..color2size.len())
+            .filter(|&i_color| color2stamp[i_color] != i_node)
+            .min_by_key(|&i_color| color2size[i_color])
+            .unwrap_or(color2size.len());
+        if i_color == color2size.len() {
+            color2size.push(0);
+            color2stamp.push(usize::MAX);
+        }
+        color2size[i_color] += 1;
+        node2color[i_node] = i_color;
+    }
+    let num_color = color2size.iter().rposition(|&n| n > 0).map_or(0, |i| i + 1);
+    (num_color, node2color)
+}
+
+/// ILU(0) preconditioner and Gauss-Seidel smoother on the matrix reordered by the colors
+pub struct MultiColor<T> {
+    /// permutation from the reordered rows to the original rows
+    pub new2old: Vec<usize>,
+    /// the rows of the `i`-th color are `color2row[i]..color2row[i+1]` in the reordered matrix
+    pub color2row: Vec<usize>,
+    /// reordered matrix `P A P^T`
+    pub sparse: crate::sparse_square::Matrix<T>,
+    /// ILU(0) factorization of the reordered matrix
+    pub ilu: crate::sparse_ilu::Preconditioner<T>,
+}
+
+impl<T> MultiColor<T>
+where
+    T: 'static
+        + Copy
+        + num_traits::Zero
+        + std::default::Default
+        + std::ops::AddAssign
+        + std::fmt::Display,
+    f32: AsPrimitive<T>,
+{
+    pub fn new() -> Self {
+        MultiColor {
+            new2old: vec![],
+            color2row: vec![0],
+            sparse: crate::sparse_square::Matrix::new(),
+            ilu: crate::sparse_ilu::Preconditioner::new(),
+        }
+    }
+
+    /// reorder the rows by the colors and set the pattern of the ILU(0)
+    /// * `num_color` - requested number of the colors (see `coloring`)
+    pub fn symbolic_initialization(
+        &mut self,
+        a: &crate::sparse_square::Matrix<T>,
+        num_color: usize,
+    ) {
+        let (num_color, node2color) = coloring(&a.row2idx, &a.idx2col, num_color);
+        let (color2row, new2old) = crate::coloring::color2elem(&node2color, num_color);
+        self.color2row = color2row;
+        self.new2old = new2old;
+        self.sparse = a.permute(&self.new2old);
+        self.ilu.initialize_ilu0(&self.sparse);
+    }
+
+    /// copy the values of the matrix with the same pattern as the one given to `symbolic_initialization`
+    pub fn set_value(&mut self, a: &crate::sparse_square::Matrix<T>) {
+        assert_eq!(a.num_blk, self.new2old.len());
+        self.sparse = a.permute(&self.new2old);
+        crate::sparse_ilu::copy_value(&mut self.ilu, &self.sparse);
+    }
+
+    pub fn num_color(&self) -> usize {
+        self.color2row.len() - 1
+    }
+}
+
+impl<T> Default for MultiColor<T>
+where
+    T: 'static
+        + Copy
+        + num_traits::Zero
+        + std::default::Default
+        + std::ops::AddAssign
+        + std::fmt::Display,
+    f32: AsPrimitive<T>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// run `f(i_row, &mut vals[i])` for the rows `i_row0..i_row0+vals.len()`, split into the parts of `part2row`
+fn par_for_rows<T, F>(vals: &mut [T], i_row0: usize, part2row: &[usize], f: &F)
+where
+    T: Send,
+    F: Fn(usize, &mut T) + Sync,
+{
+    if part2row.len() <= 2 {
+        for (i0, v) in vals.iter_mut().enumerate() {
+            f(i_row0 + i0, v);
+        }
+        return;
+    }
+    std::thread::scope(|s| {
+        let mut rest = vals;
+        for i_part in 0..part2row.len() - 1 {
+            let (vals_part, tail) = rest.split_at_mut(part2row[i_part + 1] - part2row[i_part]);
+            rest = tail;
+            let i_row1 = i_row0 + part2row[i_part];
+            s.spawn(move || {
+                for (i0, v) in vals_part.iter_mut().enumerate() {
+                    f(i_row1 + i0, v);
+                }
+            });
+        }
+    });
+}
+
+/// ILU(0) factorization of `mc.ilu` where the rows of each color are factorized concurrently
+/// * `num_thread` - number of the threads. Computed on the current thread if it is one
+pub fn par_decompose<T>(mc: &mut MultiColor<T>, num_thread: usize)
+where
+    T: 'static
+        + Copy
+        + std::ops::Mul<Output = T>
+        + std::ops::SubAssign
+        + std::ops::Div<Output = T>
+        + Send
+        + Sync,
+    f32: AsPrimitive<T>,
+{
+    let ilu = &mut mc.ilu;
+    let (row2idx, idx2col, row2idx_dia) = (&ilu.row2idx, &ilu.idx2col, &ilu.row2idx_dia);
+    for i_color in 0..mc.color2row.len() - 1 {
+        let (i_row0, i_row1) = (mc.color2row[i_color], mc.color2row[i_color + 1]);
+        // the rows of the previous colors are already factorized
+        let (idx2val_prev, idx2val_cur) = ilu.idx2val.split_at_mut(row2idx[i_row0]);
+        let idx2val_cur = &mut idx2val_cur[..row2idx[i_row1] - row2idx[i_row0]];
+        let row2val_cur = &mut ilu.row2val[i_row0..i_row1];
+        let part2row = crate::sparse_square::partition_rows(&row2idx[i_row0..=i_row1], num_thread);
+        // the values of each row are the off-diagonal entries and the diagonal entry
+        let mut rows: Vec<(&mut [T], &mut T)> = Vec::with_capacity(i_row1 - i_row0);
+        let mut rest = idx2val_cur;
+        for (i_row, dia) in (i_row0..i_row1).zip(row2val_cur.iter_mut()) {
+            let (row_vals, tail) = rest.split_at_mut(row2idx[i_row + 1] - row2idx[i_row]);
+            rest = tail;
+            rows.push((row_vals, dia));
+        }
+        let idx2val_prev: &[T] = idx2val_prev;
+        par_for_rows(&mut rows, i_row0, &part2row, &|i_row, (row_vals, dia)| {
+            let idx0 = row2idx[i_row];
+            let cols = &idx2col[idx0..row2idx[i_row + 1]];
+            // [L] * [D^-1*U]
+            for ik_idx in idx0..row2idx_dia[i_row] {
+                let k_colrow = idx2col[ik_idx];
+                assert!(k_colrow < i_row0);
+                let ik_val = row_vals[ik_idx - idx0];
+                for kj_idx in row2idx_dia[k_colrow]..row2idx[k_colrow + 1] {
+                    let j_col = idx2col[kj_idx];
+                    let kj_val = idx2val_prev[kj_idx];
+                    if j_col == i_row {
+                        **dia -= ik_val * kj_val;
+                    } else if let Ok(ij0) = cols.binary_search(&j_col) {
+                        row_vals[ij0] -= ik_val * kj_val;
+                    }
+                }
+            }
+            **dia = 1_f32.as_() / **dia;
+            // [U] = [1/D][U]
+            for v in row_vals[row2idx_dia[i_row] - idx0..].iter_mut() {
+                *v = *v * **dia;
+            }
+        });
+    }
+}
+
+/// multi-threaded ILU(0) preconditioning where the rows of each color are solved concurrently
+/// * `vec` - vector in the original ordering. Overwritten by `[LU]^{-1}{vec}`
+/// * `num_thread` - number of the threads. Computed on the current thread if it is one
+pub fn par_solve_preconditioning_vec<T>(vec: &mut [T], mc: &MultiColor<T>, num_thread: usize)
+where
+    T: Copy + std::ops::Mul<Output = T> + std::ops::SubAssign + Send + Sync,
+{
+    let ilu = &mc.ilu;
+    assert_eq!(vec.len(), ilu.num_blk);
+    let mut vec_new = crate::ordering::permute_vec(&mc.new2old, vec);
+    let num_color = mc.color2row.len() - 1;
+    // forward
+    for i_color in 0..num_color {
+        let (i_row0, i_row1) = (mc.color2row[i_color], mc.color2row[i_color + 1]);
+        let part2row =
+            crate::sparse_square::partition_rows(&ilu.row2idx[i_row0..=i_row1], num_thread);
+        let (prev, cur) = vec_new.split_at_mut(i_row0);
+        let prev: &[T] = prev;
+        par_for_rows(
+            &mut cur[..i_row1 - i_row0],
+            i_row0,
+            &part2row,
+            &|i_row, v| {
+                let idxs = ilu.row2idx[i_row]..ilu.row2idx_dia[i_row];
+                for (&a, &j_col) in ilu.idx2val[idxs.clone()].iter().zip(&ilu.idx2col[idxs]) {
+                    *v -= a * prev[j_col];
+                }
+                *v = ilu.row2val[i_row] * *v;
+            },
+        );
+    }
+    // backward
+    for i_color in (0..num_color).rev() {
+        let (i_row0, i_row1) = (mc.color2row[i_color], mc.color2row[i_color + 1]);
+        let part2row =
+            crate::sparse_square::partition_rows(&ilu.row2idx[i_row0..=i_row1], num_thread);
+        let (cur, next) = vec_new[i_row0..].split_at_mut(i_row1 - i_row0);
+        let next: &[T] = next;
+        par_for_rows(cur, i_row0, &part2row, &|i_row, v| {
+            let idxs = ilu.row2idx_dia[i_row]..ilu.row2idx[i_row + 1];
+            for (&a, &j_col) in ilu.idx2val[idxs.clone()].iter().zip(&ilu.idx2col[idxs]) {
+                *v -= a * next[j_col - i_row1];
+            }
+        });
+    }
+    let vec_old = crate::ordering::unpermute_vec(&mc.new2old, &vec_new);
+    vec.copy_from_slice(&vec_old);
+}
+
+/// multicolor Gauss-Seidel iterations for `[A]{x} = {b}`, where the rows of each color are relaxed concurrently
+/// * `x_vec` - initial guess and the result in the original ordering
+/// * `b_vec` - right-hand side in the original ordering
+/// * `num_iteration` - number of the sweeps over all the colors
+/// * `num_thread` - number of the threads. Computed on the current thread if it is one
+pub fn par_gauss_seidel<T>(
+    x_vec: &mut [T],
+    mc: &MultiColor<T>,
+    b_vec: &[T],
+    num_iteration: usize,
+    num_thread: usize,
+) where
+    T: Copy
+        + std::ops::Mul<Output = T>
+        + std::ops::SubAssign
+        + std::ops::Div<Output = T>
+        + Send
+        + Sync,
+{
+    let a = &mc.sparse;
+    assert_eq!(x_vec.len(), a.num_blk);
+    assert_eq!(b_vec.len(), a.num_blk);
+    let mut x_new = crate::ordering::permute_vec(&mc.new2old, x_vec);
+    let b_new = crate::ordering::permute_vec(&mc.new2old, b_vec);
+    let num_color = mc.color2row.len() - 1;
+    let color2part: Vec<Vec<usize>> = (0..num_color)
+        .map(|i_color| {
+            let (i_row0, i_row1) = (mc.color2row[i_color], mc.color2row[i_color + 1]);
+            crate::sparse_square::partition_rows(&a.row2idx[i_row0..=i_row1], num_thread)
+        })
+        .collect();
+    for _ in 0..num_iteration {
+        for (i_color, part2row) in color2part.iter().enumerate() {
+            let (i_row0, i_row1) = (mc.color2row[i_color], mc.color2row[i_color + 1]);
+            let (prev, rest) = x_new.split_at_mut(i_row0);
+            let (cur, next) = rest.split_at_mut(i_row1 - i_row0);
+            let (prev, next): (&[T], &[T]) = (prev, next);
+            par_for_rows(cur, i_row0, part2row, &|i_row, x| {
+                let mut v = b_new[i_row];
+                let idxs = a.row2idx[i_row]..a.row2idx[i_row + 1];
+                for (&a_ij, &j_col) in a.idx2val[idxs.clone()].iter().zip(&a.idx2col[idxs]) {
+                    let x_j = if j_col < i_row0 {
+                        prev[j_col]
+                    } else {
+                        assert!(j_col >= i_row1);
+                        next[j_col - i_row1]
+                    };
+                    v -= a_ij * x_j;
+                }
+                *x = v / a.row2val[i_row];
+            });
+        }
+    }
+    let x_old = crate::ordering::unpermute_vec(&mc.new2old, &x_new);
+    x_vec.copy_from_slice(&x_old);
+}
+
+#[test]
+fn test_multicolor() {
+    let sparse = crate::sparse_square::laplacian_grid(40, 30, 0.1);
+    let num_blk = sparse.num_blk;
+    let b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    for num_color in [2, 4, 8] {
+        let mut mc = MultiColor::<f64>::new();
+        mc.symbolic_initialization(&sparse, num_color);
+        assert_eq!(mc.num_color(), num_color);
+        // the rows of the same color are not coupled
+        for i_color in 0..mc.num_color() {
+            for i_row in mc.color2row[i_color]..mc.color2row[i_color + 1] {
+                for &j_col in
+                    &mc.sparse.idx2col[mc.sparse.row2idx[i_row]..mc.sparse.row2idx[i_row + 1]]
+                {
+                    assert!(j_col < mc.color2row[i_color] || j_col >= mc.color2row[i_color + 1]);
+                }
+            }
+        }
+        mc.set_value(&sparse);
+        // serial ILU(0) of the reordered matrix
+        let mut ilu = crate::sparse_ilu::Preconditioner::<f64>::new();
+        ilu.initialize_ilu0(&mc.sparse);
+        crate::sparse_ilu::copy_value(&mut ilu, &mc.sparse);
+        crate::sparse_ilu::decompose(&mut ilu);
+        let mut vec0 = crate::ordering::permute_vec(&mc.new2old, &b_vec);
+        crate::sparse_ilu::solve_preconditioning_vec(&mut vec0, &ilu);
+        let vec0 = crate::ordering::unpermute_vec(&mc.new2old, &vec0);
+        let mut x_vecs = vec![];
+        for num_thread in [1, 4] {
+            mc.set_value(&sparse);
+            par_decompose(&mut mc, num_thread);
+            for (v0, v1) in ilu.idx2val.iter().zip(&mc.ilu.idx2val) {
+                assert!((v0 - v1).abs() < 1.0e-12);
+            }
+            let mut vec1 = b_vec.clone();
+            par_solve_preconditioning_vec(&mut vec1, &mc, num_thread);
+            for i_blk in 0..num_blk {
+                assert!((vec0[i_blk] - vec1[i_blk]).abs() < 1.0e-12);
+            }
+            let mut x_vec = vec![0.; num_blk];
+            par_gauss_seidel(&mut x_vec, &mc, &b_vec, 100, num_thread);
+            x_vecs.push(x_vec);
+        }
+        // deterministic regardless of the number of the threads
+        assert_eq!(x_vecs[0], x_vecs[1]);
+        // the Gauss-Seidel iterations converge
+        let mut r_vec = b_vec.clone();
+        crate::sparse_square::mult_vec(&mut r_vec, 1., -1., &sparse, &x_vecs[0]);
+        let norm_r = r_vec.iter().map(|r| r * r).sum::<f64>().sqrt();
+        let norm_b = b_vec.iter().map(|b| b * b).sum::<f64>().sqrt();
+        assert!(norm_r < norm_b * 1.0e-3);
+    }
+}
+
+#[test]
+fn test_multicolor_irregular() {
+    // unsymmetric and badly scaled. More colors than requested are necessary for the high degree rows
+    let sparse = crate::sparse_square::irregular_test_matrix(1000, false, 100.);
+    let num_blk = sparse.num_blk;
+    let b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    let mut mc = MultiColor::<f64>::new();
+    mc.symbolic_initialization(&sparse, 2);
+    assert!(mc.num_color() > 2);
+    let (sym_row2idx, sym_idx2col) =
+        crate::ordering::symmetrize_pattern(&mc.sparse.row2idx, &mc.sparse.idx2col);
+    for i_color in 0..mc.num_color() {
+        for i_row in mc.color2row[i_color]..mc.color2row[i_color + 1] {
+            for &j_col in &sym_idx2col[sym_row2idx[i_row]..sym_row2idx[i_row + 1]] {
+                assert!(j_col < mc.color2row[i_color] || j_col >= mc.color2row[i_color + 1]);
+            }
+        }
+    }
+    mc.set_value(&sparse);
+    let mut ilu = crate::sparse_ilu::Preconditioner::<f64>::new();
+    ilu.initialize_ilu0(&mc.sparse);
+    crate::sparse_ilu::copy_value(&mut ilu, &mc.sparse);
+    crate::sparse_ilu::decompose(&mut ilu);
+    let mut vec0 = crate::ordering::permute_vec(&mc.new2old, &b_vec);
+    crate::sparse_ilu::solve_preconditioning_vec(&mut vec0, &ilu);
+    let vec0 = crate::ordering::unpermute_vec(&mc.new2old, &vec0);
+    let mut x_vecs = vec![];
+    for num_thread in [1, 3] {
+        mc.set_value(&sparse);
+        par_decompose(&mut mc, num_thread);
+        let mut vec1 = b_vec.clone();
+        par_solve_preconditioning_vec(&mut vec1, &mc, num_thread);
+        for i_blk in 0..num_blk {
+            assert!((vec0[i_blk] - vec1[i_blk]).abs() < 1.0e-12 * vec0[i_blk].abs().max(1.));
+        }
+        let mut x_vec = vec![0.; num_blk];
+        par_gauss_seidel(&mut x_vec, &mc, &b_vec, 100, num_thread);
+        x_vecs.push(x_vec);
+    }
+    assert_eq!(x_vecs[0], x_vecs[1]);
+    // Gauss-Seidel converges for the diagonally dominant matrix regardless of the scaling
+    let err = crate::sparse_square::componentwise_backward_error(&sparse, &x_vecs[0], &b_vec);
+    assert!(err < 1.0e-8, "{}", err);
+}
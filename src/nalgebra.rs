@@ -1,3 +1,5 @@
 //! modules dependent on the nalgebra library
 
+pub mod block_conjugate_gradient;
+pub mod block_jacobi;
 pub mod block_sparse_square;
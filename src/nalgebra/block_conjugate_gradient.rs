@@ -0,0 +1,232 @@
+//! block conjugate gradient method for multiple right-hand sides
+//!
+//! The sparse matrix is the scalar `sparse_square::Matrix`, but this module is placed here
+//! because the small dense `num_rhs x num_rhs` systems of each iteration
+//! (`[P]^T[A][P]` and its Cholesky factorization or pseudo inverse) are solved with nalgebra.
+
+use crate::sparse_square;
+use crate::stopping_criterion::{Criterion, Status};
+
+/// `[a]^T * [b]` where `[a]` and `[b]` are row-major dense matrices with the same number of rows
+fn mult_transpose<T>(a: &[T], num_col_a: usize, b: &[T], num_col_b: usize) -> nalgebra::DMatrix<T>
+where
+    T: nalgebra::RealField + Copy,
+{
+    let num_row = a.len() / num_col_a;
+    assert_eq!(b.len(), num_row * num_col_b);
+    let mut c = nalgebra::DMatrix::<T>::zeros(num_col_a, num_col_b);
+    for i_row in 0..num_row {
+        for p in 0..num_col_a {
+            let a_ip = a[i_row * num_col_a + p];
+            for q in 0..num_col_b {
+                c[(p, q)] += a_ip * b[i_row * num_col_b + q];
+            }
+        }
+    }
+    c
+}
+
+/// extract the columns `cols` of the row-major dense matrix
+fn gather_columns<T>(a: &[T], num_col: usize, cols: &[usize]) -> Vec<T>
+where
+    T: Copy,
+{
+    let num_row = a.len() / num_col;
+    let mut b = Vec::<T>::with_capacity(num_row * cols.len());
+    for i_row in 0..num_row {
+        for &j_col in cols {
+            b.push(a[i_row * num_col + j_col]);
+        }
+    }
+    b
+}
+
+/// `[a] * [w]` where `[a]` is a row-major dense matrix. The result is also row-major
+fn mult_small<T>(a: &[T], num_col_a: usize, w: &nalgebra::DMatrix<T>) -> Vec<T>
+where
+    T: nalgebra::RealField + Copy,
+{
+    let num_row = a.len() / num_col_a;
+    assert_eq!(w.nrows(), num_col_a);
+    let num_col_b = w.ncols();
+    let mut b = vec![T::zero(); num_row * num_col_b];
+    for i_row in 0..num_row {
+        for p in 0..num_col_a {
+            let a_ip = a[i_row * num_col_a + p];
+            for q in 0..num_col_b {
+                b[i_row * num_col_b + q] += a_ip * w[(p, q)];
+            }
+        }
+    }
+    b
+}
+
+/// solve `[mat] * [x_mat] = [b_mat]` for multiple right-hand sides
+/// using the block Conjugate Gradient (CG) method of O'Leary.
+/// The search directions of all the columns share the sparse matrix multiplication,
+/// and the columns that satisfy `criterion` are removed from the block.
+/// The directions are A-orthonormalized in each iteration and the (nearly) linearly dependent ones
+/// are dropped (breakdown-free block CG of Ji and Li), so the right-hand sides may be linearly dependent.
+/// * `x_mat` - solution (output) with the same layout as `b_mat`
+/// * `b_mat` - right-hand sides as a row-major `num_blk x num_rhs` matrix (same layout as `sparse_square::mult_mat`)
+/// * `criterion` - stopping criterion evaluated for each column separately
+/// * return - history of the convergence ratio `||r_k|| / ||r_0||` for each column
+pub fn block_conjugate_gradient<T>(
+    x_mat: &mut Vec<T>,
+    b_mat: &[T],
+    criterion: &Criterion<T>,
+    max_iteration: usize,
+    mat: &sparse_square::Matrix<T>,
+) -> Vec<Vec<T>>
+where
+    T: nalgebra::RealField + num_traits::Float + Copy,
+{
+    let num_blk = mat.num_blk;
+    if num_blk == 0 {
+        x_mat.clear();
+        return vec![];
+    }
+    let num_rhs = b_mat.len() / num_blk;
+    assert_eq!(b_mat.len(), num_blk * num_rhs);
+    x_mat.clear();
+    x_mat.resize(b_mat.len(), T::zero());
+    let mut conv_hist = vec![Vec::<T>::new(); num_rhs];
+    let sqnorm_res_ini: Vec<T> = (0..num_rhs)
+        .map(|j| {
+            (0..num_blk).fold(T::zero(), |sum, i| {
+                sum + b_mat[i * num_rhs + j] * b_mat[i * num_rhs + j]
+            })
+        })
+        .collect();
+    let mut statuses: Vec<Status<T>> = sqnorm_res_ini
+        .iter()
+        .map(|&v| Status::new(v, v, v))
+        .collect();
+    // active columns
+    let mut act: Vec<usize> = (0..num_rhs)
+        .filter(|&j| sqnorm_res_ini[j] != T::zero() && !criterion.is_satisfied(&statuses[j]))
+        .collect();
+    let mut r_mat = gather_columns(b_mat, num_rhs, &act);
+    let mut p_mat = r_mat.clone();
+    let mut ap_mat = vec![T::zero(); p_mat.len()];
+    for _iitr in 0..max_iteration {
+        if act.is_empty() {
+            break;
+        }
+        let num_act = act.len();
+        let num_dir = p_mat.len() / num_blk;
+        ap_mat.resize(p_mat.len(), T::zero());
+        // [AP] = [A][P]
+        sparse_square::mult_mat(&mut ap_mat, T::zero(), T::one(), mat, &p_mat);
+        // A-orthonormalize the directions, removing the (nearly) linearly dependent ones
+        let ptap = mult_transpose(&p_mat, num_dir, &ap_mat, num_dir);
+        let eig = ptap.symmetric_eigen();
+        let eig_max = eig.eigenvalues.amax();
+        let eps = num_traits::Float::sqrt(<T as num_traits::Float>::epsilon()) * eig_max;
+        let dirs: Vec<usize> = (0..num_dir).filter(|&q| eig.eigenvalues[q] > eps).collect();
+        if dirs.is_empty() {
+            break;
+        }
+        let w = nalgebra::DMatrix::<T>::from_fn(num_dir, dirs.len(), |p, q| {
+            eig.eigenvectors[(p, dirs[q])] / num_traits::Float::sqrt(eig.eigenvalues[dirs[q]])
+        });
+        p_mat = mult_small(&p_mat, num_dir, &w);
+        ap_mat = mult_small(&ap_mat, num_dir, &w);
+        // now [P]^T[A][P] = [I]
+        let num_dir = dirs.len();
+        // alpha = [P]^T[R]
+        let alpha = mult_transpose(&p_mat, num_dir, &r_mat, num_act);
+        // [X] = [X] + [P] alpha, [R] = [R] - [AP] alpha
+        for i_blk in 0..num_blk {
+            for (jj, &j) in act.iter().enumerate() {
+                let mut dx = T::zero();
+                let mut dr = T::zero();
+                for q in 0..num_dir {
+                    dx += p_mat[i_blk * num_dir + q] * alpha[(q, jj)];
+                    dr += ap_mat[i_blk * num_dir + q] * alpha[(q, jj)];
+                }
+                x_mat[i_blk * num_rhs + j] += dx;
+                r_mat[i_blk * num_act + jj] -= dr;
+            }
+        }
+        // converge judgement for each column
+        let mut keep = Vec::<usize>::with_capacity(num_act);
+        for (jj, &j) in act.iter().enumerate() {
+            let sqnorm_res = (0..num_blk).fold(T::zero(), |sum, i| {
+                sum + r_mat[i * num_act + jj] * r_mat[i * num_act + jj]
+            });
+            let energy = alpha.column(jj).norm_squared();
+            statuses[j].push(sqnorm_res, sqnorm_res, energy);
+            conv_hist[j].push(num_traits::Float::sqrt(sqnorm_res / sqnorm_res_ini[j]));
+            if sqnorm_res != T::zero() && !criterion.is_satisfied(&statuses[j]) {
+                keep.push(jj);
+            }
+        }
+        let r_mat_new = gather_columns(&r_mat, num_act, &keep);
+        // beta = -[AP]^T[R]
+        let beta = -mult_transpose(&ap_mat, num_dir, &r_mat_new, keep.len());
+        // [P] = [R] + [P] beta
+        let mut p_mat_new = r_mat_new.clone();
+        for i_blk in 0..num_blk {
+            for jj in 0..keep.len() {
+                let mut dp = T::zero();
+                for q in 0..num_dir {
+                    dp += p_mat[i_blk * num_dir + q] * beta[(q, jj)];
+                }
+                p_mat_new[i_blk * keep.len() + jj] += dp;
+            }
+        }
+        act = keep.iter().map(|&jj| act[jj]).collect();
+        r_mat = r_mat_new;
+        p_mat = p_mat_new;
+    }
+    conv_hist
+}
+
+#[test]
+fn test_block_conjugate_gradient() {
+    for sparse in [
+        crate::sparse_square::laplacian_grid(16, 12, 0.01),
+        crate::sparse_square::irregular_test_matrix(200, true, 10.),
+    ] {
+        let num_blk = sparse.num_blk;
+        let num_rhs = 4;
+        let mut b_mat = vec![0_f64; num_blk * num_rhs];
+        for i_blk in 0..num_blk {
+            b_mat[i_blk * num_rhs] = (i_blk as f64).sin();
+            b_mat[i_blk * num_rhs + 1] = (i_blk as f64 * 0.3).cos();
+            // linearly dependent on the first two columns
+            b_mat[i_blk * num_rhs + 2] = b_mat[i_blk * num_rhs] + b_mat[i_blk * num_rhs + 1];
+            b_mat[i_blk * num_rhs + 3] = if i_blk == 7 { 1. } else { 0. };
+        }
+        let mut x_mat = Vec::<f64>::new();
+        let conv_hist = block_conjugate_gradient(
+            &mut x_mat,
+            &b_mat,
+            &Criterion::RelativeResidual(1.0e-8),
+            1000,
+            &sparse,
+        );
+        let mut r_mat = b_mat.clone();
+        sparse_square::mult_mat(&mut r_mat, 1., -1., &sparse, &x_mat);
+        for j in 0..num_rhs {
+            assert!(*conv_hist[j].last().unwrap() < 1.0e-8);
+            let norm_b = (0..num_blk).fold(0., |s, i| {
+                s + b_mat[i * num_rhs + j] * b_mat[i * num_rhs + j]
+            });
+            let norm_r = (0..num_blk).fold(0., |s, i| s + r_mat[i * num_rhs + j].powi(2));
+            assert!((norm_r / norm_b).sqrt() < 1.0e-7);
+        }
+    }
+    // empty matrix
+    let sparse = crate::sparse_square::laplacian_grid(0, 0, 0.1);
+    let mut x_mat = vec![1.];
+    let conv_hist = block_conjugate_gradient(
+        &mut x_mat,
+        &[],
+        &Criterion::RelativeResidual(1.0e-8),
+        10,
+        &sparse,
+    );
+    assert!(conv_hist.is_empty() && x_mat.is_empty());
+}
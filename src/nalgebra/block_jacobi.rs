@@ -0,0 +1,184 @@
+//! block Jacobi preconditioner and preconditioned conjugate gradient method
+//! for the block sparse matrix whose blocks are `nalgebra::SMatrix<T, N, N>` (e.g., `nalgebra::Matrix3<T>`)
+
+use crate::sparse_square;
+use crate::stopping_criterion::Criterion;
+
+/// inverse of the diagonal blocks
+pub struct BlockJacobi<T, const N: usize> {
+    pub row2inv: Vec<nalgebra::SMatrix<T, N, N>>,
+}
+
+impl<T, const N: usize> BlockJacobi<T, N>
+where
+    T: nalgebra::RealField + Copy,
+{
+    pub fn new() -> Self {
+        BlockJacobi {
+            row2inv: Vec::<nalgebra::SMatrix<T, N, N>>::new(),
+        }
+    }
+}
+
+impl<T, const N: usize> Default for BlockJacobi<T, N>
+where
+    T: nalgebra::RealField + Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// compute the inverse of the diagonal blocks `row2val` of the matrix
+pub fn decompose<T, const N: usize>(
+    bj: &mut BlockJacobi<T, N>,
+    a_mat: &sparse_square::Matrix<nalgebra::SMatrix<T, N, N>>,
+) -> Result<(), String>
+where
+    T: nalgebra::RealField + Copy,
+{
+    bj.row2inv.clear();
+    bj.row2inv.reserve(a_mat.num_blk);
+    for (i_row, a_ii) in a_mat.row2val.iter().enumerate() {
+        match a_ii.try_inverse() {
+            Some(inv) => bj.row2inv.push(inv),
+            None => return Err(format!("singular diagonal block at row {}", i_row)),
+        }
+    }
+    Ok(())
+}
+
+/// `{vec} <- [D]^{-1}{vec}` where `[D]` is the block diagonal of the matrix
+pub fn solve_preconditioning_vec<T, const N: usize>(
+    vec: &mut [nalgebra::SVector<T, N>],
+    bj: &BlockJacobi<T, N>,
+) where
+    T: nalgebra::RealField + Copy,
+{
+    assert_eq!(vec.len(), bj.row2inv.len());
+    for (v, inv) in vec.iter_mut().zip(bj.row2inv.iter()) {
+        *v = inv * *v;
+    }
+}
+
+/// solve the block sparse linear system using the conjugate gradient method with the block Jacobi preconditioner.
+/// The vectors are flattened and solved by `solver_sparse::preconditioned_conjugate_gradient_with_operators`
+/// * `r_vec` - rhs vector as input, residual vector as output
+/// * `x_vec` - solution (output)
+/// * return - history of the residual norm `||r_k||` (the first element is `||r_0||`)
+pub fn preconditioned_conjugate_gradient<T, const N: usize>(
+    r_vec: &mut [nalgebra::SVector<T, N>],
+    x_vec: &mut Vec<nalgebra::SVector<T, N>>,
+    criterion: &Criterion<T>,
+    max_nitr: usize,
+    mat: &sparse_square::Matrix<nalgebra::SMatrix<T, N, N>>,
+    bj: &BlockJacobi<T, N>,
+) -> Vec<T>
+where
+    T: nalgebra::RealField + num_traits::Float + Copy,
+{
+    let num_blk = mat.num_blk;
+    assert_eq!(r_vec.len(), num_blk);
+    assert_eq!(bj.row2inv.len(), num_blk);
+    let mut r_flat: Vec<T> = r_vec.iter().flat_map(|v| v.iter().copied()).collect();
+    let (mut x_flat, mut pr_flat, mut p_flat) = (vec![], vec![], vec![]);
+    // buffers of the block vectors for the matrix-vector product
+    let mut x_blk = vec![nalgebra::SVector::<T, N>::zeros(); num_blk];
+    let mut y_blk = x_blk.clone();
+    let conv_hist = crate::solver_sparse::preconditioned_conjugate_gradient_with_operators(
+        &mut r_flat,
+        &mut x_flat,
+        &mut pr_flat,
+        &mut p_flat,
+        criterion,
+        max_nitr,
+        |y: &mut [T], x: &[T]| {
+            for (x_i, x) in x_blk.iter_mut().zip(x.chunks(N)) {
+                x_i.copy_from_slice(x);
+            }
+            super::block_sparse_square::par_gemv_for_block_sparse_matrix_nalgebra(
+                &mut y_blk,
+                T::zero(),
+                T::one(),
+                mat,
+                &x_blk,
+                1,
+            );
+            for (y, y_i) in y.chunks_mut(N).zip(&y_blk) {
+                y.copy_from_slice(y_i.as_slice());
+            }
+        },
+        |v: &mut [T]| {
+            for (v, inv) in v.chunks_mut(N).zip(&bj.row2inv) {
+                let w = inv * nalgebra::SVector::<T, N>::from_column_slice(v);
+                v.copy_from_slice(w.as_slice());
+            }
+        },
+        |_, _, _| std::ops::ControlFlow::Continue(()),
+    );
+    for (r, r_i) in r_flat.chunks(N).zip(r_vec.iter_mut()) {
+        r_i.copy_from_slice(r);
+    }
+    x_vec.clear();
+    x_vec.extend(
+        x_flat
+            .chunks(N)
+            .map(nalgebra::SVector::<T, N>::from_column_slice),
+    );
+    conv_hist
+}
+
+#[test]
+fn test_block_jacobi() {
+    type M3 = nalgebra::Matrix3<f64>;
+    type V3 = nalgebra::Vector3<f64>;
+    let norm = |v: &[V3]| v.iter().map(|a| a.norm_squared()).sum::<f64>().sqrt();
+    for (i_case, lap) in [
+        crate::sparse_square::laplacian_grid(12, 10, 0.1),
+        crate::sparse_square::irregular_test_matrix(150, true, 10.),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        // [A] = [L] \otimes [M] where [M] is an ill-conditioned 3x3 matrix
+        let num_blk = lap.num_blk;
+        let m = M3::new(1000., 1., 0., 1., 1., 0.5, 0., 0.5, 10.);
+        let mut sparse = crate::sparse_square::Matrix::<M3>::new();
+        sparse.symbolic_initialization(&lap.row2idx, &lap.idx2col);
+        for (a, &l) in sparse.idx2val.iter_mut().zip(&lap.idx2val) {
+            *a = m * l;
+        }
+        for (a, &l) in sparse.row2val.iter_mut().zip(&lap.row2val) {
+            *a = m * l;
+        }
+        let b_vec: Vec<V3> = (0..num_blk)
+            .map(|i| V3::new((i as f64).sin(), (i as f64).cos(), 1.))
+            .collect();
+        let mut bj = BlockJacobi::<f64, 3>::new();
+        decompose(&mut bj, &sparse).unwrap();
+        let mut identity = BlockJacobi::<f64, 3>::new();
+        identity.row2inv = vec![M3::identity(); num_blk];
+        let criterion = Criterion::RelativeResidual(1.0e-8);
+        let mut num_itrs = vec![];
+        for pre in [&bj, &identity] {
+            let mut r_vec = b_vec.clone();
+            let mut x_vec = Vec::<V3>::new();
+            let conv_hist = preconditioned_conjugate_gradient(
+                &mut r_vec, &mut x_vec, &criterion, 1000, &sparse, pre,
+            );
+            // check the residual
+            let mut y_vec = b_vec.clone();
+            super::block_sparse_square::gemv_for_block_sparse_matrix_nalgebra(
+                &mut y_vec, 1., -1., &sparse, &x_vec,
+            );
+            assert!((norm(&y_vec) - norm(&r_vec)).abs() < norm(&b_vec) * 1.0e-7);
+            if i_case == 0 || std::ptr::eq(pre, &bj) {
+                assert!(norm(&y_vec) < norm(&b_vec) * 1.0e-7);
+            }
+            num_itrs.push(conv_hist.len());
+        }
+        // the block Jacobi preconditioner removes the ill-conditioning of [M] and the scaling.
+        // Without it, CG does not converge within the iterations for the badly scaled matrix
+        assert!(num_itrs[0] * 2 < num_itrs[1]);
+    }
+}
@@ -35,6 +35,57 @@ pub fn gemv_for_block_sparse_matrix_nalgebra<
     }
 }
 
+/// multi-threaded version of `gemv_for_block_sparse_matrix_nalgebra`,
+/// where the rows are partitioned by `sparse_square::partition_rows`
+/// * `num_thread` - number of the threads. Computed on the current thread if it is one
+pub fn par_gemv_for_block_sparse_matrix_nalgebra<
+    T: nalgebra::RealField + Copy,
+    R: nalgebra::Dim,
+    C: nalgebra::Dim,
+    SVECM: nalgebra::StorageMut<T, R, C> + Send,
+    SVEC: nalgebra::Storage<T, R, C> + Sync,
+    SMAT: nalgebra::Storage<T, R, R> + Sync,
+>(
+    y_vec: &mut [nalgebra::Matrix<T, R, C, SVECM>],
+    beta: T,
+    alpha: T,
+    a_mat: &crate::sparse_square::Matrix<nalgebra::Matrix<T, R, R, SMAT>>,
+    x_vec: &[nalgebra::Matrix<T, R, C, SVEC>],
+    num_thread: usize,
+) {
+    assert_eq!(y_vec.len(), a_mat.num_blk);
+    assert_eq!(x_vec.len(), a_mat.num_blk);
+    let gemv_rows = |y_rows: &mut [nalgebra::Matrix<T, R, C, SVECM>], i_row0: usize| {
+        for (i0, y) in y_rows.iter_mut().enumerate() {
+            let i_row = i_row0 + i0;
+            y.scale_mut(beta);
+            for idx in a_mat.row2idx[i_row]..a_mat.row2idx[i_row + 1] {
+                y.gemm(
+                    alpha,
+                    &a_mat.idx2val[idx],
+                    &x_vec[a_mat.idx2col[idx]],
+                    T::one(),
+                );
+            }
+            y.gemm(alpha, &a_mat.row2val[i_row], &x_vec[i_row], T::one());
+        }
+    };
+    if num_thread <= 1 {
+        gemv_rows(y_vec, 0);
+        return;
+    }
+    let part2row = crate::sparse_square::partition_rows(&a_mat.row2idx, num_thread);
+    std::thread::scope(|scope| {
+        let mut y_rest = y_vec;
+        for rows in part2row.windows(2) {
+            let (y_rows, tail) = y_rest.split_at_mut(rows[1] - rows[0]);
+            y_rest = tail;
+            let i_row0 = rows[0];
+            scope.spawn(move || gemv_rows(y_rows, i_row0));
+        }
+    });
+}
+
 #[test]
 fn test_block33() {
     type MAT = nalgebra::Matrix3<f32>;
@@ -61,3 +112,37 @@ fn test_block33() {
     lhs.resize(nblk, Default::default());
     gemv_for_block_sparse_matrix_nalgebra(&mut lhs, 1.0, 1.0, &sparse, &rhs);
 }
+
+#[test]
+fn test_par_gemv() {
+    type M3 = nalgebra::Matrix3<f64>;
+    type V3 = nalgebra::Vector3<f64>;
+    let lap = crate::sparse_square::laplacian_grid(9, 7, 0.1);
+    let num_blk = lap.num_blk;
+    let mut sparse = crate::sparse_square::Matrix::<M3>::new();
+    sparse.symbolic_initialization(&lap.row2idx, &lap.idx2col);
+    for (idx, a) in sparse.idx2val.iter_mut().enumerate() {
+        *a = M3::from_fn(|i, j| lap.idx2val[idx] * (1. + (idx + 3 * i + j) as f64 * 0.1).sin());
+    }
+    for (i_row, a) in sparse.row2val.iter_mut().enumerate() {
+        *a = M3::from_fn(|i, j| lap.row2val[i_row] + if i == j { 1. } else { 0.2 });
+    }
+    let x_vec: Vec<V3> = (0..num_blk)
+        .map(|i| V3::new(1., i as f64, (i * i) as f64))
+        .collect();
+    let y_vec: Vec<V3> = (0..num_blk).map(|i| V3::new(i as f64, 2., 3.)).collect();
+    let mut y_vec0 = y_vec.clone();
+    gemv_for_block_sparse_matrix_nalgebra(&mut y_vec0, 0.5, 2.0, &sparse, &x_vec);
+    for num_thread in [1, 2, 5] {
+        let mut y_vec1 = y_vec.clone();
+        par_gemv_for_block_sparse_matrix_nalgebra(
+            &mut y_vec1,
+            0.5,
+            2.0,
+            &sparse,
+            &x_vec,
+            num_thread,
+        );
+        assert_eq!(y_vec0, y_vec1);
+    }
+}
@@ -0,0 +1,122 @@
+//! reordering of the rows and columns of sparse matrices.
+//! The permutation is represented as `new2old` (the `i`-th row of the reordered matrix is the `new2old[i]`-th row of the original matrix)
+//! or its inverse `old2new`.
+
+pub mod approximate_minimum_degree;
+pub mod minimum_degree;
+pub mod nested_dissection;
+pub mod reverse_cuthill_mckee;
+
+/// inverse of the permutation (`new2old` -> `old2new` or vice versa)
+pub fn inverse_permutation(new2old: &[usize]) -> Vec<usize> {
+    let mut old2new = vec![usize::MAX; new2old.len()];
+    for (i_new, &i_old) in new2old.iter().enumerate() {
+        assert_eq!(old2new[i_old], usize::MAX, "not a permutation");
+        old2new[i_old] = i_new;
+    }
+    old2new
+}
+
+/// reorder the vector to the new ordering (`vec_new[i] = vec_old[new2old[i]]`).
+/// The vector can have multiple components for each row (length `num_row * num_dim`)
+pub fn permute_vec<T>(new2old: &[usize], vec_old: &[T]) -> Vec<T>
+where
+    T: Copy,
+{
+    let num_dim = vec_old.len() / new2old.len();
+    assert_eq!(vec_old.len(), new2old.len() * num_dim);
+    new2old
+        .iter()
+        .flat_map(|&i_old| &vec_old[i_old * num_dim..(i_old + 1) * num_dim])
+        .cloned()
+        .collect()
+}
+
+/// reorder the vector back to the original ordering (`vec_old[new2old[i]] = vec_new[i]`).
+/// The vector can have multiple components for each row (length `num_row * num_dim`)
+pub fn unpermute_vec<T>(new2old: &[usize], vec_new: &[T]) -> Vec<T>
+where
+    T: Copy,
+{
+    let num_dim = vec_new.len() / new2old.len();
+    assert_eq!(vec_new.len(), new2old.len() * num_dim);
+    let mut vec_old = vec_new.to_vec();
+    for (i_new, &i_old) in new2old.iter().enumerate() {
+        vec_old[i_old * num_dim..(i_old + 1) * num_dim]
+            .copy_from_slice(&vec_new[i_new * num_dim..(i_new + 1) * num_dim]);
+    }
+    vec_old
+}
+
+/// bandwidth of the matrix, i.e., the largest `|i - j|` for the non-zero entries
+pub fn bandwidth(row2idx: &[usize], idx2col: &[usize]) -> usize {
+    let num_row = row2idx.len() - 1;
+    (0..num_row)
+        .flat_map(|i_row| {
+            idx2col[row2idx[i_row]..row2idx[i_row + 1]]
+                .iter()
+                .map(move |&j_col| i_row.abs_diff(j_col))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// non-zero pattern of `A + A^T` without the diagonal entries, with sorted columns
+/// * `row2idx`, `idx2col` - non-zero pattern of the square matrix `A` in the CRS format
+pub fn symmetrize_pattern(row2idx: &[usize], idx2col: &[usize]) -> (Vec<usize>, Vec<usize>) {
+    let num_row = row2idx.len() - 1;
+    let mut row2cols = vec![Vec::<usize>::new(); num_row];
+    for i_row in 0..num_row {
+        for &j_col in &idx2col[row2idx[i_row]..row2idx[i_row + 1]] {
+            if i_row == j_col {
+                continue;
+            }
+            row2cols[i_row].push(j_col);
+            row2cols[j_col].push(i_row);
+        }
+    }
+    let mut sym_row2idx = vec![0_usize; num_row + 1];
+    let mut sym_idx2col = Vec::<usize>::with_capacity(idx2col.len() * 2);
+    for (i_row, cols) in row2cols.iter_mut().enumerate() {
+        cols.sort();
+        cols.dedup();
+        sym_idx2col.extend_from_slice(cols);
+        sym_row2idx[i_row + 1] = sym_idx2col.len();
+    }
+    (sym_row2idx, sym_idx2col)
+}
+
+/// statistics of the fill-in of the Cholesky factor `L` of `P (A + A^T) P^T`
+/// * `num_nonzero_a` - number of the non-zero entries in the lower triangular part of `A + A^T` including the diagonal
+/// * `num_nonzero_l` - number of the non-zero entries in `L` including the diagonal
+/// * `max_column` - largest number of the non-zero entries in a column of `L`
+/// * `num_flop` - number of the multiplications in the Cholesky factorization, i.e., the sum of the squared column counts
+pub struct FillStatistics {
+    pub num_nonzero_a: usize,
+    pub num_nonzero_l: usize,
+    pub max_column: usize,
+    pub num_flop: usize,
+}
+
+impl FillStatistics {
+    /// number of the fill-in entries
+    pub fn num_fill(&self) -> usize {
+        self.num_nonzero_l - self.num_nonzero_a
+    }
+}
+
+/// statistics of the fill-in predicted by the symbolic Cholesky factorization with the ordering
+/// * `row2idx`, `idx2col` - non-zero pattern of the square matrix in the CRS format
+/// * `new2old` - ordering
+pub fn fill_statistics(row2idx: &[usize], idx2col: &[usize], new2old: &[usize]) -> FillStatistics {
+    let (sym_row2idx, sym_idx2col) = symmetrize_pattern(row2idx, idx2col);
+    let mut factor = crate::sparse_cholesky::Factorization::<f64>::new();
+    factor.symbolic_initialization_with_permutation(&sym_row2idx, &sym_idx2col, new2old.to_vec());
+    let col2cnt: Vec<usize> = factor.col2idx.windows(2).map(|v| v[1] - v[0] + 1).collect();
+    FillStatistics {
+        num_nonzero_a: sym_idx2col.len() / 2 + new2old.len(),
+        num_nonzero_l: factor.num_nonzero(),
+        max_column: col2cnt.iter().cloned().max().unwrap_or(0),
+        num_flop: col2cnt.iter().map(|c| c * c).sum(),
+    }
+}
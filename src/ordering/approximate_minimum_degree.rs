@@ -0,0 +1,209 @@
+//! approximate minimum degree (AMD) ordering
+
+/// approximate minimum degree ordering of Amestoy, Davis and Duff.
+/// The elimination is simulated on the quotient graph, where the eliminated nodes are represented as elements,
+/// and the degree of the node is replaced by its upper bound that is cheap to update.
+/// The indistinguishable nodes are merged into a supervariable and eliminated together.
+/// The pattern of `A + A^T` is used for the non-symmetric matrix.
+/// * `row2idx`, `idx2col` - non-zero pattern of the square matrix in the CRS format (the diagonal entries are ignored)
+/// * return - `new2old` and the statistics of the fill-in of the Cholesky factor with this ordering
+pub fn approximate_minimum_degree(
+    row2idx: &[usize],
+    idx2col: &[usize],
+) -> (Vec<usize>, super::FillStatistics) {
+    use std::cmp::Reverse;
+    let (sym_row2idx, sym_idx2col) = super::symmetrize_pattern(row2idx, idx2col);
+    let num_node = sym_row2idx.len() - 1;
+    // adjacent variables and elements of the variables
+    let mut var2vars: Vec<Vec<usize>> = (0..num_node)
+        .map(|i| sym_idx2col[sym_row2idx[i]..sym_row2idx[i + 1]].to_vec())
+        .collect();
+    let mut var2elems = vec![Vec::<usize>::new(); num_node];
+    // variables of the elements. The element is indexed by its pivot variable
+    let mut elem2vars = vec![Vec::<usize>::new(); num_node];
+    let mut elem2weight = vec![0_usize; num_node];
+    // number of the nodes in the supervariable (zero if merged into another one) and its members
+    let mut node2weight = vec![1_usize; num_node];
+    let mut node2members: Vec<Vec<usize>> = (0..num_node).map(|i| vec![i]).collect();
+    let mut is_element = vec![false; num_node];
+    let mut is_absorbed = vec![false; num_node];
+    let mut degree: Vec<usize> = var2vars.iter().map(|v| v.len()).collect();
+    let mut que = std::collections::BinaryHeap::<Reverse<(usize, usize)>>::new();
+    for (i_node, &d) in degree.iter().enumerate() {
+        que.push(Reverse((d, i_node)));
+    }
+    let mut mark = vec![false; num_node];
+    let mut elem2ext = vec![usize::MAX; num_node]; // |L_e \ L_p|
+    let mut new2old = Vec::<usize>::with_capacity(num_node);
+    while let Some(Reverse((d, p))) = que.pop() {
+        if node2weight[p] == 0 || is_element[p] || degree[p] != d {
+            continue; // outdated entry in the queue
+        }
+        // pattern of the new element L_p, absorbing the elements adjacent to p
+        mark[p] = true;
+        let mut lp = Vec::<usize>::new();
+        let is_live = |i: usize, is_element: &[bool], node2weight: &[usize]| {
+            !is_element[i] && node2weight[i] > 0
+        };
+        for &i in &var2vars[p] {
+            if !mark[i] && is_live(i, &is_element, &node2weight) {
+                mark[i] = true;
+                lp.push(i);
+            }
+        }
+        for &e in &var2elems[p] {
+            if is_absorbed[e] {
+                continue;
+            }
+            for &i in &elem2vars[e] {
+                if !mark[i] && is_live(i, &is_element, &node2weight) {
+                    mark[i] = true;
+                    lp.push(i);
+                }
+            }
+            is_absorbed[e] = true;
+            elem2vars[e] = vec![];
+        }
+        is_element[p] = true;
+        new2old.extend_from_slice(&node2members[p]);
+        var2vars[p] = vec![];
+        var2elems[p] = vec![];
+        let weight_lp = lp.iter().map(|&i| node2weight[i]).sum::<usize>();
+        elem2weight[p] = weight_lp;
+        // the variables in L_p are adjacent to p, and the edges among them are pruned
+        for &i in &lp {
+            var2elems[i].retain(|&e| !is_absorbed[e]);
+            var2elems[i].push(p);
+            var2vars[i].retain(|&j| !mark[j] && is_live(j, &is_element, &node2weight));
+        }
+        // |L_e \ L_p| for the elements adjacent to L_p
+        let mut elems_touched = Vec::<usize>::new();
+        for &i in &lp {
+            for &e in &var2elems[i] {
+                if e == p {
+                    continue;
+                }
+                if elem2ext[e] == usize::MAX {
+                    elem2ext[e] = elem2weight[e];
+                    elems_touched.push(e);
+                }
+                elem2ext[e] -= node2weight[i];
+            }
+        }
+        // aggressive absorption of the elements included in L_p
+        for &e in &elems_touched {
+            if elem2ext[e] == 0 {
+                is_absorbed[e] = true;
+                elem2vars[e] = vec![];
+            }
+        }
+        // approximate degree
+        let num_remain = num_node - new2old.len();
+        for &i in &lp {
+            var2elems[i].retain(|&e| !is_absorbed[e]);
+            let weight_i = node2weight[i];
+            let mut d = weight_lp - weight_i;
+            for &e in &var2elems[i] {
+                if e != p {
+                    d += elem2ext[e];
+                }
+            }
+            for &j in &var2vars[i] {
+                d += node2weight[j];
+            }
+            degree[i] = d
+                .min(degree[i] + weight_lp - weight_i)
+                .min(num_remain - weight_i);
+        }
+        for &e in &elems_touched {
+            elem2ext[e] = usize::MAX;
+        }
+        // merge the indistinguishable variables into a supervariable
+        let mut hash2var: Vec<(usize, usize)> = lp
+            .iter()
+            .map(|&i| {
+                var2elems[i].sort();
+                var2vars[i].sort();
+                let hash = var2elems[i].iter().chain(var2vars[i].iter()).sum::<usize>();
+                (hash, i)
+            })
+            .collect();
+        hash2var.sort();
+        for (i0, &(hash, i)) in hash2var.iter().enumerate() {
+            if node2weight[i] == 0 {
+                continue;
+            }
+            for &(hash_j, j) in &hash2var[i0 + 1..] {
+                if hash_j != hash {
+                    break;
+                }
+                if node2weight[j] == 0 || var2elems[i] != var2elems[j] || var2vars[i] != var2vars[j]
+                {
+                    continue;
+                }
+                let weight_j = std::mem::replace(&mut node2weight[j], 0);
+                node2weight[i] += weight_j;
+                degree[i] -= weight_j;
+                let members = std::mem::take(&mut node2members[j]);
+                node2members[i].extend(members);
+                var2vars[j] = vec![];
+                var2elems[j] = vec![];
+            }
+        }
+        // L_p keeps the live variables only
+        for &i in &lp {
+            mark[i] = false;
+        }
+        mark[p] = false;
+        lp.retain(|&i| node2weight[i] > 0);
+        for &i in &lp {
+            que.push(Reverse((degree[i], i)));
+        }
+        elem2vars[p] = lp;
+    }
+    assert_eq!(new2old.len(), num_node);
+    let stat = super::fill_statistics(row2idx, idx2col, &new2old);
+    (new2old, stat)
+}
+
+#[test]
+fn test_approximate_minimum_degree() {
+    let sparse = crate::sparse_square::laplacian_grid(20, 18, 0.1);
+    let num_blk = sparse.num_blk;
+    let (row2idx, idx2col) = (&sparse.row2idx, &sparse.idx2col);
+    let (new2old, stat) = approximate_minimum_degree(row2idx, idx2col);
+    // panic if not a permutation
+    let _ = crate::ordering::inverse_permutation(&new2old);
+    // compare with the natural ordering and the exact minimum degree ordering
+    let stat0 =
+        crate::ordering::fill_statistics(row2idx, idx2col, &(0..num_blk).collect::<Vec<_>>());
+    let new2old1 = crate::ordering::minimum_degree::minimum_degree(row2idx, idx2col);
+    let stat1 = crate::ordering::fill_statistics(row2idx, idx2col, &new2old1);
+    assert_eq!(stat.num_nonzero_a, stat0.num_nonzero_a);
+    assert!(stat.num_nonzero_l * 2 < stat0.num_nonzero_l);
+    assert!(stat.num_nonzero_l * 4 < stat1.num_nonzero_l * 5);
+    // the prediction matches the actual factorization
+    let mut factor = crate::sparse_cholesky::Factorization::<f64>::new();
+    factor.symbolic_initialization_with_permutation(row2idx, idx2col, new2old);
+    crate::sparse_cholesky::decompose(&mut factor, &sparse).unwrap();
+    assert_eq!(factor.num_nonzero(), stat.num_nonzero_l);
+}
+
+#[test]
+fn test_approximate_minimum_degree_irregular() {
+    let sparse = crate::sparse_square::irregular_test_matrix(300, true, 100.);
+    let num_blk = sparse.num_blk;
+    let (row2idx, idx2col) = (&sparse.row2idx, &sparse.idx2col);
+    let (new2old, stat) = approximate_minimum_degree(row2idx, idx2col);
+    let stat0 =
+        crate::ordering::fill_statistics(row2idx, idx2col, &(0..num_blk).collect::<Vec<_>>());
+    let new2old1 = crate::ordering::minimum_degree::minimum_degree(row2idx, idx2col);
+    let stat1 = crate::ordering::fill_statistics(row2idx, idx2col, &new2old1);
+    assert!(stat.num_nonzero_l * 3 < stat0.num_nonzero_l);
+    assert!(stat.num_nonzero_l * 10 < stat1.num_nonzero_l * 11);
+    // the prediction matches the actual factorization of the badly scaled matrix
+    let mut factor = crate::sparse_cholesky::Factorization::<f64>::new();
+    factor.symbolic_initialization_with_permutation(row2idx, idx2col, new2old);
+    crate::sparse_cholesky::decompose(&mut factor, &sparse).unwrap();
+    assert_eq!(factor.num_nonzero(), stat.num_nonzero_l);
+}
@@ -0,0 +1,47 @@
+//! minimum degree ordering
+
+/// minimum degree ordering computed on the explicit elimination graph.
+/// The node with the smallest degree is eliminated first, and its neighbours form a clique.
+/// * `row2idx`, `idx2col` - symmetric adjacency of the graph in the CRS format (the diagonal entries are ignored)
+/// * return - `new2old`
+pub fn minimum_degree(row2idx: &[usize], idx2col: &[usize]) -> Vec<usize> {
+    use std::cmp::Reverse;
+    let num_node = row2idx.len() - 1;
+    let mut node2adj: Vec<std::collections::BTreeSet<usize>> = (0..num_node)
+        .map(|i_node| {
+            idx2col[row2idx[i_node]..row2idx[i_node + 1]]
+                .iter()
+                .filter(|&&j_node| j_node != i_node)
+                .cloned()
+                .collect()
+        })
+        .collect();
+    let mut que = std::collections::BinaryHeap::<Reverse<(usize, usize)>>::new();
+    for (i_node, adj) in node2adj.iter().enumerate() {
+        que.push(Reverse((adj.len(), i_node)));
+    }
+    let mut is_eliminated = vec![false; num_node];
+    let mut new2old = Vec::<usize>::with_capacity(num_node);
+    while let Some(Reverse((degree, i_node))) = que.pop() {
+        if is_eliminated[i_node] || node2adj[i_node].len() != degree {
+            continue; // outdated entry in the queue
+        }
+        is_eliminated[i_node] = true;
+        new2old.push(i_node);
+        let adj: Vec<usize> = std::mem::take(&mut node2adj[i_node]).into_iter().collect();
+        for &j_node in &adj {
+            node2adj[j_node].remove(&i_node);
+        }
+        for (i_adj, &j_node) in adj.iter().enumerate() {
+            for &k_node in &adj[i_adj + 1..] {
+                node2adj[j_node].insert(k_node);
+                node2adj[k_node].insert(j_node);
+            }
+        }
+        for &j_node in &adj {
+            que.push(Reverse((node2adj[j_node].len(), j_node)));
+        }
+    }
+    assert_eq!(new2old.len(), num_node);
+    new2old
+}
@@ -0,0 +1,540 @@
+//! nested dissection ordering by the multilevel graph bisection
+
+/// separator tree of the nested dissection.
+/// Each tree node is a subgraph whose rows are contiguous in the new ordering, and the tree nodes are stored
+/// in the post order (children before parent, the root at the end).
+/// The subtrees that do not share the ancestors can be factorized independently.
+/// * `node2parent` - parent of the tree node (`usize::MAX` for the root)
+/// * `node2begin`, `node2end` - rows of the subgraph in the new ordering `node2begin[i]..node2end[i]`
+/// * `node2separator` - rows `node2separator[i]..node2end[i]` belong to the tree node itself,
+///   which is the separator for the internal node and the whole subgraph for the leaf
+pub struct SeparatorTree {
+    pub node2parent: Vec<usize>,
+    pub node2begin: Vec<usize>,
+    pub node2separator: Vec<usize>,
+    pub node2end: Vec<usize>,
+}
+
+impl SeparatorTree {
+    pub fn num_node(&self) -> usize {
+        self.node2parent.len()
+    }
+
+    /// children of the tree node
+    pub fn children(&self, i_node: usize) -> Vec<usize> {
+        (0..i_node)
+            .filter(|&j_node| self.node2parent[j_node] == i_node)
+            .collect()
+    }
+}
+
+/// undirected graph with the weights on the nodes and the edges
+struct Graph {
+    node2weight: Vec<usize>,
+    row2idx: Vec<usize>,
+    idx2col: Vec<usize>,
+    idx2weight: Vec<usize>,
+}
+
+impl Graph {
+    fn num_node(&self) -> usize {
+        self.node2weight.len()
+    }
+
+    /// subgraph induced by `nodes` with the unit weights
+    fn induced_subgraph(&self, nodes: &[usize]) -> Graph {
+        let mut node2local = vec![usize::MAX; self.num_node()];
+        for (i_local, &i_node) in nodes.iter().enumerate() {
+            node2local[i_node] = i_local;
+        }
+        let mut row2idx = vec![0_usize; nodes.len() + 1];
+        let mut idx2col = Vec::<usize>::new();
+        for (i_local, &i_node) in nodes.iter().enumerate() {
+            for &j_node in &self.idx2col[self.row2idx[i_node]..self.row2idx[i_node + 1]] {
+                if node2local[j_node] != usize::MAX {
+                    idx2col.push(node2local[j_node]);
+                }
+            }
+            row2idx[i_local + 1] = idx2col.len();
+        }
+        Graph {
+            node2weight: vec![1; nodes.len()],
+            row2idx,
+            idx2weight: vec![1; idx2col.len()],
+            idx2col,
+        }
+    }
+
+    /// coarse graph by the heavy edge matching
+    /// * return - coarse graph and the map from the fine nodes to the coarse nodes
+    fn coarsen(&self) -> (Graph, Vec<usize>) {
+        let num_node = self.num_node();
+        let mut fine2coarse = vec![usize::MAX; num_node];
+        let mut num_coarse = 0;
+        // nodes with the smaller degree are matched first
+        let mut nodes: Vec<usize> = (0..num_node).collect();
+        nodes.sort_by_key(|&i| self.row2idx[i + 1] - self.row2idx[i]);
+        for &i_node in &nodes {
+            if fine2coarse[i_node] != usize::MAX {
+                continue;
+            }
+            let mut j_match = i_node;
+            let mut max_weight = 0;
+            for idx in self.row2idx[i_node]..self.row2idx[i_node + 1] {
+                let j_node = self.idx2col[idx];
+                if fine2coarse[j_node] == usize::MAX && self.idx2weight[idx] > max_weight {
+                    j_match = j_node;
+                    max_weight = self.idx2weight[idx];
+                }
+            }
+            fine2coarse[i_node] = num_coarse;
+            fine2coarse[j_match] = num_coarse;
+            num_coarse += 1;
+        }
+        let mut coarse2fines = vec![Vec::<usize>::with_capacity(2); num_coarse];
+        for (i_node, &i_coarse) in fine2coarse.iter().enumerate() {
+            coarse2fines[i_coarse].push(i_node);
+        }
+        let mut node2weight = vec![0_usize; num_coarse];
+        let mut row2idx = vec![0_usize; num_coarse + 1];
+        let mut idx2col = Vec::<usize>::new();
+        let mut idx2weight = Vec::<usize>::new();
+        let mut col2idx = vec![usize::MAX; num_coarse];
+        for (i_coarse, fines) in coarse2fines.iter().enumerate() {
+            let idx0 = idx2col.len();
+            for &i_node in fines {
+                node2weight[i_coarse] += self.node2weight[i_node];
+                for idx in self.row2idx[i_node]..self.row2idx[i_node + 1] {
+                    let j_coarse = fine2coarse[self.idx2col[idx]];
+                    if j_coarse == i_coarse {
+                        continue;
+                    }
+                    if col2idx[j_coarse] == usize::MAX {
+                        col2idx[j_coarse] = idx2col.len();
+                        idx2col.push(j_coarse);
+                        idx2weight.push(0);
+                    }
+                    idx2weight[col2idx[j_coarse]] += self.idx2weight[idx];
+                }
+            }
+            for &j_coarse in &idx2col[idx0..] {
+                col2idx[j_coarse] = usize::MAX;
+            }
+            row2idx[i_coarse + 1] = idx2col.len();
+        }
+        let coarse = Graph {
+            node2weight,
+            row2idx,
+            idx2col,
+            idx2weight,
+        };
+        (coarse, fine2coarse)
+    }
+
+    /// total weight of the edges between the two parts
+    fn edge_cut(&self, node2part: &[usize]) -> usize {
+        let mut cut = 0;
+        for i_node in 0..self.num_node() {
+            for idx in self.row2idx[i_node]..self.row2idx[i_node + 1] {
+                if node2part[self.idx2col[idx]] != node2part[i_node] {
+                    cut += self.idx2weight[idx];
+                }
+            }
+        }
+        cut / 2
+    }
+
+    /// Fiduccia-Mattheyses refinement of the bisection
+    /// * `max_weight` - upper bound of the weight of each part
+    fn refine(&self, node2part: &mut [usize], max_weight: usize) {
+        let num_node = self.num_node();
+        for _itr in 0..4 {
+            let mut part2weight = [0_usize; 2];
+            for i_node in 0..num_node {
+                part2weight[node2part[i_node]] += self.node2weight[i_node];
+            }
+            // reduction of the edge cut when the node is moved to the other part
+            let mut node2gain = vec![0_isize; num_node];
+            let mut que = std::collections::BinaryHeap::<(isize, usize)>::new();
+            for i_node in 0..num_node {
+                let mut is_boundary = false;
+                for idx in self.row2idx[i_node]..self.row2idx[i_node + 1] {
+                    let w = self.idx2weight[idx] as isize;
+                    if node2part[self.idx2col[idx]] == node2part[i_node] {
+                        node2gain[i_node] -= w;
+                    } else {
+                        node2gain[i_node] += w;
+                        is_boundary = true;
+                    }
+                }
+                if is_boundary {
+                    que.push((node2gain[i_node], i_node));
+                }
+            }
+            let mut is_locked = vec![false; num_node];
+            let mut moves = Vec::<usize>::new();
+            let mut cut = 0_isize;
+            let (mut best_cut, mut best_num_move) = (0_isize, 0_usize);
+            let imbalance = |part2weight: &[usize; 2]| part2weight[0].abs_diff(part2weight[1]);
+            let mut best_imbalance = imbalance(&part2weight);
+            while let Some((gain, i_node)) = que.pop() {
+                if is_locked[i_node] || node2gain[i_node] != gain {
+                    continue; // outdated entry in the queue
+                }
+                let (i_part, j_part) = (node2part[i_node], 1 - node2part[i_node]);
+                let w = self.node2weight[i_node];
+                if part2weight[j_part] + w > max_weight {
+                    continue;
+                }
+                node2part[i_node] = j_part;
+                part2weight[i_part] -= w;
+                part2weight[j_part] += w;
+                is_locked[i_node] = true;
+                moves.push(i_node);
+                cut -= gain;
+                for idx in self.row2idx[i_node]..self.row2idx[i_node + 1] {
+                    let j_node = self.idx2col[idx];
+                    if is_locked[j_node] {
+                        continue;
+                    }
+                    let w = self.idx2weight[idx] as isize;
+                    if node2part[j_node] == j_part {
+                        node2gain[j_node] -= 2 * w;
+                    } else {
+                        node2gain[j_node] += 2 * w;
+                    }
+                    que.push((node2gain[j_node], j_node));
+                }
+                let imb = imbalance(&part2weight);
+                if cut < best_cut || (cut == best_cut && imb < best_imbalance) {
+                    (best_cut, best_num_move, best_imbalance) = (cut, moves.len(), imb);
+                }
+                if moves.len() > best_num_move + 64 {
+                    break;
+                }
+            }
+            // roll back the moves after the best state
+            for &i_node in &moves[best_num_move..] {
+                node2part[i_node] = 1 - node2part[i_node];
+            }
+            if best_num_move == 0 {
+                break;
+            }
+        }
+    }
+
+    /// bisection of the coarsest graph by growing a part from several seeds with the breadth-first search
+    fn initial_bisection(&self, max_weight: usize) -> Vec<usize> {
+        let num_node = self.num_node();
+        let total_weight = self.node2weight.iter().sum::<usize>();
+        let mut best: (usize, Vec<usize>) = (usize::MAX, vec![]);
+        let num_seed = num_node.min(16);
+        for i_seed in 0..num_seed {
+            let mut node2part = vec![1_usize; num_node];
+            let mut weight0 = 0;
+            let mut que = std::collections::VecDeque::<usize>::new();
+            let mut next_seed = i_seed * num_node / num_seed;
+            while weight0 * 2 < total_weight {
+                let i_node = match que.pop_front() {
+                    Some(i_node) => i_node,
+                    None => {
+                        // start from an unvisited node if the graph is disconnected
+                        while node2part[next_seed] == 0 {
+                            next_seed = (next_seed + 1) % num_node;
+                        }
+                        node2part[next_seed] = 0;
+                        weight0 += self.node2weight[next_seed];
+                        next_seed
+                    }
+                };
+                for &j_node in &self.idx2col[self.row2idx[i_node]..self.row2idx[i_node + 1]] {
+                    if node2part[j_node] == 1 && weight0 * 2 < total_weight {
+                        node2part[j_node] = 0;
+                        weight0 += self.node2weight[j_node];
+                        que.push_back(j_node);
+                    }
+                }
+            }
+            self.refine(&mut node2part, max_weight);
+            let cut = self.edge_cut(&node2part);
+            if cut < best.0 {
+                best = (cut, node2part);
+            }
+        }
+        best.1
+    }
+
+    /// multilevel bisection of the graph
+    /// * return - part (0 or 1) of each node
+    fn bisection(&self, max_weight: usize) -> Vec<usize> {
+        let num_node = self.num_node();
+        if num_node <= 64 {
+            return self.initial_bisection(max_weight);
+        }
+        let (coarse, fine2coarse) = self.coarsen();
+        if coarse.num_node() * 10 > num_node * 9 {
+            // the coarsening does not reduce the graph any more
+            return self.initial_bisection(max_weight);
+        }
+        let coarse2part = coarse.bisection(max_weight);
+        let mut node2part: Vec<usize> = fine2coarse.iter().map(|&i| coarse2part[i]).collect();
+        self.refine(&mut node2part, max_weight);
+        node2part
+    }
+
+    /// vertex separator from the bisection, which is the minimum vertex cover of the edges between the two parts
+    /// computed by the maximum matching (Konig's theorem)
+    fn vertex_separator(&self, node2part: &[usize]) -> Vec<bool> {
+        let num_node = self.num_node();
+        let cut_adj = |i_node: usize| {
+            self.idx2col[self.row2idx[i_node]..self.row2idx[i_node + 1]]
+                .iter()
+                .cloned()
+                .filter(move |&j_node| node2part[j_node] != node2part[i_node])
+        };
+        let lefts: Vec<usize> = (0..num_node)
+            .filter(|&i_node| node2part[i_node] == 0 && cut_adj(i_node).next().is_some())
+            .collect();
+        // maximum matching by the augmenting paths
+        let mut node2match = vec![usize::MAX; num_node];
+        let mut node2stamp = vec![usize::MAX; num_node];
+        for (i_stamp, &i_left) in lefts.iter().enumerate() {
+            // depth-first search of the alternating path. (left node, right node chosen at the left node)
+            let mut path = vec![(i_left, usize::MAX)];
+            let mut iters = vec![cut_adj(i_left)];
+            while let Some(iter) = iters.last_mut() {
+                let Some(j_right) = iter.find(|&j| node2stamp[j] != i_stamp) else {
+                    iters.pop();
+                    path.pop();
+                    continue;
+                };
+                node2stamp[j_right] = i_stamp;
+                path.last_mut().unwrap().1 = j_right;
+                let k_left = node2match[j_right];
+                if k_left == usize::MAX {
+                    for &(i, j) in &path {
+                        node2match[i] = j;
+                        node2match[j] = i;
+                    }
+                    break;
+                }
+                path.push((k_left, usize::MAX));
+                iters.push(cut_adj(k_left));
+            }
+        }
+        // nodes reachable by the alternating paths from the unmatched left nodes
+        let mut is_reachable = vec![false; num_node];
+        let mut stack: Vec<usize> = lefts
+            .iter()
+            .cloned()
+            .filter(|&i| node2match[i] == usize::MAX)
+            .collect();
+        for &i_left in &stack {
+            is_reachable[i_left] = true;
+        }
+        while let Some(i_left) = stack.pop() {
+            for j_right in cut_adj(i_left) {
+                if is_reachable[j_right] {
+                    continue;
+                }
+                is_reachable[j_right] = true;
+                let k_left = node2match[j_right];
+                if k_left != usize::MAX && !is_reachable[k_left] {
+                    is_reachable[k_left] = true;
+                    stack.push(k_left);
+                }
+            }
+        }
+        // the left nodes not reachable and the right nodes reachable
+        let mut is_separator = vec![false; num_node];
+        for &i_left in &lefts {
+            if !is_reachable[i_left] {
+                is_separator[i_left] = true;
+            }
+        }
+        for i_node in 0..num_node {
+            if node2part[i_node] == 1 && is_reachable[i_node] {
+                is_separator[i_node] = true;
+            }
+        }
+        is_separator
+    }
+}
+
+/// order the subgraph recursively and add the tree nodes in the post order
+/// * `local2global` - original index of the nodes of the subgraph
+/// * return - index of the tree node of the subgraph
+fn dissect(
+    graph: &Graph,
+    local2global: &[usize],
+    leaf_size: usize,
+    new2old: &mut Vec<usize>,
+    tree: &mut SeparatorTree,
+) -> usize {
+    let num_node = graph.num_node();
+    let i_begin = new2old.len();
+    let parts = if num_node > leaf_size {
+        let max_weight = (num_node * 11).div_ceil(20);
+        let node2part = graph.bisection(max_weight);
+        let is_separator = graph.vertex_separator(&node2part);
+        let mut parts = [vec![], vec![], vec![]];
+        for i_node in 0..num_node {
+            let i_part = if is_separator[i_node] {
+                2
+            } else {
+                node2part[i_node]
+            };
+            parts[i_part].push(i_node);
+        }
+        Some(parts)
+    } else {
+        None
+    };
+    match parts {
+        Some(parts) if !parts[0].is_empty() && !parts[1].is_empty() => {
+            let mut children = [0; 2];
+            for i_part in 0..2 {
+                let sub = graph.induced_subgraph(&parts[i_part]);
+                let sub2global: Vec<usize> =
+                    parts[i_part].iter().map(|&i| local2global[i]).collect();
+                children[i_part] = dissect(&sub, &sub2global, leaf_size, new2old, tree);
+            }
+            let i_separator = new2old.len();
+            new2old.extend(parts[2].iter().map(|&i| local2global[i]));
+            let i_tree = tree.num_node();
+            for i_child in children {
+                tree.node2parent[i_child] = i_tree;
+            }
+            tree.node2parent.push(usize::MAX);
+            tree.node2begin.push(i_begin);
+            tree.node2separator.push(i_separator);
+            tree.node2end.push(new2old.len());
+            i_tree
+        }
+        _ => {
+            // leaf ordered by the minimum degree
+            let order = super::minimum_degree::minimum_degree(&graph.row2idx, &graph.idx2col);
+            new2old.extend(order.iter().map(|&i| local2global[i]));
+            tree.node2parent.push(usize::MAX);
+            tree.node2begin.push(i_begin);
+            tree.node2separator.push(i_begin);
+            tree.node2end.push(new2old.len());
+            tree.num_node() - 1
+        }
+    }
+}
+
+/// nested dissection ordering. The graph is split recursively into two parts and the vertex separator between them
+/// by the multilevel bisection (heavy edge matching, graph growing and Fiduccia-Mattheyses refinement),
+/// and the rows of the separator are ordered after the two parts.
+/// The subgraphs smaller than `leaf_size` are ordered by the minimum degree ordering.
+/// The pattern of `A + A^T` is used for the non-symmetric matrix.
+/// * `row2idx`, `idx2col` - non-zero pattern of the square matrix in the CRS format (the diagonal entries are ignored)
+/// * return - `new2old` and the separator tree
+pub fn nested_dissection(
+    row2idx: &[usize],
+    idx2col: &[usize],
+    leaf_size: usize,
+) -> (Vec<usize>, SeparatorTree) {
+    let (sym_row2idx, sym_idx2col) = super::symmetrize_pattern(row2idx, idx2col);
+    let num_node = sym_row2idx.len() - 1;
+    let graph = Graph {
+        node2weight: vec![1; num_node],
+        idx2weight: vec![1; sym_idx2col.len()],
+        row2idx: sym_row2idx,
+        idx2col: sym_idx2col,
+    };
+    let mut new2old = Vec::<usize>::with_capacity(num_node);
+    let mut tree = SeparatorTree {
+        node2parent: vec![],
+        node2begin: vec![],
+        node2separator: vec![],
+        node2end: vec![],
+    };
+    let local2global: Vec<usize> = (0..num_node).collect();
+    dissect(
+        &graph,
+        &local2global,
+        leaf_size.max(1),
+        &mut new2old,
+        &mut tree,
+    );
+    assert_eq!(new2old.len(), num_node);
+    (new2old, tree)
+}
+
+/// check that the separator of each node splits the rows of its two children in the pattern of `A + A^T`
+#[cfg(test)]
+fn check_separator_tree(
+    row2idx: &[usize],
+    idx2col: &[usize],
+    new2old: &[usize],
+    tree: &SeparatorTree,
+) {
+    let (row2idx, idx2col) = super::symmetrize_pattern(row2idx, idx2col);
+    let old2new = crate::ordering::inverse_permutation(new2old);
+    assert_eq!(tree.node2end[tree.num_node() - 1], new2old.len());
+    for i_node in 0..tree.num_node() {
+        let children = tree.children(i_node);
+        if children.is_empty() {
+            assert_eq!(tree.node2begin[i_node], tree.node2separator[i_node]);
+            continue;
+        }
+        // the separator splits the rows of the two children
+        assert_eq!(children.len(), 2);
+        let (c0, c1) = (children[0], children[1]);
+        assert_eq!(tree.node2begin[i_node], tree.node2begin[c0]);
+        assert_eq!(tree.node2end[c0], tree.node2begin[c1]);
+        assert_eq!(tree.node2end[c1], tree.node2separator[i_node]);
+        let range0 = tree.node2begin[c0]..tree.node2end[c0];
+        let range1 = tree.node2begin[c1]..tree.node2end[c1];
+        for i_new in range0 {
+            let i_old = new2old[i_new];
+            for &j_old in &idx2col[row2idx[i_old]..row2idx[i_old + 1]] {
+                assert!(!range1.contains(&old2new[j_old]));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_nested_dissection() {
+    let sparse = crate::sparse_square::laplacian_grid(40, 30, 0.1);
+    let num_blk = sparse.num_blk;
+    let (row2idx, idx2col) = (&sparse.row2idx, &sparse.idx2col);
+    let (new2old, tree) = nested_dissection(row2idx, idx2col, 32);
+    assert!(tree.num_node() > 7);
+    check_separator_tree(row2idx, idx2col, &new2old, &tree);
+    // the separator of the root is small
+    let i_root = tree.num_node() - 1;
+    assert!(tree.node2end[i_root] - tree.node2separator[i_root] <= 40);
+    // less fill than the natural ordering
+    let stat0 =
+        crate::ordering::fill_statistics(row2idx, idx2col, &(0..num_blk).collect::<Vec<_>>());
+    let stat1 = crate::ordering::fill_statistics(row2idx, idx2col, &new2old);
+    assert!(stat1.num_nonzero_l * 2 < stat0.num_nonzero_l);
+}
+
+#[test]
+fn test_nested_dissection_irregular() {
+    // irregular pattern made unsymmetric by removing the upper triangle of the even rows
+    let sparse = crate::sparse_square::irregular_test_matrix(400, true, 1.);
+    let num_blk = sparse.num_blk;
+    let mut row2idx = vec![0_usize; num_blk + 1];
+    let mut idx2col = Vec::<usize>::new();
+    for i_row in 0..num_blk {
+        for &j_col in &sparse.idx2col[sparse.row2idx[i_row]..sparse.row2idx[i_row + 1]] {
+            if i_row % 2 == 1 || j_col < i_row {
+                idx2col.push(j_col);
+            }
+        }
+        row2idx[i_row + 1] = idx2col.len();
+    }
+    let (new2old, tree) = nested_dissection(&row2idx, &idx2col, 16);
+    assert!(tree.num_node() > 3);
+    check_separator_tree(&row2idx, &idx2col, &new2old, &tree);
+    let stat0 =
+        crate::ordering::fill_statistics(&row2idx, &idx2col, &(0..num_blk).collect::<Vec<_>>());
+    let stat1 = crate::ordering::fill_statistics(&row2idx, &idx2col, &new2old);
+    assert!(stat1.num_nonzero_l * 2 < stat0.num_nonzero_l);
+}
@@ -0,0 +1,170 @@
+//! Reverse Cuthill-McKee (RCM) ordering for the bandwidth reduction
+
+/// level structure of the breadth-first search from `root` over the nodes that are not ordered yet
+/// * `is_ordered` - nodes excluded from the search
+/// * `mark` - working buffer. All `false` on input and output
+/// * return - nodes in the visited order and the index of the first node of each level
+fn level_structure(
+    root: usize,
+    row2idx: &[usize],
+    idx2col: &[usize],
+    is_ordered: &[bool],
+    mark: &mut [bool],
+) -> (Vec<usize>, Vec<usize>) {
+    let mut order = vec![root];
+    let mut level2idx = vec![0, 1];
+    mark[root] = true;
+    loop {
+        let (idx0, idx1) = (
+            level2idx[level2idx.len() - 2],
+            level2idx[level2idx.len() - 1],
+        );
+        for i0 in idx0..idx1 {
+            let i_node = order[i0];
+            for &j_node in &idx2col[row2idx[i_node]..row2idx[i_node + 1]] {
+                if !mark[j_node] && !is_ordered[j_node] {
+                    mark[j_node] = true;
+                    order.push(j_node);
+                }
+            }
+        }
+        if order.len() == idx1 {
+            break;
+        }
+        level2idx.push(order.len());
+    }
+    for &i_node in &order {
+        mark[i_node] = false;
+    }
+    (order, level2idx)
+}
+
+/// Reverse Cuthill-McKee ordering.
+/// Each connected component is numbered by the breadth-first search from a pseudo-peripheral node
+/// (George-Liu algorithm), visiting the neighbours in the increasing order of the degree, and the order is reversed at the end.
+/// * `row2idx`, `idx2col` - symmetric adjacency of the graph in the CRS format (the diagonal entries are ignored)
+/// * return - `new2old`
+pub fn reverse_cuthill_mckee(row2idx: &[usize], idx2col: &[usize]) -> Vec<usize> {
+    let num_node = row2idx.len() - 1;
+    let node2degree: Vec<usize> = (0..num_node)
+        .map(|i_node| {
+            idx2col[row2idx[i_node]..row2idx[i_node + 1]]
+                .iter()
+                .filter(|&&j_node| j_node != i_node)
+                .count()
+        })
+        .collect();
+    // nodes with smaller degree are tried first as the seed of the component
+    let mut seeds: Vec<usize> = (0..num_node).collect();
+    seeds.sort_by_key(|&i_node| node2degree[i_node]);
+    let mut is_ordered = vec![false; num_node];
+    let mut mark = vec![false; num_node];
+    let mut new2old = Vec::<usize>::with_capacity(num_node);
+    for &seed in &seeds {
+        if is_ordered[seed] {
+            continue;
+        }
+        // pseudo-peripheral node of the component
+        let mut root = seed;
+        let (mut order, mut level2idx) =
+            level_structure(root, row2idx, idx2col, &is_ordered, &mut mark);
+        loop {
+            let last_level = &order[level2idx[level2idx.len() - 2]..];
+            let cand = *last_level
+                .iter()
+                .min_by_key(|&&i_node| node2degree[i_node])
+                .unwrap();
+            let (order1, level2idx1) =
+                level_structure(cand, row2idx, idx2col, &is_ordered, &mut mark);
+            if level2idx1.len() <= level2idx.len() {
+                break;
+            }
+            (root, order, level2idx) = (cand, order1, level2idx1);
+        }
+        // Cuthill-McKee numbering from the root
+        let idx0 = new2old.len();
+        new2old.push(root);
+        is_ordered[root] = true;
+        let mut head = idx0;
+        while head < new2old.len() {
+            let i_node = new2old[head];
+            head += 1;
+            let mut adj: Vec<usize> = idx2col[row2idx[i_node]..row2idx[i_node + 1]]
+                .iter()
+                .filter(|&&j_node| !is_ordered[j_node])
+                .cloned()
+                .collect();
+            adj.sort_by_key(|&j_node| node2degree[j_node]);
+            for j_node in adj {
+                if !is_ordered[j_node] {
+                    is_ordered[j_node] = true;
+                    new2old.push(j_node);
+                }
+            }
+        }
+        assert_eq!(new2old.len() - idx0, order.len());
+    }
+    new2old.reverse();
+    new2old
+}
+
+#[test]
+fn test_reverse_cuthill_mckee() {
+    let (num_x, num_y) = (12, 10);
+    let sparse0 = crate::sparse_square::laplacian_grid(num_x, num_y, 0.1);
+    let num_blk = sparse0.num_blk;
+    // scattered ordering
+    let sparse1 = sparse0.permute(&(0..num_blk).map(|i| (i * 7) % num_blk).collect::<Vec<_>>());
+    let bandwidth1 = crate::ordering::bandwidth(&sparse1.row2idx, &sparse1.idx2col);
+    assert!(bandwidth1 > num_x * 2);
+    let new2old = reverse_cuthill_mckee(&sparse1.row2idx, &sparse1.idx2col);
+    let sparse2 = sparse1.permute(&new2old);
+    assert!(crate::ordering::bandwidth(&sparse2.row2idx, &sparse2.idx2col) <= num_x);
+    // P A P^T P x = P A x
+    let x_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    let mut y_vec = vec![0.; num_blk];
+    crate::sparse_square::mult_vec(&mut y_vec, 0., 1., &sparse1, &x_vec);
+    let mut y_vec_new = vec![0.; num_blk];
+    let x_vec_new = crate::ordering::permute_vec(&new2old, &x_vec);
+    crate::sparse_square::mult_vec(&mut y_vec_new, 0., 1., &sparse2, &x_vec_new);
+    let y_vec1 = crate::ordering::unpermute_vec(&new2old, &y_vec_new);
+    for i_blk in 0..num_blk {
+        assert!((y_vec[i_blk] - y_vec1[i_blk]).abs() < 1.0e-10);
+    }
+}
+
+#[test]
+fn test_reverse_cuthill_mckee_irregular() {
+    let sparse0 = crate::sparse_square::irregular_test_matrix(300, true, 1.);
+    let num_blk = sparse0.num_blk;
+    let (row2idx, idx2col) = (&sparse0.row2idx, &sparse0.idx2col);
+    let new2old = reverse_cuthill_mckee(row2idx, idx2col);
+    let sparse1 = sparse0.permute(&new2old);
+    let bandwidth0 = crate::ordering::bandwidth(row2idx, idx2col);
+    assert!(crate::ordering::bandwidth(&sparse1.row2idx, &sparse1.idx2col) * 2 < bandwidth0);
+    let stat0 =
+        crate::ordering::fill_statistics(row2idx, idx2col, &(0..num_blk).collect::<Vec<_>>());
+    let stat1 = crate::ordering::fill_statistics(row2idx, idx2col, &new2old);
+    assert!(stat1.num_nonzero_l < stat0.num_nonzero_l);
+    // two connected components by removing the edges between the halves
+    let half = num_blk / 2;
+    let mut row2idx2 = vec![0_usize; num_blk + 1];
+    let mut idx2col2 = Vec::<usize>::new();
+    for i_row in 0..num_blk {
+        for &j_col in &idx2col[row2idx[i_row]..row2idx[i_row + 1]] {
+            if (i_row < half) == (j_col < half) {
+                idx2col2.push(j_col);
+            }
+        }
+        row2idx2[i_row + 1] = idx2col2.len();
+    }
+    let new2old2 = reverse_cuthill_mckee(&row2idx2, &idx2col2);
+    // panic if not a permutation
+    let _ = crate::ordering::inverse_permutation(&new2old2);
+    // each component is numbered contiguously
+    let num_switch = new2old2
+        .windows(2)
+        .filter(|w| (w[0] < half) != (w[1] < half))
+        .count();
+    assert_eq!(num_switch, 1);
+}
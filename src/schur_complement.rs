@@ -0,0 +1,535 @@
+//! solver of the 2x2 block saddle-point system `[A B^T; B 0] {u; p} = {f; g}`
+//! by the restarted GMRES method with the block preconditioners based on the Schur complement.
+//! The `A` block is approximated by the incomplete LU factorization, and the Schur complement `B A^{-1} B^T`
+//! is approximated by `B diag(A)^{-1} B^T` factorized by the sparse Cholesky factorization.
+//! The vectors are the concatenation of `u` (size of `A`) and `p` (number of the rows of `B`).
+
+use crate::sparse_square::Matrix;
+use crate::stopping_criterion::{Criterion, Status};
+use num_traits::AsPrimitive;
+
+/// saddle-point matrix `[A B^T; B 0]`
+/// * `a` - square block `A`
+/// * `b_row2idx`, `b_idx2col`, `b_idx2val` - rectangular block `B` in the CRS format, whose columns are the rows of `A`
+pub struct BlockMatrix<T> {
+    pub a: Matrix<T>,
+    pub b_row2idx: Vec<usize>,
+    pub b_idx2col: Vec<usize>,
+    pub b_idx2val: Vec<T>,
+}
+
+impl<T> BlockMatrix<T>
+where
+    T: Copy + num_traits::Zero,
+{
+    /// split the assembled saddle-point matrix into the blocks.
+    /// The upper right block is assumed to be the transpose of the lower left block and the lower right block is ignored.
+    /// * `num_a` - size of the block `A`
+    pub fn from_monolithic(k: &Matrix<T>, num_a: usize) -> Self {
+        let mut a_row2idx = vec![0_usize; num_a + 1];
+        let mut a_idx2col = Vec::<usize>::new();
+        let mut a_idx2val = Vec::<T>::new();
+        for i_row in 0..num_a {
+            for idx in k.row2idx[i_row]..k.row2idx[i_row + 1] {
+                if k.idx2col[idx] < num_a {
+                    a_idx2col.push(k.idx2col[idx]);
+                    a_idx2val.push(k.idx2val[idx]);
+                }
+            }
+            a_row2idx[i_row + 1] = a_idx2col.len();
+        }
+        let num_b = k.num_blk - num_a;
+        let mut b_row2idx = vec![0_usize; num_b + 1];
+        let mut b_idx2col = Vec::<usize>::new();
+        let mut b_idx2val = Vec::<T>::new();
+        for i_b in 0..num_b {
+            let i_row = num_a + i_b;
+            for idx in k.row2idx[i_row]..k.row2idx[i_row + 1] {
+                if k.idx2col[idx] < num_a {
+                    b_idx2col.push(k.idx2col[idx]);
+                    b_idx2val.push(k.idx2val[idx]);
+                }
+            }
+            b_row2idx[i_b + 1] = b_idx2col.len();
+        }
+        BlockMatrix {
+            a: Matrix {
+                num_blk: num_a,
+                row2idx: a_row2idx,
+                idx2col: a_idx2col,
+                idx2val: a_idx2val,
+                row2val: k.row2val[..num_a].to_vec(),
+            },
+            b_row2idx,
+            b_idx2col,
+            b_idx2val,
+        }
+    }
+
+    /// number of the rows of `B`
+    pub fn num_b(&self) -> usize {
+        self.b_row2idx.len() - 1
+    }
+}
+
+/// `{y} <- \alpha [A B^T; B 0] {x} + \beta {y}`
+pub fn mult_vec<T>(y_vec: &mut [T], beta: T, alpha: T, block: &BlockMatrix<T>, x_vec: &[T])
+where
+    T: 'static + num_traits::Float + std::ops::MulAssign + std::ops::AddAssign,
+    f32: AsPrimitive<T>,
+{
+    let num_a = block.a.num_blk;
+    assert_eq!(y_vec.len(), num_a + block.num_b());
+    assert_eq!(x_vec.len(), y_vec.len());
+    let (y_u, y_p) = y_vec.split_at_mut(num_a);
+    let (x_u, x_p) = x_vec.split_at(num_a);
+    crate::sparse_square::mult_mat(y_u, beta, alpha, &block.a, x_u);
+    for (i_b, y) in y_p.iter_mut().enumerate() {
+        let mut v = T::zero();
+        for idx in block.b_row2idx[i_b]..block.b_row2idx[i_b + 1] {
+            let j_col = block.b_idx2col[idx];
+            v += block.b_idx2val[idx] * x_u[j_col];
+            y_u[j_col] += alpha * block.b_idx2val[idx] * x_p[i_b];
+        }
+        *y = beta * *y + alpha * v;
+    }
+}
+
+/// approximation of the Schur complement `[B] diag([A])^{-1} [B]^T`
+pub fn approximate_schur_complement<T>(block: &BlockMatrix<T>) -> Matrix<T>
+where
+    T: num_traits::Float + std::ops::AddAssign,
+{
+    let num_a = block.a.num_blk;
+    let num_b = block.num_b();
+    // transpose of B, i.e., the rows of B in each column
+    let mut col2idx = vec![0_usize; num_a + 1];
+    for &j_col in &block.b_idx2col {
+        col2idx[j_col + 1] += 1;
+    }
+    for j_col in 0..num_a {
+        col2idx[j_col + 1] += col2idx[j_col];
+    }
+    let mut idx2row = vec![0_usize; block.b_idx2col.len()];
+    let mut idx2val = vec![T::zero(); block.b_idx2col.len()];
+    {
+        let mut col2pos = col2idx[..num_a].to_vec();
+        for i_b in 0..num_b {
+            for idx in block.b_row2idx[i_b]..block.b_row2idx[i_b + 1] {
+                let j_col = block.b_idx2col[idx];
+                idx2row[col2pos[j_col]] = i_b;
+                idx2val[col2pos[j_col]] = block.b_idx2val[idx];
+                col2pos[j_col] += 1;
+            }
+        }
+    }
+    // S_ij = sum_k B_ik B_jk / A_kk
+    let mut s_row2idx = vec![0_usize; num_b + 1];
+    let mut s_idx2col = Vec::<usize>::new();
+    let mut s_idx2val = Vec::<T>::new();
+    let mut s_row2val = vec![T::zero(); num_b];
+    let mut col2pos = vec![usize::MAX; num_b];
+    for i_b in 0..num_b {
+        let idx_row0 = s_idx2col.len();
+        for idx in block.b_row2idx[i_b]..block.b_row2idx[i_b + 1] {
+            let k = block.b_idx2col[idx];
+            let b_ik = block.b_idx2val[idx] / block.a.row2val[k];
+            for kdx in col2idx[k]..col2idx[k + 1] {
+                let (j_b, b_jk) = (idx2row[kdx], idx2val[kdx]);
+                if j_b == i_b {
+                    s_row2val[i_b] += b_ik * b_jk;
+                    continue;
+                }
+                if col2pos[j_b] == usize::MAX {
+                    col2pos[j_b] = s_idx2col.len();
+                    s_idx2col.push(j_b);
+                    s_idx2val.push(T::zero());
+                }
+                s_idx2val[col2pos[j_b]] += b_ik * b_jk;
+            }
+        }
+        for &j_b in &s_idx2col[idx_row0..] {
+            col2pos[j_b] = usize::MAX;
+        }
+        s_row2idx[i_b + 1] = s_idx2col.len();
+    }
+    Matrix {
+        num_blk: num_b,
+        row2idx: s_row2idx,
+        idx2col: s_idx2col,
+        idx2val: s_idx2val,
+        row2val: s_row2val,
+    }
+}
+
+/// type of the block preconditioner
+pub enum BlockPreconditioner {
+    /// `[A 0; 0 S]`
+    Diagonal,
+    /// `[A B^T; 0 -S]`, which makes the preconditioned system have the single eigenvalue 1 if `A` and `S` are exact
+    UpperTriangular,
+}
+
+/// block preconditioner of the saddle-point system
+/// * `ilu` - incomplete LU factorization of `A`
+/// * `schur` - approximated Schur complement `S = B diag(A)^{-1} B^T`
+/// * `schur_factor` - Cholesky factorization of `schur`
+pub struct SchurPreconditioner<T> {
+    pub ilu: crate::sparse_ilu::Preconditioner<T>,
+    pub schur: Matrix<T>,
+    pub schur_factor: crate::sparse_cholesky::Factorization<T>,
+    pub kind: BlockPreconditioner,
+}
+
+impl<T> SchurPreconditioner<T>
+where
+    T: 'static + num_traits::Float + std::ops::AddAssign + std::ops::SubAssign,
+    f32: AsPrimitive<T>,
+{
+    pub fn new() -> Self {
+        SchurPreconditioner {
+            ilu: crate::sparse_ilu::Preconditioner::new(),
+            schur: Matrix {
+                num_blk: 0,
+                row2idx: vec![0],
+                idx2col: vec![],
+                idx2val: vec![],
+                row2val: vec![],
+            },
+            schur_factor: crate::sparse_cholesky::Factorization::new(),
+            kind: BlockPreconditioner::UpperTriangular,
+        }
+    }
+
+    /// factorize the blocks of the preconditioner
+    /// * `lev_fill` - fill-in level of the ILU-k factorization of `A`
+    /// * return - error if the ILU factorization of `A` breaks down,
+    ///   or the approximated Schur complement is not positive definite (e.g., `B` is rank deficient)
+    pub fn initialize(
+        &mut self,
+        block: &BlockMatrix<T>,
+        lev_fill: usize,
+        kind: BlockPreconditioner,
+    ) -> Result<(), String> {
+        self.ilu.initialize_iluk(&block.a, lev_fill);
+        crate::sparse_ilu::copy_value(&mut self.ilu, &block.a);
+        crate::sparse_ilu::try_decompose(&mut self.ilu)?;
+        self.schur = approximate_schur_complement(block);
+        self.schur_factor
+            .symbolic_initialization(&self.schur.row2idx, &self.schur.idx2col);
+        crate::sparse_cholesky::decompose(&mut self.schur_factor, &self.schur)?;
+        self.kind = kind;
+        Ok(())
+    }
+}
+
+impl<T> Default for SchurPreconditioner<T>
+where
+    T: 'static + num_traits::Float + std::ops::AddAssign + std::ops::SubAssign,
+    f32: AsPrimitive<T>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `{vec} <- [P]^{-1}{vec}` where `[P]` is the block preconditioner
+pub fn solve_preconditioning_vec<T>(
+    vec: &mut [T],
+    pre: &SchurPreconditioner<T>,
+    block: &BlockMatrix<T>,
+) where
+    T: num_traits::Float + std::ops::AddAssign + std::ops::SubAssign,
+{
+    let num_a = block.a.num_blk;
+    assert_eq!(vec.len(), num_a + block.num_b());
+    let (f_vec, g_vec) = vec.split_at_mut(num_a);
+    match pre.kind {
+        BlockPreconditioner::Diagonal => {
+            crate::sparse_cholesky::solve_vec(&pre.schur_factor, g_vec);
+        }
+        BlockPreconditioner::UpperTriangular => {
+            // {p} = -[S]^{-1}{g}
+            crate::sparse_cholesky::solve_vec(&pre.schur_factor, g_vec);
+            g_vec.iter_mut().for_each(|v| *v = -*v);
+            // {f} <- {f} - [B]^T{p}
+            for (i_b, &p) in g_vec.iter().enumerate() {
+                for idx in block.b_row2idx[i_b]..block.b_row2idx[i_b + 1] {
+                    f_vec[block.b_idx2col[idx]] -= block.b_idx2val[idx] * p;
+                }
+            }
+        }
+    }
+    crate::sparse_ilu::solve_preconditioning_vec(f_vec, &pre.ilu);
+}
+
+/// solve the saddle-point system using the restarted GMRES method with the right block preconditioner.
+/// The convergence is judged by the residual estimated in the Arnoldi process,
+/// and the true residual is recomputed at each restart.
+/// If the Arnoldi process breaks down with the singular Hessenberg matrix (e.g., for the singular system),
+/// the iterate at that point is returned.
+/// Only the residual based criteria are supported because the Arnoldi process gives neither
+/// the preconditioned residual nor the energy of the updates.
+/// It panics if `criterion` contains `PreconditionedResidual` or `EnergyNormError`.
+/// * `x_vec` - solution (output)
+/// * `restart` - dimension of the Krylov subspace before restart
+/// * return - history of the residual norm (the first element is `||r_0||`)
+pub fn solve<T>(
+    x_vec: &mut Vec<T>,
+    rhs_vec: &[T],
+    block: &BlockMatrix<T>,
+    pre: &SchurPreconditioner<T>,
+    criterion: &Criterion<T>,
+    restart: usize,
+    max_nitr: usize,
+) -> Vec<T>
+where
+    T: 'static
+        + num_traits::Float
+        + std::ops::MulAssign
+        + std::ops::AddAssign
+        + std::ops::SubAssign,
+    f32: AsPrimitive<T>,
+{
+    use crate::slice::{add_scaled_vector, dot};
+    let num_blk = block.a.num_blk + block.num_b();
+    assert_eq!(rhs_vec.len(), num_blk);
+    assert!(restart > 0);
+    assert!(
+        criterion.is_residual_based(),
+        "only the residual based criteria are supported"
+    );
+    x_vec.clear();
+    x_vec.resize(num_blk, T::zero());
+    let mut r_vec = rhs_vec.to_vec();
+    let sqnorm_rhs = dot(rhs_vec, rhs_vec);
+    let mut status = Status::new(sqnorm_rhs, sqnorm_rhs, sqnorm_rhs);
+    if sqnorm_rhs == T::zero() || criterion.is_satisfied(&status) {
+        return status.res_hist;
+    }
+    let mut v_vecs = vec![vec![T::zero(); num_blk]; restart + 1];
+    let mut z_vecs = vec![vec![T::zero(); num_blk]; restart];
+    // Hessenberg matrix stored by columns
+    let mut h_mat = vec![vec![T::zero(); restart + 1]; restart];
+    let mut cs = vec![T::zero(); restart];
+    let mut sn = vec![T::zero(); restart];
+    let mut g = vec![T::zero(); restart + 1];
+    loop {
+        let beta = dot(&r_vec, &r_vec).sqrt();
+        for (v, &r) in v_vecs[0].iter_mut().zip(&r_vec) {
+            *v = r / beta;
+        }
+        crate::slice::set_zero(&mut g);
+        g[0] = beta;
+        let mut num_krylov = 0;
+        let mut is_converged = false;
+        let mut is_breakdown = false;
+        for j in 0..restart {
+            // {z_j} = [P]^{-1}{v_j}, {w} = [K]{z_j}
+            z_vecs[j].copy_from_slice(&v_vecs[j]);
+            solve_preconditioning_vec(&mut z_vecs[j], pre, block);
+            let (v_lower, v_upper) = v_vecs.split_at_mut(j + 1);
+            let w_vec = &mut v_upper[0];
+            mult_vec(w_vec, T::zero(), T::one(), block, &z_vecs[j]);
+            // modified Gram-Schmidt
+            for (i, v_vec) in v_lower.iter().enumerate() {
+                h_mat[j][i] = dot(w_vec, v_vec);
+                add_scaled_vector(w_vec, -h_mat[j][i], v_vec);
+            }
+            let h_norm = dot(w_vec, w_vec).sqrt();
+            h_mat[j][j + 1] = h_norm;
+            if h_norm > T::zero() {
+                w_vec.iter_mut().for_each(|w| *w = *w / h_norm);
+            }
+            // apply the Givens rotations
+            for i in 0..j {
+                let (h0, h1) = (h_mat[j][i], h_mat[j][i + 1]);
+                h_mat[j][i] = cs[i] * h0 + sn[i] * h1;
+                h_mat[j][i + 1] = -sn[i] * h0 + cs[i] * h1;
+            }
+            let (h0, h1) = (h_mat[j][j], h_mat[j][j + 1]);
+            let r = (h0 * h0 + h1 * h1).sqrt();
+            if r == T::zero() {
+                // the Hessenberg matrix is singular (e.g., the singular system).
+                // The Krylov subspace does not grow, so the current iterate is returned
+                is_breakdown = true;
+                break;
+            }
+            (cs[j], sn[j]) = (h0 / r, h1 / r);
+            h_mat[j][j] = r;
+            h_mat[j][j + 1] = T::zero();
+            g[j + 1] = -sn[j] * g[j];
+            g[j] = cs[j] * g[j];
+            num_krylov = j + 1;
+            let sqnorm_res = g[j + 1] * g[j + 1];
+            status.push(sqnorm_res, sqnorm_res, T::zero());
+            if h_norm == T::zero() || criterion.is_satisfied(&status) {
+                is_converged = true;
+                break;
+            }
+            if status.iteration >= max_nitr {
+                break;
+            }
+        }
+        // {x} += [Z]{y} where [H]{y} = {g}
+        let mut y = g[..num_krylov].to_vec();
+        for i in (0..num_krylov).rev() {
+            for k in i + 1..num_krylov {
+                y[i] = y[i] - h_mat[k][i] * y[k];
+            }
+            y[i] = y[i] / h_mat[i][i];
+        }
+        for (z_vec, &y_i) in z_vecs.iter().zip(&y) {
+            add_scaled_vector(x_vec, y_i, z_vec);
+        }
+        if is_converged || is_breakdown || status.iteration >= max_nitr {
+            return status.res_hist;
+        }
+        // true residual {r} = {b} - [K]{x}
+        r_vec.copy_from_slice(rhs_vec);
+        mult_vec(&mut r_vec, T::one(), -T::one(), block, x_vec);
+    }
+}
+
+#[test]
+fn test_schur_complement() {
+    use crate::slice::dot;
+    let (num_x, num_y, num_constraint) = (16, 14, 20);
+    let sparse = crate::sparse_ldlt::saddle_point_matrix(num_x, num_y, num_constraint);
+    let block = BlockMatrix::from_monolithic(&sparse, num_x * num_y);
+    assert_eq!(block.num_b(), num_constraint);
+    let rhs_vec: Vec<f64> = (0..sparse.num_blk).map(|i| (i as f64).sin()).collect();
+    // the product is the same as the monolithic matrix
+    {
+        let mut y0 = vec![1.; sparse.num_blk];
+        let mut y1 = y0.clone();
+        crate::sparse_square::mult_vec(&mut y0, 0.5, 2., &sparse, &rhs_vec);
+        mult_vec(&mut y1, 0.5, 2., &block, &rhs_vec);
+        let diff: f64 = y0.iter().zip(&y1).map(|(a, b)| (a - b).abs()).sum();
+        assert!(diff < 1.0e-10);
+    }
+    let criterion = Criterion::RelativeResidual(1.0e-8);
+    let mut num_itrs = vec![];
+    for kind in [
+        BlockPreconditioner::Diagonal,
+        BlockPreconditioner::UpperTriangular,
+    ] {
+        let mut pre = SchurPreconditioner::new();
+        pre.initialize(&block, 1, kind).unwrap();
+        let mut x_vec = Vec::<f64>::new();
+        let conv_hist = solve(&mut x_vec, &rhs_vec, &block, &pre, &criterion, 30, 1000);
+        // check the true residual
+        let mut r_vec = rhs_vec.clone();
+        crate::sparse_square::mult_vec(&mut r_vec, 1., -1., &sparse, &x_vec);
+        assert!(dot(&r_vec, &r_vec).sqrt() < dot(&rhs_vec, &rhs_vec).sqrt() * 1.0e-7);
+        num_itrs.push(conv_hist.len());
+    }
+    // the block triangular preconditioner converges faster
+    assert!(num_itrs[1] < num_itrs[0]);
+}
+
+#[test]
+#[should_panic(expected = "only the residual based criteria are supported")]
+fn test_schur_complement_unsupported_criterion() {
+    let sparse = crate::sparse_ldlt::saddle_point_matrix(6, 5, 4);
+    let block = BlockMatrix::from_monolithic(&sparse, 30);
+    let mut pre = SchurPreconditioner::new();
+    pre.initialize(&block, 0, BlockPreconditioner::Diagonal)
+        .unwrap();
+    let criterion = Criterion::Any(vec![
+        Criterion::RelativeResidual(1.0e-8),
+        Criterion::EnergyNormError {
+            tol: 1.0e-8,
+            delay: 2,
+        },
+    ]);
+    let rhs_vec = vec![1.; sparse.num_blk];
+    solve(&mut vec![], &rhs_vec, &block, &pre, &criterion, 10, 100);
+}
+
+#[test]
+fn test_schur_complement_breakdown() {
+    // `[A B^T; B 0]` is singular because `B A^{-1} B^T = 0`, while its approximation `B diag(A)^{-1} B^T` is positive.
+    // The first Krylov vector is mapped to zero by the preconditioned matrix
+    let block = BlockMatrix {
+        a: Matrix {
+            num_blk: 2,
+            row2idx: vec![0, 1, 2],
+            idx2col: vec![1, 0],
+            idx2val: vec![-1., -1.],
+            row2val: vec![3., -5.],
+        },
+        b_row2idx: vec![0, 2],
+        b_idx2col: vec![0, 1],
+        b_idx2val: vec![1., 1.],
+    };
+    let mut pre = SchurPreconditioner::new();
+    pre.initialize(&block, 0, BlockPreconditioner::UpperTriangular)
+        .unwrap();
+    let rhs_vec = vec![0., 0., 1.];
+    let mut x_vec = Vec::<f64>::new();
+    let criterion = Criterion::RelativeResidual(1.0e-8);
+    let conv_hist = solve(&mut x_vec, &rhs_vec, &block, &pre, &criterion, 10, 100);
+    assert_eq!(conv_hist, vec![1.]);
+    assert_eq!(x_vec, vec![0.; 3]);
+}
+
+#[test]
+fn test_schur_complement_irregular() {
+    use crate::slice::dot;
+    // badly scaled irregular `A` and the constraints `B` on the scattered rows with the scattered scales
+    let a = crate::sparse_square::irregular_test_matrix(300, true, 100.);
+    let num_a = a.num_blk;
+    let num_b = 40;
+    let mut b_row2idx = vec![0];
+    let mut b_idx2col = vec![];
+    let mut b_idx2val = vec![];
+    for i_b in 0..num_b {
+        let scale = 10_f64.powi((i_b % 5) as i32 - 2);
+        let mut cols = vec![
+            (i_b * 7) % num_a,
+            (i_b * 113 + 5) % num_a,
+            (i_b * 211 + 1) % num_a,
+        ];
+        cols.sort();
+        cols.dedup();
+        for (k, &j_col) in cols.iter().enumerate() {
+            b_idx2col.push(j_col);
+            b_idx2val.push(scale * (1. + k as f64 + (j_col as f64).sin()));
+        }
+        b_row2idx.push(b_idx2col.len());
+    }
+    let block = BlockMatrix {
+        a,
+        b_row2idx,
+        b_idx2col,
+        b_idx2val,
+    };
+    let num_blk = num_a + num_b;
+    let rhs_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64 * 0.7).sin()).collect();
+    let criterion = Criterion::RelativeResidual(1.0e-10);
+    let mut num_itrs = vec![];
+    for kind in [
+        BlockPreconditioner::Diagonal,
+        BlockPreconditioner::UpperTriangular,
+    ] {
+        let mut pre = SchurPreconditioner::new();
+        pre.initialize(&block, 1, kind).unwrap();
+        let mut x_vec = Vec::<f64>::new();
+        let conv_hist = solve(&mut x_vec, &rhs_vec, &block, &pre, &criterion, 30, 1000);
+        // check the true residual
+        let mut r_vec = rhs_vec.clone();
+        mult_vec(&mut r_vec, 1., -1., &block, &x_vec);
+        let ratio = (dot(&r_vec, &r_vec) / dot(&rhs_vec, &rhs_vec)).sqrt();
+        assert!(ratio < 1.0e-9, "{}", ratio);
+        num_itrs.push(conv_hist.len());
+    }
+    assert!(num_itrs[1] < num_itrs[0]);
+    // the breakdown of the ILU factorization of `A` is reported
+    let mut block = block;
+    block.a.row2val[100] = 0.;
+    let mut pre = SchurPreconditioner::new();
+    assert!(pre
+        .initialize(&block, 0, BlockPreconditioner::Diagonal)
+        .is_err());
+}
@@ -0,0 +1,374 @@
+//! overlapping additive Schwarz and restricted additive Schwarz (RAS) domain decomposition preconditioners.
+//! The rows of the matrix are partitioned into the contiguous subdomains, which are extended by the overlap,
+//! and the local submatrices are factorized and solved concurrently.
+
+use num_traits::AsPrimitive;
+
+/// solver of the local submatrix
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LocalSolver {
+    /// incomplete LU factorization with the fill-in level
+    Ilu(usize),
+    /// sparse direct LU factorization
+    Direct,
+}
+
+enum LocalFactor<T> {
+    Ilu(crate::sparse_ilu::Preconditioner<T>),
+    Direct(crate::sparse_lu::Factorization<T>),
+}
+
+/// subdomain of the domain decomposition
+/// * `idx2row` - global rows of the subdomain including the overlap in the increasing order
+/// * `idx2is_owned` - if the row belongs to the subdomain before extended by the overlap
+/// * `sparse` - local submatrix `A(idx2row, idx2row)`
+pub struct Subdomain<T> {
+    pub idx2row: Vec<usize>,
+    pub idx2is_owned: Vec<bool>,
+    pub sparse: crate::sparse_square::Matrix<T>,
+    // position in the global `idx2val` for each local `idx2val`
+    idx2src: Vec<usize>,
+    factor: LocalFactor<T>,
+}
+
+/// additive Schwarz preconditioner `[M]^{-1} = \sum_i [R_i]^T [A_i]^{-1} [R_i]`
+/// * `restricted` - if true, each row takes the local solution of the subdomain owning it (RAS)
+///   instead of the sum over the overlapping subdomains. RAS converges faster but is not symmetric
+pub struct AdditiveSchwarz<T> {
+    pub subdomains: Vec<Subdomain<T>>,
+    pub restricted: bool,
+}
+
+impl<T> AdditiveSchwarz<T>
+where
+    T: 'static + num_traits::Float + std::ops::SubAssign + Send + Sync,
+    f32: AsPrimitive<T>,
+{
+    pub fn new() -> Self {
+        AdditiveSchwarz {
+            subdomains: vec![],
+            restricted: true,
+        }
+    }
+
+    /// partition the rows into the subdomains, extend them by the overlap, and set the local patterns
+    /// * `num_subdomain` - number of the subdomains. The rows are split by `sparse_square::partition_rows`
+    /// * `overlap` - number of the layers of the adjacent rows added to each subdomain
+    pub fn symbolic_initialization(
+        &mut self,
+        a: &crate::sparse_square::Matrix<T>,
+        num_subdomain: usize,
+        overlap: usize,
+        local_solver: LocalSolver,
+    ) {
+        let num_blk = a.num_blk;
+        let part2row = crate::sparse_square::partition_rows(&a.row2idx, num_subdomain);
+        let (sym_row2idx, sym_idx2col) =
+            crate::ordering::symmetrize_pattern(&a.row2idx, &a.idx2col);
+        let mut row2local = vec![usize::MAX; num_blk];
+        let mut row2flag = vec![false; num_blk];
+        self.subdomains.clear();
+        for rows in part2row.windows(2) {
+            // extend the subdomain by the breadth-first search
+            let mut idx2row: Vec<usize> = (rows[0]..rows[1]).collect();
+            idx2row.iter().for_each(|&i_row| row2flag[i_row] = true);
+            let mut idx_front = 0;
+            for _ in 0..overlap {
+                let idx_end = idx2row.len();
+                for idx in idx_front..idx_end {
+                    let i_row = idx2row[idx];
+                    for &j_col in &sym_idx2col[sym_row2idx[i_row]..sym_row2idx[i_row + 1]] {
+                        if !row2flag[j_col] {
+                            row2flag[j_col] = true;
+                            idx2row.push(j_col);
+                        }
+                    }
+                }
+                idx_front = idx_end;
+            }
+            idx2row.sort();
+            let idx2is_owned: Vec<bool> = idx2row
+                .iter()
+                .map(|&i_row| i_row >= rows[0] && i_row < rows[1])
+                .collect();
+            // local pattern
+            for (i_local, &i_row) in idx2row.iter().enumerate() {
+                row2local[i_row] = i_local;
+            }
+            let num_local = idx2row.len();
+            let mut row2idx = vec![0_usize; num_local + 1];
+            let mut idx2col = Vec::<usize>::new();
+            let mut idx2src = Vec::<usize>::new();
+            for (i_local, &i_row) in idx2row.iter().enumerate() {
+                for idx in a.row2idx[i_row]..a.row2idx[i_row + 1] {
+                    let j_local = row2local[a.idx2col[idx]];
+                    if j_local != usize::MAX {
+                        idx2col.push(j_local);
+                        idx2src.push(idx);
+                    }
+                }
+                row2idx[i_local + 1] = idx2col.len();
+            }
+            for &i_row in &idx2row {
+                row2local[i_row] = usize::MAX;
+                row2flag[i_row] = false;
+            }
+            let sparse = crate::sparse_square::Matrix::<T> {
+                num_blk: num_local,
+                row2idx,
+                idx2col,
+                idx2val: vec![T::zero(); idx2src.len()],
+                row2val: vec![T::zero(); num_local],
+            };
+            let factor = match local_solver {
+                LocalSolver::Ilu(lev_fill) => {
+                    let mut ilu = crate::sparse_ilu::Preconditioner::<T>::new();
+                    ilu.initialize_iluk(&sparse, lev_fill);
+                    LocalFactor::Ilu(ilu)
+                }
+                LocalSolver::Direct => {
+                    let mut lu = crate::sparse_lu::Factorization::<T>::new();
+                    lu.symbolic_initialization(&sparse.row2idx, &sparse.idx2col);
+                    LocalFactor::Direct(lu)
+                }
+            };
+            self.subdomains.push(Subdomain {
+                idx2row,
+                idx2is_owned,
+                sparse,
+                idx2src,
+                factor,
+            });
+        }
+    }
+}
+
+impl<T> Default for AdditiveSchwarz<T>
+where
+    T: 'static + num_traits::Float + std::ops::SubAssign + Send + Sync,
+    f32: AsPrimitive<T>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// copy the values of the local submatrices from the matrix and factorize them concurrently
+/// * `a` - matrix with the same pattern as the one given to `symbolic_initialization`
+/// * `num_thread` - number of the threads. Computed on the current thread if it is one
+/// * return - error if the factorization of a local submatrix breaks down
+pub fn decompose<T>(
+    schwarz: &mut AdditiveSchwarz<T>,
+    a: &crate::sparse_square::Matrix<T>,
+    num_thread: usize,
+) -> Result<(), String>
+where
+    T: 'static + num_traits::Float + std::ops::SubAssign + Send + Sync,
+    f32: AsPrimitive<T>,
+{
+    let decompose_subdomain = |sd: &mut Subdomain<T>| -> Result<(), String> {
+        for (v, &idx) in sd.sparse.idx2val.iter_mut().zip(&sd.idx2src) {
+            *v = a.idx2val[idx];
+        }
+        for (v, &i_row) in sd.sparse.row2val.iter_mut().zip(&sd.idx2row) {
+            *v = a.row2val[i_row];
+        }
+        match &mut sd.factor {
+            LocalFactor::Ilu(ilu) => {
+                crate::sparse_ilu::copy_value(ilu, &sd.sparse);
+                crate::sparse_ilu::try_decompose(ilu)
+            }
+            LocalFactor::Direct(lu) => crate::sparse_lu::decompose(lu, &sd.sparse),
+        }
+    };
+    let num_subdomain = schwarz.subdomains.len();
+    if num_thread <= 1 || num_subdomain <= 1 {
+        return schwarz
+            .subdomains
+            .iter_mut()
+            .try_for_each(decompose_subdomain);
+    }
+    let chunk_size = num_subdomain.div_ceil(num_thread);
+    let results: Vec<Result<(), String>> = std::thread::scope(|s| {
+        let handles: Vec<_> = schwarz
+            .subdomains
+            .chunks_mut(chunk_size)
+            .map(|sds| {
+                let decompose_subdomain = &decompose_subdomain;
+                s.spawn(move || sds.iter_mut().try_for_each(decompose_subdomain))
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    results.into_iter().collect()
+}
+
+/// `{vec} <- [M]^{-1}{vec}` where the local problems are solved concurrently
+/// * `num_thread` - number of the threads. Computed on the current thread if it is one
+pub fn solve_preconditioning_vec<T>(vec: &mut [T], schwarz: &AdditiveSchwarz<T>, num_thread: usize)
+where
+    T: 'static + num_traits::Float + std::ops::SubAssign + Send + Sync,
+    f32: AsPrimitive<T>,
+{
+    let r_vec: &[T] = vec;
+    let solve_subdomain = |sd: &Subdomain<T>| -> Vec<T> {
+        let mut local: Vec<T> = sd.idx2row.iter().map(|&i_row| r_vec[i_row]).collect();
+        match &sd.factor {
+            LocalFactor::Ilu(ilu) => crate::sparse_ilu::solve_preconditioning_vec(&mut local, ilu),
+            LocalFactor::Direct(lu) => crate::sparse_lu::solve_vec(lu, &mut local),
+        }
+        local
+    };
+    let num_subdomain = schwarz.subdomains.len();
+    let sd2local: Vec<Vec<T>> = if num_thread <= 1 || num_subdomain <= 1 {
+        schwarz.subdomains.iter().map(solve_subdomain).collect()
+    } else {
+        let chunk_size = num_subdomain.div_ceil(num_thread);
+        std::thread::scope(|s| {
+            let handles: Vec<_> = schwarz
+                .subdomains
+                .chunks(chunk_size)
+                .map(|sds| {
+                    let solve_subdomain = &solve_subdomain;
+                    s.spawn(move || sds.iter().map(solve_subdomain).collect::<Vec<_>>())
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect()
+        })
+    };
+    // sum up in the order of the subdomains for the deterministic result
+    vec.iter_mut().for_each(|v| *v = T::zero());
+    for (sd, local) in schwarz.subdomains.iter().zip(sd2local) {
+        for ((&i_row, &is_owned), v) in sd.idx2row.iter().zip(&sd.idx2is_owned).zip(local) {
+            if !schwarz.restricted {
+                vec[i_row] = vec[i_row] + v;
+            } else if is_owned {
+                vec[i_row] = v;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_additive_schwarz() {
+    use crate::slice::dot;
+    let sparse = crate::sparse_square::laplacian_grid(24, 20, 0.01);
+    let num_blk = sparse.num_blk;
+    let b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    // residual after the Richardson iterations with the RAS preconditioner
+    let richardson = |schwarz: &AdditiveSchwarz<f64>, num_thread: usize| {
+        let mut x_vec = vec![0.; num_blk];
+        let mut r_vec = b_vec.clone();
+        for _ in 0..10 {
+            solve_preconditioning_vec(&mut r_vec, schwarz, num_thread);
+            crate::slice::add_scaled_vector(&mut x_vec, 1., &r_vec);
+            r_vec.copy_from_slice(&b_vec);
+            crate::sparse_square::mult_vec(&mut r_vec, 1., -1., &sparse, &x_vec);
+        }
+        (dot(&r_vec, &r_vec).sqrt(), x_vec)
+    };
+    let mut residuals = vec![];
+    for overlap in [0, 1, 3] {
+        for local_solver in [LocalSolver::Direct, LocalSolver::Ilu(2)] {
+            let mut schwarz = AdditiveSchwarz::<f64>::new();
+            schwarz.symbolic_initialization(&sparse, 4, overlap, local_solver);
+            decompose(&mut schwarz, &sparse, 4).unwrap();
+            let (res1, x_vec1) = richardson(&schwarz, 1);
+            let (_, x_vec4) = richardson(&schwarz, 4);
+            assert_eq!(x_vec1, x_vec4);
+            assert!(res1 < dot(&b_vec, &b_vec).sqrt());
+            if local_solver == LocalSolver::Direct {
+                residuals.push(res1);
+            }
+        }
+    }
+    // the larger overlap gives the faster convergence
+    assert!(residuals[1] < residuals[0] && residuals[2] < residuals[1]);
+    // single subdomain with the direct solver is the exact inverse
+    let mut schwarz = AdditiveSchwarz::<f64>::new();
+    schwarz.symbolic_initialization(&sparse, 1, 0, LocalSolver::Direct);
+    decompose(&mut schwarz, &sparse, 1).unwrap();
+    let mut x_vec = b_vec.clone();
+    solve_preconditioning_vec(&mut x_vec, &schwarz, 1);
+    let mut r_vec = b_vec.clone();
+    crate::sparse_square::mult_vec(&mut r_vec, 1., -1., &sparse, &x_vec);
+    assert!(dot(&r_vec, &r_vec).sqrt() < 1.0e-10);
+    // the non-restricted version is symmetric
+    let mut schwarz = AdditiveSchwarz::<f64>::new();
+    schwarz.symbolic_initialization(&sparse, 4, 2, LocalSolver::Direct);
+    schwarz.restricted = false;
+    decompose(&mut schwarz, &sparse, 4).unwrap();
+    let u_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64 * 0.3).cos()).collect();
+    let mut mu_vec = u_vec.clone();
+    solve_preconditioning_vec(&mut mu_vec, &schwarz, 4);
+    let mut mb_vec = b_vec.clone();
+    solve_preconditioning_vec(&mut mb_vec, &schwarz, 4);
+    assert!((dot(&b_vec, &mu_vec) - dot(&u_vec, &mb_vec)).abs() < 1.0e-8);
+}
+
+#[test]
+fn test_additive_schwarz_irregular() {
+    use crate::solver_sparse::preconditioned_conjugate_gradient_with_operators;
+    use std::ops::ControlFlow;
+    let sparse = crate::sparse_square::irregular_test_matrix(600, true, 100.);
+    let num_blk = sparse.num_blk;
+    let b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    let criterion = crate::stopping_criterion::Criterion::RelativeResidual(1.0e-10);
+    // the symmetric (non-restricted) additive Schwarz preconditioned CG compared with Jacobi (last)
+    let mut num_itrs = vec![];
+    for local_solver in [Some(LocalSolver::Direct), Some(LocalSolver::Ilu(1)), None] {
+        let mut schwarz = AdditiveSchwarz::<f64>::new();
+        if let Some(local_solver) = local_solver {
+            schwarz.symbolic_initialization(&sparse, 2, 1, local_solver);
+            schwarz.restricted = false;
+            decompose(&mut schwarz, &sparse, 3).unwrap();
+        }
+        let mut r_vec = b_vec.clone();
+        let (mut x_vec, mut pr_vec, mut p_vec) = (vec![], vec![], vec![]);
+        let conv_hist = preconditioned_conjugate_gradient_with_operators(
+            &mut r_vec,
+            &mut x_vec,
+            &mut pr_vec,
+            &mut p_vec,
+            &criterion,
+            1000,
+            |y: &mut [f64], x: &[f64]| crate::sparse_square::mult_mat(y, 0., 1., &sparse, x),
+            |v: &mut [f64]| {
+                if local_solver.is_some() {
+                    solve_preconditioning_vec(v, &schwarz, 3);
+                } else {
+                    v.iter_mut().zip(&sparse.row2val).for_each(|(v, d)| *v /= d);
+                }
+            },
+            |_, _, _| ControlFlow::Continue(()),
+        );
+        let err = crate::sparse_square::componentwise_backward_error(&sparse, &x_vec, &b_vec);
+        assert!(err < 1.0e-8, "{}", err);
+        num_itrs.push(conv_hist.len());
+    }
+    assert!(num_itrs[0] < num_itrs[1] && num_itrs[1] < num_itrs[2]);
+    // RAS Richardson iterations converge for the unsymmetric matrix
+    let sparse = crate::sparse_square::irregular_test_matrix(600, false, 100.);
+    let mut schwarz = AdditiveSchwarz::<f64>::new();
+    schwarz.symbolic_initialization(&sparse, 6, 2, LocalSolver::Direct);
+    decompose(&mut schwarz, &sparse, 3).unwrap();
+    let mut x_vec = vec![0.; num_blk];
+    let mut r_vec = b_vec.clone();
+    for _ in 0..50 {
+        solve_preconditioning_vec(&mut r_vec, &schwarz, 3);
+        crate::slice::add_scaled_vector(&mut x_vec, 1., &r_vec);
+        r_vec.copy_from_slice(&b_vec);
+        crate::sparse_square::mult_vec(&mut r_vec, 1., -1., &sparse, &x_vec);
+    }
+    let err = crate::sparse_square::componentwise_backward_error(&sparse, &x_vec, &b_vec);
+    assert!(err < 1.0e-8, "{}", err);
+    // the breakdown of the local ILU factorization is reported
+    let mut singular = sparse.clone();
+    singular.row2val[100] = 0.;
+    let mut schwarz = AdditiveSchwarz::<f64>::new();
+    schwarz.symbolic_initialization(&singular, 6, 1, LocalSolver::Ilu(0));
+    assert!(decompose(&mut schwarz, &singular, 3).is_err());
+}
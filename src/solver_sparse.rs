@@ -1,5 +1,22 @@
+use crate::stopping_criterion::{Criterion, Status};
 use crate::{sparse_ilu, sparse_square};
 use num_traits::AsPrimitive;
+use std::ops::ControlFlow;
+
+/// criterion of the solvers taking `conv_ratio_tol`.
+/// The iteration is skipped if the squared norm of the initial residual is less than `1.0e-20`,
+/// and it is terminated by the relative residual otherwise.
+fn criterion_for_conv_ratio<T>(r_vec: &[T], conv_ratio_tol: T) -> Criterion<T>
+where
+    T: 'static + num_traits::Float,
+    f32: AsPrimitive<T>,
+{
+    if crate::slice::dot(r_vec, r_vec) < 1.0e-20_f32.as_() {
+        Criterion::AbsoluteResidual(T::infinity())
+    } else {
+        Criterion::RelativeResidual(conv_ratio_tol)
+    }
+}
 
 /// solve linear system using the Confugate Gradient (CG) method
 pub fn conjugate_gradient<T>(
@@ -23,7 +40,155 @@ where
         + std::ops::Div<Output = T>
         + std::ops::Neg<Output = T>
         + std::fmt::Display
-        + std::ops::Mul<Output = T>,
+        + std::ops::Mul<Output = T>
+        + Send
+        + Sync,
+{
+    let criterion = criterion_for_conv_ratio(r_vec, conv_ratio_tol);
+    conjugate_gradient_with_criterion(r_vec, u_vec, ap_vec, p_vec, &criterion, max_iteration, mat)
+}
+
+/// solve linear system using the Confugate Gradient (CG) method
+/// with the stopping criterion given by `criterion`
+/// * `r_vec` - rhs vector as input, residual vector as output
+/// * return - history of the convergence ratio `||r_k|| / ||r_0||`
+pub fn conjugate_gradient_with_criterion<T>(
+    r_vec: &mut Vec<T>,
+    u_vec: &mut Vec<T>,
+    ap_vec: &mut Vec<T>,
+    p_vec: &mut Vec<T>,
+    criterion: &Criterion<T>,
+    max_iteration: usize,
+    mat: &sparse_square::Matrix<T>,
+) -> Vec<T>
+where
+    f32: AsPrimitive<T>,
+    T: 'static
+        + Copy
+        + num_traits::Float
+        + std::ops::MulAssign
+        + std::ops::Mul
+        + std::ops::AddAssign
+        + std::cmp::PartialOrd
+        + std::ops::Div<Output = T>
+        + std::ops::Neg<Output = T>
+        + std::fmt::Display
+        + std::ops::Mul<Output = T>
+        + Send
+        + Sync,
+{
+    conjugate_gradient_with_monitor(
+        r_vec,
+        u_vec,
+        ap_vec,
+        p_vec,
+        criterion,
+        max_iteration,
+        mat,
+        |_, _, _| ControlFlow::Continue(()),
+    )
+}
+
+/// solve linear system using the Confugate Gradient (CG) method
+/// while reporting the progress to `monitor`
+/// The matrix-vector product is multi-threaded for the large matrix (see `sparse_square::num_thread_for_mult`)
+/// * `monitor` - called after each iteration with the iteration index, the residual norm `||r_k||`
+///   and the current solution. Returning `ControlFlow::Break(())` terminates the iteration
+/// * return - history of the convergence ratio `||r_k|| / ||r_0||`
+#[allow(clippy::too_many_arguments)]
+pub fn conjugate_gradient_with_monitor<T, F>(
+    r_vec: &mut Vec<T>,
+    u_vec: &mut Vec<T>,
+    ap_vec: &mut Vec<T>,
+    p_vec: &mut Vec<T>,
+    criterion: &Criterion<T>,
+    max_iteration: usize,
+    mat: &sparse_square::Matrix<T>,
+    monitor: F,
+) -> Vec<T>
+where
+    F: FnMut(usize, T, &[T]) -> ControlFlow<()>,
+    f32: AsPrimitive<T>,
+    T: 'static
+        + Copy
+        + num_traits::Float
+        + std::ops::MulAssign
+        + std::ops::Mul
+        + std::ops::AddAssign
+        + std::cmp::PartialOrd
+        + std::ops::Div<Output = T>
+        + std::ops::Neg<Output = T>
+        + std::fmt::Display
+        + std::ops::Mul<Output = T>
+        + Send
+        + Sync,
+{
+    let num_thread = sparse_square::num_thread_for_mult(mat, r_vec.len() / mat.num_blk.max(1));
+    conjugate_gradient_with_operator(
+        r_vec,
+        u_vec,
+        ap_vec,
+        p_vec,
+        criterion,
+        max_iteration,
+        |y_vec, x_vec| {
+            sparse_square::par_mult_mat(y_vec, T::zero(), T::one(), mat, x_vec, num_thread)
+        },
+        monitor,
+    )
+}
+
+/// multi-threaded version of `conjugate_gradient_with_criterion`
+/// * `num_thread` - number of the threads for the matrix-vector product (see `sparse_square::par_mult_mat`).
+///   The threads are spawned for each product, so that it pays off only for the large matrix
+#[allow(clippy::too_many_arguments)]
+pub fn par_conjugate_gradient_with_criterion<T>(
+    r_vec: &mut Vec<T>,
+    u_vec: &mut Vec<T>,
+    ap_vec: &mut Vec<T>,
+    p_vec: &mut Vec<T>,
+    criterion: &Criterion<T>,
+    max_iteration: usize,
+    mat: &sparse_square::Matrix<T>,
+    num_thread: usize,
+) -> Vec<T>
+where
+    T: num_traits::Float + std::ops::MulAssign + std::ops::AddAssign + Send + Sync,
+{
+    conjugate_gradient_with_operator(
+        r_vec,
+        u_vec,
+        ap_vec,
+        p_vec,
+        criterion,
+        max_iteration,
+        |y_vec, x_vec| {
+            sparse_square::par_mult_mat(y_vec, T::zero(), T::one(), mat, x_vec, num_thread)
+        },
+        |_, _, _| ControlFlow::Continue(()),
+    )
+}
+
+/// solve linear system using the Confugate Gradient (CG) method
+/// where the matrix is given as the operator `mult`
+/// * `mult` - `{y} <- [A]{x}` called as `mult(y, x)`
+/// * `monitor` - same as `conjugate_gradient_with_monitor`
+/// * return - history of the convergence ratio `||r_k|| / ||r_0||`
+#[allow(clippy::too_many_arguments)]
+pub fn conjugate_gradient_with_operator<T, M, F>(
+    r_vec: &mut Vec<T>,
+    u_vec: &mut Vec<T>,
+    ap_vec: &mut Vec<T>,
+    p_vec: &mut Vec<T>,
+    criterion: &Criterion<T>,
+    max_iteration: usize,
+    mut mult: M,
+    mut monitor: F,
+) -> Vec<T>
+where
+    T: num_traits::Float + std::ops::MulAssign + std::ops::AddAssign,
+    M: FnMut(&mut [T], &[T]),
+    F: FnMut(usize, T, &[T]) -> ControlFlow<()>,
 {
     use crate::slice::{add_scaled_vector, copy, dot, scale_and_add_vec, set_zero};
     {
@@ -32,23 +197,22 @@ where
         ap_vec.resize(n, T::zero());
         p_vec.resize(n, T::zero());
     }
-    let num_dim = r_vec.len() / mat.row2val.len();
     //
     let mut conv_hist = Vec::<T>::new();
     set_zero(u_vec);
     let mut sqnorm_res = dot(r_vec, r_vec);
-    if sqnorm_res < 1.0e-20_f32.as_() {
+    if sqnorm_res == T::zero() {
+        return conv_hist;
+    }
+    let mut status = Status::new(sqnorm_res, sqnorm_res, sqnorm_res);
+    if criterion.is_satisfied(&status) {
         return conv_hist;
     }
     let inv_sqnorm_res_ini = T::one() / sqnorm_res;
     copy(p_vec, &r_vec); // {p} = {r}  (set initial serch direction, copy value not reference)
-    for _iitr in 0..max_iteration {
-        if num_dim == 1 {
-            // alpha = (r,r) / (p,Ap)
-            sparse_square::mult_vec(ap_vec, T::zero(), T::one(), mat, p_vec); // {Ap_vec} = [mat]*{p_vec}
-        } else {
-            sparse_square::mult_mat(ap_vec, T::zero(), T::one(), mat, p_vec); // {Ap_vec} = [mat]*{p_vec}
-        }
+    for iitr in 0..max_iteration {
+        // alpha = (r,r) / (p,Ap)
+        mult(ap_vec, p_vec); // {Ap_vec} = [mat]*{p_vec}
         let pap = dot(p_vec, ap_vec);
         assert!(pap >= T::zero());
         let alpha = sqnorm_res / pap;
@@ -57,7 +221,11 @@ where
         let sqnorm_res_new = dot(r_vec, r_vec);
         let conv_ratio = (sqnorm_res_new * inv_sqnorm_res_ini).sqrt();
         conv_hist.push(conv_ratio);
-        if conv_ratio < conv_ratio_tol {
+        status.push(sqnorm_res_new, sqnorm_res_new, alpha * sqnorm_res);
+        if monitor(iitr, status.norm_res(), u_vec).is_break() {
+            return conv_hist;
+        }
+        if sqnorm_res_new == T::zero() || criterion.is_satisfied(&status) {
             return conv_hist;
         }
         {
@@ -87,8 +255,204 @@ where
         + num_traits::Float
         + std::ops::AddAssign
         + std::ops::MulAssign
-        + std::ops::SubAssign,
+        + std::ops::SubAssign
+        + Send
+        + Sync,
     f32: AsPrimitive<T>,
+{
+    let criterion = criterion_for_conv_ratio(r_vec, conv_ratio_tol);
+    preconditioned_conjugate_gradient_with_criterion(
+        r_vec, x_vec, pr_vec, p_vec, &criterion, max_nitr, mat, ilu,
+    )
+}
+
+/// solve a real-valued linear system using the conjugate gradient method with preconditioner
+/// with the stopping criterion given by `criterion`
+/// * `r_vec` - rhs vector as input, residual vector as output
+/// * return - history of the residual norm `||r_k||` (the first element is `||r_0||`)
+#[allow(clippy::too_many_arguments)]
+pub fn preconditioned_conjugate_gradient_with_criterion<T>(
+    r_vec: &mut Vec<T>,
+    x_vec: &mut Vec<T>,
+    pr_vec: &mut Vec<T>,
+    p_vec: &mut Vec<T>,
+    criterion: &Criterion<T>,
+    max_nitr: usize,
+    mat: &sparse_square::Matrix<T>,
+    ilu: &sparse_ilu::Preconditioner<T>,
+) -> Vec<T>
+where
+    T: 'static
+        + Copy
+        + std::ops::Mul
+        + num_traits::Float
+        + std::ops::AddAssign
+        + std::ops::MulAssign
+        + std::ops::SubAssign
+        + Send
+        + Sync,
+    f32: AsPrimitive<T>,
+{
+    preconditioned_conjugate_gradient_with_monitor(
+        r_vec,
+        x_vec,
+        pr_vec,
+        p_vec,
+        criterion,
+        max_nitr,
+        mat,
+        ilu,
+        |_, _, _| ControlFlow::Continue(()),
+    )
+}
+
+/// solve a real-valued linear system using the conjugate gradient method with preconditioner
+/// while reporting the progress to `monitor`
+/// The matrix-vector product is multi-threaded for the large matrix (see `sparse_square::num_thread_for_mult`)
+/// * `monitor` - called after each iteration with the iteration index, the residual norm `||r_k||`
+///   and the current solution. Returning `ControlFlow::Break(())` terminates the iteration
+/// * return - history of the residual norm `||r_k||` (the first element is `||r_0||`)
+#[allow(clippy::too_many_arguments)]
+pub fn preconditioned_conjugate_gradient_with_monitor<T, F>(
+    r_vec: &mut Vec<T>,
+    x_vec: &mut Vec<T>,
+    pr_vec: &mut Vec<T>,
+    p_vec: &mut Vec<T>,
+    criterion: &Criterion<T>,
+    max_nitr: usize,
+    mat: &sparse_square::Matrix<T>,
+    ilu: &sparse_ilu::Preconditioner<T>,
+    monitor: F,
+) -> Vec<T>
+where
+    F: FnMut(usize, T, &[T]) -> ControlFlow<()>,
+    T: 'static
+        + Copy
+        + std::ops::Mul
+        + num_traits::Float
+        + std::ops::AddAssign
+        + std::ops::MulAssign
+        + std::ops::SubAssign
+        + Send
+        + Sync,
+    f32: AsPrimitive<T>,
+{
+    assert_eq!(
+        r_vec.len(),
+        mat.num_blk,
+        "the preconditioned CG supports only one value per block"
+    );
+    let num_thread = sparse_square::num_thread_for_mult(mat, 1);
+    let (conv_hist, _) = preconditioned_conjugate_gradient_loop(
+        r_vec,
+        x_vec,
+        pr_vec,
+        p_vec,
+        criterion,
+        max_nitr,
+        |y_vec, x_vec| {
+            sparse_square::par_mult_mat(y_vec, T::zero(), T::one(), mat, x_vec, num_thread)
+        },
+        |v_vec| sparse_ilu::solve_preconditioning_vec(v_vec, ilu),
+        monitor,
+        true,
+    );
+    conv_hist
+}
+
+/// multi-threaded version of `preconditioned_conjugate_gradient_with_criterion`
+/// * `num_thread` - number of the threads for the matrix-vector product (see `sparse_square::par_mult_vec`).
+///   The threads are spawned for each product, so that it pays off only for the large matrix
+#[allow(clippy::too_many_arguments)]
+pub fn par_preconditioned_conjugate_gradient_with_criterion<T>(
+    r_vec: &mut Vec<T>,
+    x_vec: &mut Vec<T>,
+    pr_vec: &mut Vec<T>,
+    p_vec: &mut Vec<T>,
+    criterion: &Criterion<T>,
+    max_nitr: usize,
+    mat: &sparse_square::Matrix<T>,
+    ilu: &sparse_ilu::Preconditioner<T>,
+    num_thread: usize,
+) -> Vec<T>
+where
+    T: num_traits::Float
+        + std::ops::AddAssign
+        + std::ops::MulAssign
+        + std::ops::SubAssign
+        + Send
+        + Sync,
+{
+    assert_eq!(r_vec.len(), mat.num_blk);
+    let (conv_hist, _) = preconditioned_conjugate_gradient_loop(
+        r_vec,
+        x_vec,
+        pr_vec,
+        p_vec,
+        criterion,
+        max_nitr,
+        |y_vec, x_vec| {
+            sparse_square::par_mult_vec(y_vec, T::zero(), T::one(), mat, x_vec, num_thread)
+        },
+        |v_vec| sparse_ilu::solve_preconditioning_vec(v_vec, ilu),
+        |_, _, _| ControlFlow::Continue(()),
+        true,
+    );
+    conv_hist
+}
+
+/// solve a real-valued linear system using the conjugate gradient method
+/// where the matrix and the preconditioner are given as the operators
+/// * `mult` - `{y} <- [A]{x}` called as `mult(y, x)`
+/// * `precond` - `{v} <- [M]^{-1}{v}` where `[M]^{-1}` is symmetric positive definite
+/// * `monitor` - same as `preconditioned_conjugate_gradient_with_monitor`
+/// * return - history of the residual norm `||r_k||` (the first element is `||r_0||`)
+#[allow(clippy::too_many_arguments)]
+pub fn preconditioned_conjugate_gradient_with_operators<T, M, P, F>(
+    r_vec: &mut Vec<T>,
+    x_vec: &mut Vec<T>,
+    pr_vec: &mut Vec<T>,
+    p_vec: &mut Vec<T>,
+    criterion: &Criterion<T>,
+    max_nitr: usize,
+    mult: M,
+    precond: P,
+    monitor: F,
+) -> Vec<T>
+where
+    T: num_traits::Float + std::ops::AddAssign + std::ops::MulAssign,
+    M: FnMut(&mut [T], &[T]),
+    P: FnMut(&mut [T]),
+    F: FnMut(usize, T, &[T]) -> ControlFlow<()>,
+{
+    let (conv_hist, _) = preconditioned_conjugate_gradient_loop(
+        r_vec, x_vec, pr_vec, p_vec, criterion, max_nitr, mult, precond, monitor, false,
+    );
+    conv_hist
+}
+
+/// iterations of the preconditioned conjugate gradient method
+/// * `is_final_residual` - push the residual again if the iteration is not terminated within `max_nitr`
+///   (the behavior of the solvers with the ILU preconditioner)
+/// * return - history of the residual norm and whether it is terminated within `max_nitr`
+#[allow(clippy::too_many_arguments)]
+fn preconditioned_conjugate_gradient_loop<T, M, P, F>(
+    r_vec: &mut Vec<T>,
+    x_vec: &mut Vec<T>,
+    pr_vec: &mut Vec<T>,
+    p_vec: &mut Vec<T>,
+    criterion: &Criterion<T>,
+    max_nitr: usize,
+    mut mult: M,
+    mut precond: P,
+    mut monitor: F,
+    is_final_residual: bool,
+) -> (Vec<T>, bool)
+where
+    T: num_traits::Float + std::ops::AddAssign + std::ops::MulAssign,
+    M: FnMut(&mut [T], &[T]),
+    P: FnMut(&mut [T]),
+    F: FnMut(usize, T, &[T]) -> ControlFlow<()>,
 {
     use crate::slice::{add_scaled_vector, copy, dot, scale_and_add_vec, set_zero};
     {
@@ -97,68 +461,54 @@ where
         pr_vec.resize(n, T::zero());
         p_vec.resize(n, T::zero());
     }
-    let num_dim = r_vec.len() / mat.num_blk;
     let mut conv_hist = Vec::<T>::new();
 
     set_zero(x_vec);
 
-    let inv_sqnorm_res0 = {
-        let sqnorm_res0 = dot(r_vec, r_vec); // DotX(r_vec, r_vec, N);
-        conv_hist.push(sqnorm_res0.sqrt());
-        if sqnorm_res0 < 1.0e-20_f32.as_() {
-            return conv_hist;
-        }
-        T::one() / sqnorm_res0
-    };
+    let sqnorm_res0 = dot(r_vec, r_vec); // DotX(r_vec, r_vec, N);
+    conv_hist.push(sqnorm_res0.sqrt());
+    if sqnorm_res0 == T::zero() {
+        return (conv_hist, true);
+    }
 
     // {Pr} = [P]{r}
     copy(pr_vec, r_vec); // std::vector<double> Pr_vec(r_vec, r_vec + N);
-
-    if num_dim == 1 {
-        sparse_ilu::solve_preconditioning_vec(pr_vec, &ilu); // ilu.SolvePrecond(Pr_vec.data());
-    } else {
-        todo!();
-    }
+    precond(pr_vec);
 
     // {p} = {Pr}
     copy(p_vec, pr_vec);
 
     // rPr = ({r},{Pr})
     let mut rpr = dot(r_vec, pr_vec); // DotX(r_vec, Pr_vec.data(), N);
-    for _iitr in 0..max_nitr {
+    let mut status = Status::new(sqnorm_res0, sqnorm_res0, rpr);
+    if criterion.is_satisfied(&status) {
+        return (conv_hist, true);
+    }
+    for iitr in 0..max_nitr {
         // {Ap} = [A]{p}
-        if num_dim == 1 {
-            sparse_square::mult_vec(pr_vec, T::zero(), T::one(), mat, p_vec);
-        } else {
-            sparse_square::mult_mat(pr_vec, T::zero(), T::one(), mat, p_vec);
-        }
-        {
-            // alpha = ({r},{Pr})/({p},{Ap})
-            let pap = dot(p_vec, pr_vec);
-            let alpha = rpr / pap;
-            add_scaled_vector(r_vec, -alpha, pr_vec); // {r} = -alpha*{Ap} + {r}
-            add_scaled_vector(x_vec, alpha, p_vec); // {x} = +alpha*{p} + {x}
-        }
+        mult(pr_vec, p_vec);
+        // alpha = ({r},{Pr})/({p},{Ap})
+        let alpha = rpr / dot(p_vec, pr_vec);
+        add_scaled_vector(r_vec, -alpha, pr_vec); // {r} = -alpha*{Ap} + {r}
+        add_scaled_vector(x_vec, alpha, p_vec); // {x} = +alpha*{p} + {x}
+        let sqnorm_res = dot(r_vec, r_vec); // DotX(r_vec, r_vec, N);
+        conv_hist.push(sqnorm_res.sqrt());
+        // {Pr} = [P]{r}
+        copy(pr_vec, r_vec);
+        precond(pr_vec);
+        // rPr1 = ({r},{Pr})
+        let rpr1 = dot(r_vec, pr_vec);
         {
             // Converge Judgement
-            let sqnorm_res = dot(r_vec, r_vec); // DotX(r_vec, r_vec, N);
-            conv_hist.push(sqnorm_res.sqrt());
-            let conv_ratio = (sqnorm_res * inv_sqnorm_res0).sqrt();
-            if conv_ratio < conv_ratio_tol {
-                return conv_hist;
+            status.push(sqnorm_res, rpr1, alpha * rpr);
+            if monitor(iitr, status.norm_res(), x_vec).is_break() {
+                return (conv_hist, true);
+            }
+            if sqnorm_res == T::zero() || criterion.is_satisfied(&status) {
+                return (conv_hist, true);
             }
         }
         {
-            // calc beta
-            copy(pr_vec, r_vec);
-            // {Pr} = [P]{r}
-            if num_dim == 1 {
-                sparse_ilu::solve_preconditioning_vec(pr_vec, &ilu);
-            } else {
-                todo!();
-            }
-            // rPr1 = ({r},{Pr})
-            let rpr1 = dot(r_vec, pr_vec);
             // beta = rPr1/rPr
             let beta = rpr1 / rpr;
             rpr = rpr1;
@@ -166,10 +516,497 @@ where
             scale_and_add_vec(p_vec, beta, pr_vec);
         }
     }
-    {
+    if is_final_residual {
         // Converge Judgement
         let sq_norm_res = dot(r_vec, r_vec); // DotX(r_vec, r_vec, N);
         conv_hist.push(sq_norm_res.sqrt());
     }
-    return conv_hist;
+    (conv_hist, false)
+}
+
+/// dot product for each component of the interleaved vectors
+/// (i.e., `v[i_blk * num_dim + i_dim]`)
+fn dot_componentwise<T>(v0: &[T], v1: &[T], num_dim: usize) -> Vec<T>
+where
+    T: Copy + std::ops::Mul<Output = T> + std::ops::AddAssign + num_traits::Zero,
+{
+    assert_eq!(v0.len(), v1.len());
+    let mut res = vec![T::zero(); num_dim];
+    for (i, (&x, &y)) in v0.iter().zip(v1.iter()).enumerate() {
+        res[i % num_dim] += x * y;
+    }
+    res
+}
+
+/// solve linear system using the Confugate Gradient (CG) method
+/// for each of the `num_dim` interleaved components independently.
+/// Each component has its own step sizes and convergence judgement,
+/// while the sparse matrix multiplication is shared,
+/// and it is multi-threaded for the large matrix (see `sparse_square::num_thread_for_mult`).
+/// * `r_vec` - rhs vector as input, residual vector as output. The value of `i_dim`-th component of `i_blk`-th block is `r_vec[i_blk * num_dim + i_dim]`
+/// * `criterion` - stopping criterion evaluated for each component separately
+/// * return - history of the convergence ratio `||r_k|| / ||r_0||` for each component
+pub fn conjugate_gradient_componentwise<T>(
+    r_vec: &mut [T],
+    u_vec: &mut Vec<T>,
+    ap_vec: &mut Vec<T>,
+    p_vec: &mut Vec<T>,
+    criterion: &Criterion<T>,
+    max_iteration: usize,
+    mat: &sparse_square::Matrix<T>,
+) -> Vec<Vec<T>>
+where
+    f32: AsPrimitive<T>,
+    T: 'static
+        + Copy
+        + num_traits::Float
+        + std::ops::MulAssign
+        + std::ops::AddAssign
+        + std::ops::SubAssign
+        + Send
+        + Sync,
+{
+    use crate::slice::{copy, set_zero};
+    {
+        let n = r_vec.len();
+        u_vec.resize(n, T::zero());
+        ap_vec.resize(n, T::zero());
+        p_vec.resize(n, T::zero());
+    }
+    let num_dim = r_vec.len() / mat.num_blk;
+    assert_eq!(r_vec.len(), mat.num_blk * num_dim);
+    let num_thread = sparse_square::num_thread_for_mult(mat, num_dim);
+    //
+    let mut conv_hist = vec![Vec::<T>::new(); num_dim];
+    set_zero(u_vec);
+    let mut sqnorm_res = dot_componentwise(r_vec, r_vec, num_dim);
+    let sqnorm_res_ini = sqnorm_res.clone();
+    let mut statuses: Vec<Status<T>> = sqnorm_res.iter().map(|&v| Status::new(v, v, v)).collect();
+    let mut is_active: Vec<bool> = (0..num_dim)
+        .map(|i_dim| sqnorm_res[i_dim] != T::zero() && !criterion.is_satisfied(&statuses[i_dim]))
+        .collect();
+    copy(p_vec, r_vec); // {p} = {r}
+    let mut alpha = vec![T::zero(); num_dim];
+    for _iitr in 0..max_iteration {
+        if !is_active.iter().any(|&v| v) {
+            break;
+        }
+        sparse_square::par_mult_mat(ap_vec, T::zero(), T::one(), mat, p_vec, num_thread); // {Ap_vec} = [mat]*{p_vec}
+        let pap = dot_componentwise(p_vec, ap_vec, num_dim);
+        for i_dim in 0..num_dim {
+            alpha[i_dim] = if is_active[i_dim] {
+                sqnorm_res[i_dim] / pap[i_dim]
+            } else {
+                T::zero()
+            };
+        }
+        for i in 0..r_vec.len() {
+            let a = alpha[i % num_dim];
+            u_vec[i] += a * p_vec[i]; // {u} = +alpha*{p} + {u}
+            r_vec[i] -= a * ap_vec[i]; // {r} = -alpha*{Ap} + {r}
+        }
+        let sqnorm_res_new = dot_componentwise(r_vec, r_vec, num_dim);
+        let mut beta = vec![T::zero(); num_dim];
+        for i_dim in 0..num_dim {
+            if !is_active[i_dim] {
+                continue;
+            }
+            let status = &mut statuses[i_dim];
+            status.push(
+                sqnorm_res_new[i_dim],
+                sqnorm_res_new[i_dim],
+                alpha[i_dim] * sqnorm_res[i_dim],
+            );
+            conv_hist[i_dim].push((sqnorm_res_new[i_dim] / sqnorm_res_ini[i_dim]).sqrt());
+            if sqnorm_res_new[i_dim] == T::zero() || criterion.is_satisfied(status) {
+                is_active[i_dim] = false;
+                continue;
+            }
+            beta[i_dim] = sqnorm_res_new[i_dim] / sqnorm_res[i_dim]; // beta = (r1,r1) / (r0,r0)
+        }
+        sqnorm_res = sqnorm_res_new;
+        for i in 0..p_vec.len() {
+            let i_dim = i % num_dim;
+            // {p} = {r} + beta*{p}. The direction of a converged component is set zero
+            p_vec[i] = if is_active[i_dim] {
+                r_vec[i] + beta[i_dim] * p_vec[i]
+            } else {
+                T::zero()
+            };
+        }
+    }
+    conv_hist
+}
+
+/// number of the iterations until `criterion` is satisfied for the first time,
+/// evaluated with the status reconstructed from the history of the solution
+/// * `x_hist` - solution of each iteration, starting from the zero vector
+/// * `precond` - `{v} <- [M]^{-1}{v}`
+#[cfg(test)]
+fn first_satisfied_iteration<P>(
+    criterion: &Criterion<f64>,
+    mat: &sparse_square::Matrix<f64>,
+    b_vec: &[f64],
+    x_hist: &[Vec<f64>],
+    precond: P,
+) -> usize
+where
+    P: Fn(&mut Vec<f64>),
+{
+    use crate::slice::dot;
+    let residual = |x_vec: &Vec<f64>| {
+        let mut r_vec = b_vec.to_vec();
+        sparse_square::mult_vec(&mut r_vec, 1., -1., mat, x_vec);
+        let mut pr_vec = r_vec.clone();
+        precond(&mut pr_vec);
+        (dot(&r_vec, &r_vec), dot(&r_vec, &pr_vec))
+    };
+    let (sqnorm_res, rpr) = residual(&x_hist[0]);
+    let mut status = Status::new(dot(b_vec, b_vec), sqnorm_res, rpr);
+    for k in 1..x_hist.len() {
+        let (sqnorm_res, rpr) = residual(&x_hist[k]);
+        let dx_vec: Vec<f64> = x_hist[k]
+            .iter()
+            .zip(&x_hist[k - 1])
+            .map(|(a, b)| a - b)
+            .collect();
+        let mut adx_vec = vec![0.; dx_vec.len()];
+        sparse_square::mult_vec(&mut adx_vec, 0., 1., mat, &dx_vec);
+        status.push(sqnorm_res, rpr, dot(&dx_vec, &adx_vec));
+        if criterion.is_satisfied(&status) {
+            return k;
+        }
+    }
+    usize::MAX
+}
+
+#[cfg(test)]
+fn criteria_for_test() -> Vec<Criterion<f64>> {
+    vec![
+        Criterion::RelativeResidual(1.0e-6),
+        Criterion::AbsoluteResidual(1.0e-4),
+        Criterion::RhsRelativeResidual(1.0e-5),
+        Criterion::PreconditionedResidual(1.0e-6),
+        Criterion::EnergyNormError {
+            tol: 1.0e-6,
+            delay: 4,
+        },
+        Criterion::Stagnation {
+            window: 2,
+            ratio: 0.7,
+        },
+        Criterion::Any(vec![
+            Criterion::RelativeResidual(1.0e-12),
+            Criterion::AbsoluteResidual(1.0e-3),
+        ]),
+        Criterion::All(vec![
+            Criterion::RelativeResidual(1.0e-3),
+            Criterion::EnergyNormError {
+                tol: 1.0e-8,
+                delay: 4,
+            },
+        ]),
+    ]
+}
+
+#[test]
+fn test_cg_criterion() {
+    let num_blk = 100;
+    let sparse = sparse_square::laplacian_grid(num_blk, 1, 0.1);
+    let b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    let solve = |criterion: &Criterion<f64>, x_hist: &mut Vec<Vec<f64>>| {
+        let mut r_vec = b_vec.clone();
+        let (mut u_vec, mut ap_vec, mut p_vec) = (vec![], vec![], vec![]);
+        x_hist.push(vec![0.; num_blk]);
+        conjugate_gradient_with_monitor(
+            &mut r_vec,
+            &mut u_vec,
+            &mut ap_vec,
+            &mut p_vec,
+            criterion,
+            200,
+            &sparse,
+            |_, _, x_vec| {
+                x_hist.push(x_vec.to_vec());
+                ControlFlow::Continue(())
+            },
+        );
+        u_vec
+    };
+    // history without the termination
+    let mut x_hist = vec![];
+    solve(&Criterion::AbsoluteResidual(0.), &mut x_hist);
+    for criterion in criteria_for_test().iter() {
+        let num_itr = first_satisfied_iteration(criterion, &sparse, &b_vec, &x_hist, |_| {});
+        assert!(num_itr > 1 && num_itr < 200);
+        let mut x_hist1 = vec![];
+        let u_vec = solve(criterion, &mut x_hist1);
+        assert_eq!(x_hist1.len(), num_itr + 1);
+        assert_eq!(u_vec, x_hist[num_itr]);
+    }
+}
+
+#[test]
+fn test_pcg_criterion() {
+    let sparse = sparse_square::laplacian_grid(30, 20, 0.01);
+    let num_blk = sparse.num_blk;
+    let b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    let mut ilu = sparse_ilu::Preconditioner::<f64>::new();
+    ilu.initialize_ilu0(&sparse);
+    sparse_ilu::copy_value(&mut ilu, &sparse);
+    sparse_ilu::decompose(&mut ilu);
+    let solve = |criterion: &Criterion<f64>, x_hist: &mut Vec<Vec<f64>>| {
+        let mut r_vec = b_vec.clone();
+        let (mut x_vec, mut pr_vec, mut p_vec) = (vec![], vec![], vec![]);
+        x_hist.push(vec![0.; num_blk]);
+        preconditioned_conjugate_gradient_with_monitor(
+            &mut r_vec,
+            &mut x_vec,
+            &mut pr_vec,
+            &mut p_vec,
+            criterion,
+            200,
+            &sparse,
+            &ilu,
+            |_, _, x_vec| {
+                x_hist.push(x_vec.to_vec());
+                ControlFlow::Continue(())
+            },
+        );
+    };
+    let mut x_hist = vec![];
+    solve(&Criterion::AbsoluteResidual(0.), &mut x_hist);
+    let precond = |v: &mut Vec<f64>| sparse_ilu::solve_preconditioning_vec(v, &ilu);
+    for criterion in criteria_for_test().iter() {
+        let num_itr = first_satisfied_iteration(criterion, &sparse, &b_vec, &x_hist, precond);
+        assert!(num_itr > 1 && num_itr < 200);
+        let mut x_hist1 = vec![];
+        solve(criterion, &mut x_hist1);
+        assert_eq!(x_hist1.len(), num_itr + 1);
+    }
+}
+
+#[test]
+fn test_conv_ratio_wrappers() {
+    // the wrappers taking `conv_ratio_tol` are compared with the original implementation
+    use crate::slice::{add_scaled_vector, dot, scale_and_add_vec};
+    let sparse = sparse_square::laplacian_grid(20, 10, 0.1);
+    let num_blk = sparse.num_blk;
+    let mut ilu = sparse_ilu::Preconditioner::<f64>::new();
+    ilu.initialize_ilu0(&sparse);
+    sparse_ilu::copy_value(&mut ilu, &sparse);
+    sparse_ilu::decompose(&mut ilu);
+    let tol = 1.0e-8;
+    // the rhs of the small scale and the one below the threshold of the initial residual
+    for scale in [1., 1.0e-6, 1.0e-12] {
+        let b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin() * scale).collect();
+        for use_ilu in [false, true] {
+            // original implementation
+            let mut r_vec = b_vec.clone();
+            let mut x_vec0 = vec![0.; num_blk];
+            let mut conv_hist0 = vec![];
+            let mut z_vec = r_vec.clone();
+            if use_ilu {
+                conv_hist0.push(dot(&r_vec, &r_vec).sqrt());
+                sparse_ilu::solve_preconditioning_vec(&mut z_vec, &ilu);
+            }
+            let sqnorm_res0 = dot(&r_vec, &r_vec);
+            let mut p_vec = z_vec.clone();
+            let mut rz = dot(&r_vec, &z_vec);
+            let mut ap_vec = vec![0.; num_blk];
+            for _ in 0..(if sqnorm_res0 < 1.0e-20 { 0 } else { 1000 }) {
+                sparse_square::mult_vec(&mut ap_vec, 0., 1., &sparse, &p_vec);
+                let alpha = rz / dot(&p_vec, &ap_vec);
+                add_scaled_vector(&mut x_vec0, alpha, &p_vec);
+                add_scaled_vector(&mut r_vec, -alpha, &ap_vec);
+                let sqnorm_res = dot(&r_vec, &r_vec);
+                let conv_ratio = (sqnorm_res * (1. / sqnorm_res0)).sqrt();
+                conv_hist0.push(if use_ilu {
+                    sqnorm_res.sqrt()
+                } else {
+                    conv_ratio
+                });
+                if conv_ratio < tol {
+                    break;
+                }
+                z_vec.clone_from(&r_vec);
+                if use_ilu {
+                    sparse_ilu::solve_preconditioning_vec(&mut z_vec, &ilu);
+                }
+                let rz1 = dot(&r_vec, &z_vec);
+                scale_and_add_vec(&mut p_vec, rz1 / rz, &z_vec);
+                rz = rz1;
+            }
+            // wrapper
+            let mut r_vec = b_vec.clone();
+            let (mut x_vec1, mut tmp0, mut tmp1) = (vec![], vec![], vec![]);
+            let conv_hist1 = if use_ilu {
+                preconditioned_conjugate_gradient(
+                    &mut r_vec,
+                    &mut x_vec1,
+                    &mut tmp0,
+                    &mut tmp1,
+                    tol,
+                    1000,
+                    &sparse,
+                    &ilu,
+                )
+            } else {
+                conjugate_gradient(
+                    &mut r_vec,
+                    &mut x_vec1,
+                    &mut tmp0,
+                    &mut tmp1,
+                    tol,
+                    1000,
+                    &sparse,
+                )
+            };
+            assert_eq!(conv_hist0, conv_hist1);
+            assert_eq!(x_vec0, x_vec1);
+        }
+    }
+}
+
+#[test]
+fn test_cg_monitor() {
+    let num_blk = 100;
+    let sparse = sparse_square::laplacian_grid(num_blk, 1, 0.1);
+    let mut r_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    let (mut u_vec, mut ap_vec, mut p_vec) = (vec![], vec![], vec![]);
+    let mut norm_res_hist = Vec::<f64>::new();
+    let mut norm_x = 0_f64;
+    let conv_hist = conjugate_gradient_with_monitor(
+        &mut r_vec,
+        &mut u_vec,
+        &mut ap_vec,
+        &mut p_vec,
+        &Criterion::RelativeResidual(1.0e-10),
+        1000,
+        &sparse,
+        |iitr, norm_res, x_vec| {
+            assert_eq!(iitr, norm_res_hist.len());
+            norm_res_hist.push(norm_res);
+            norm_x = crate::slice::dot(x_vec, x_vec).sqrt();
+            if iitr == 4 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        },
+    );
+    assert_eq!(conv_hist.len(), 5);
+    assert_eq!(norm_res_hist.len(), 5);
+    assert_eq!(norm_x, crate::slice::dot(&u_vec, &u_vec).sqrt());
+}
+
+#[test]
+fn test_cg_componentwise() {
+    let num_dim = 3;
+    for sparse in [
+        sparse_square::laplacian_grid(100, 1, 0.1),
+        sparse_square::irregular_test_matrix(100, true, 10.),
+    ] {
+        let num_blk = sparse.num_blk;
+        let rhs = |i_blk: usize, i_dim: usize| ((i_blk * (i_dim + 1)) as f64).sin();
+        let mut r_vec = vec![0_f64; num_blk * num_dim];
+        for i_blk in 0..num_blk {
+            for i_dim in 0..num_dim {
+                r_vec[i_blk * num_dim + i_dim] = rhs(i_blk, i_dim);
+            }
+        }
+        let (mut u_vec, mut ap_vec, mut p_vec) = (vec![], vec![], vec![]);
+        let criterion = Criterion::RelativeResidual(1.0e-10);
+        let conv_hists = conjugate_gradient_componentwise(
+            &mut r_vec,
+            &mut u_vec,
+            &mut ap_vec,
+            &mut p_vec,
+            &criterion,
+            1000,
+            &sparse,
+        );
+        // each component should follow the same convergence as the scalar CG
+        for i_dim in 0..num_dim {
+            let mut r_vec1: Vec<f64> = (0..num_blk).map(|i_blk| rhs(i_blk, i_dim)).collect();
+            let (mut u_vec1, mut ap_vec1, mut p_vec1) = (vec![], vec![], vec![]);
+            let conv_hist1 = conjugate_gradient_with_criterion(
+                &mut r_vec1,
+                &mut u_vec1,
+                &mut ap_vec1,
+                &mut p_vec1,
+                &criterion,
+                1000,
+                &sparse,
+            );
+            assert_eq!(conv_hists[i_dim].len(), conv_hist1.len());
+            for i_blk in 0..num_blk {
+                let diff = u_vec[i_blk * num_dim + i_dim] - u_vec1[i_blk];
+                assert!(diff.abs() < 1.0e-8);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_par_conjugate_gradient() {
+    let sparse = sparse_square::laplacian_grid(40, 30, 0.1);
+    let num_blk = sparse.num_blk;
+    let b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    let mut ilu = sparse_ilu::Preconditioner::<f64>::new();
+    ilu.initialize_ilu0(&sparse);
+    sparse_ilu::copy_value(&mut ilu, &sparse);
+    sparse_ilu::decompose(&mut ilu);
+    let criterion = Criterion::RelativeResidual(1.0e-8);
+    let (mut u_vec0, mut tmp0, mut tmp1) = (vec![], vec![], vec![]);
+    let conv_hist0 = conjugate_gradient_with_criterion(
+        &mut b_vec.clone(),
+        &mut u_vec0,
+        &mut tmp0,
+        &mut tmp1,
+        &criterion,
+        1000,
+        &sparse,
+    );
+    let (mut x_vec0, mut tmp0, mut tmp1) = (vec![], vec![], vec![]);
+    let pcg_hist0 = preconditioned_conjugate_gradient_with_criterion(
+        &mut b_vec.clone(),
+        &mut x_vec0,
+        &mut tmp0,
+        &mut tmp1,
+        &criterion,
+        1000,
+        &sparse,
+        &ilu,
+    );
+    // the threaded products give the same result as the serial ones
+    for num_thread in [1, 4] {
+        let (mut u_vec1, mut tmp0, mut tmp1) = (vec![], vec![], vec![]);
+        let conv_hist1 = par_conjugate_gradient_with_criterion(
+            &mut b_vec.clone(),
+            &mut u_vec1,
+            &mut tmp0,
+            &mut tmp1,
+            &criterion,
+            1000,
+            &sparse,
+            num_thread,
+        );
+        assert_eq!(conv_hist0, conv_hist1);
+        assert_eq!(u_vec0, u_vec1);
+        let (mut x_vec1, mut tmp0, mut tmp1) = (vec![], vec![], vec![]);
+        let pcg_hist1 = par_preconditioned_conjugate_gradient_with_criterion(
+            &mut b_vec.clone(),
+            &mut x_vec1,
+            &mut tmp0,
+            &mut tmp1,
+            &criterion,
+            1000,
+            &sparse,
+            &ilu,
+            num_thread,
+        );
+        assert_eq!(pcg_hist0, pcg_hist1);
+        assert_eq!(x_vec0, x_vec1);
+    }
 }
@@ -0,0 +1,480 @@
+//! sparse approximate inverse preconditioners applied by the sparse matrix-vector products alone.
+//! * factorized sparse approximate inverse (FSAI) `[G]^T[G] ~ [A]^{-1}` for the symmetric positive definite matrix
+//! * left sparse approximate inverse (SPAI) `[M] ~ [A]^{-1}` minimizing `||[M][A] - [I]||_F` for the general matrix
+//!
+//! The non-zero pattern is taken from the power of the matrix `A^power`.
+
+use crate::sparse_square::Matrix;
+
+/// approximate inverse given as the product of the sparse matrices `[F_{n-1}]...[F_1][F_0]`
+pub struct Preconditioner<T> {
+    pub factors: Vec<Matrix<T>>,
+}
+
+/// solve `[a]{x} = {b}` for the small dense symmetric positive definite matrix by the Cholesky factorization
+/// * `a` - row-major `n x n` matrix. Overwritten by the factor
+/// * `b` - right-hand side as input, solution as output
+fn solve_dense_spd<T>(a: &mut [T], b: &mut [T]) -> Result<(), String>
+where
+    T: num_traits::Float,
+{
+    let n = b.len();
+    assert_eq!(a.len(), n * n);
+    for j in 0..n {
+        let mut d = a[j * n + j];
+        for k in 0..j {
+            d = d - a[j * n + k] * a[j * n + k];
+        }
+        if d <= T::zero() {
+            return Err("the local matrix is not positive definite".to_string());
+        }
+        let d = d.sqrt();
+        a[j * n + j] = d;
+        for i in j + 1..n {
+            let mut v = a[i * n + j];
+            for k in 0..j {
+                v = v - a[i * n + k] * a[j * n + k];
+            }
+            a[i * n + j] = v / d;
+        }
+    }
+    for i in 0..n {
+        let mut v = b[i];
+        for k in 0..i {
+            v = v - a[i * n + k] * b[k];
+        }
+        b[i] = v / a[i * n + i];
+    }
+    for i in (0..n).rev() {
+        let mut v = b[i];
+        for k in i + 1..n {
+            v = v - a[k * n + i] * b[k];
+        }
+        b[i] = v / a[i * n + i];
+    }
+    Ok(())
+}
+
+/// solve the small dense least squares problem `min ||[c]{x} - {b}||` by the Householder QR factorization
+/// * `c` - row-major `m x n` matrix with `m >= n` and the full column rank. Overwritten
+/// * `b` - right-hand side of the length `m` as input. The first `n` entries are the solution as output
+fn solve_dense_least_squares<T>(c: &mut [T], b: &mut [T], n: usize) -> Result<(), String>
+where
+    T: num_traits::Float,
+{
+    let m = b.len();
+    assert_eq!(c.len(), m * n);
+    assert!(m >= n);
+    let mut c_max = T::zero();
+    for &v in c.iter() {
+        c_max = c_max.max(v.abs());
+    }
+    for k in 0..n {
+        // Householder vector {v} = {x} - alpha {e_k} overwrites the column k
+        let norm = (k..m)
+            .fold(T::zero(), |sum, i| sum + c[i * n + k] * c[i * n + k])
+            .sqrt();
+        if norm <= T::epsilon() * c_max * T::from(m).unwrap() {
+            return Err("the local matrix is rank deficient".to_string());
+        }
+        let alpha = if c[k * n + k] > T::zero() {
+            -norm
+        } else {
+            norm
+        };
+        c[k * n + k] = c[k * n + k] - alpha;
+        let vtv = (k..m).fold(T::zero(), |sum, i| sum + c[i * n + k] * c[i * n + k]);
+        let two = T::one() + T::one();
+        for j in k + 1..n {
+            let vtc = (k..m).fold(T::zero(), |sum, i| sum + c[i * n + k] * c[i * n + j]);
+            let s = two * vtc / vtv;
+            for i in k..m {
+                c[i * n + j] = c[i * n + j] - s * c[i * n + k];
+            }
+        }
+        let vtb = (k..m).fold(T::zero(), |sum, i| sum + c[i * n + k] * b[i]);
+        let s = two * vtb / vtv;
+        for i in k..m {
+            b[i] = b[i] - s * c[i * n + k];
+        }
+        c[k * n + k] = alpha; // diagonal of [R]
+    }
+    for i in (0..n).rev() {
+        let mut v = b[i];
+        for j in i + 1..n {
+            v = v - c[i * n + j] * b[j];
+        }
+        b[i] = v / c[i * n + i];
+    }
+    Ok(())
+}
+
+/// compute the values of the rows of the matrix with the given pattern concurrently
+/// * `row_value` - `row_value(i_row, idx2val_row, row_val, col2local)` sets the off-diagonal and diagonal values of the row.
+///   `col2local` is a working buffer of the thread filled with `usize::MAX` on input and output
+fn par_fill_rows<T, F>(
+    row2idx: Vec<usize>,
+    idx2col: Vec<usize>,
+    num_thread: usize,
+    row_value: F,
+) -> Result<Matrix<T>, String>
+where
+    T: num_traits::Float + Send + Sync,
+    F: Fn(usize, &mut [T], &mut T, &mut [usize]) -> Result<(), String> + Sync,
+{
+    let num_blk = row2idx.len() - 1;
+    let mut idx2val = vec![T::zero(); idx2col.len()];
+    let mut row2val = vec![T::zero(); num_blk];
+    let part2row = crate::sparse_square::partition_rows(&row2idx, num_thread);
+    let fill_rows = |i_row0: usize, idx2val: &mut [T], row2val: &mut [T]| {
+        let mut col2local = vec![usize::MAX; num_blk];
+        let idx0 = row2idx[i_row0];
+        for (i0, row_val) in row2val.iter_mut().enumerate() {
+            let i_row = i_row0 + i0;
+            let idxs = row2idx[i_row] - idx0..row2idx[i_row + 1] - idx0;
+            row_value(i_row, &mut idx2val[idxs], row_val, &mut col2local)?;
+        }
+        Ok(())
+    };
+    let results: Vec<Result<(), String>> = std::thread::scope(|s| {
+        let mut idx2val_rest = idx2val.as_mut_slice();
+        let mut row2val_rest = row2val.as_mut_slice();
+        let mut handles = vec![];
+        for rows in part2row.windows(2) {
+            let (idx2val_part, tail) =
+                idx2val_rest.split_at_mut(row2idx[rows[1]] - row2idx[rows[0]]);
+            idx2val_rest = tail;
+            let (row2val_part, tail) = row2val_rest.split_at_mut(rows[1] - rows[0]);
+            row2val_rest = tail;
+            let (i_row0, fill_rows) = (rows[0], &fill_rows);
+            if part2row.len() <= 2 {
+                return vec![fill_rows(i_row0, idx2val_part, row2val_part)];
+            }
+            handles.push(s.spawn(move || fill_rows(i_row0, idx2val_part, row2val_part)));
+        }
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    results.into_iter().collect::<Result<Vec<()>, String>>()?;
+    Ok(Matrix {
+        num_blk,
+        row2idx,
+        idx2col,
+        idx2val,
+        row2val,
+    })
+}
+
+/// transpose of the sparse matrix with sorted columns
+fn transpose<T>(a: &Matrix<T>) -> Matrix<T>
+where
+    T: Copy,
+{
+    let num_blk = a.num_blk;
+    let mut row2idx = vec![0_usize; num_blk + 1];
+    for &j_col in &a.idx2col {
+        row2idx[j_col + 1] += 1;
+    }
+    for i_row in 0..num_blk {
+        row2idx[i_row + 1] += row2idx[i_row];
+    }
+    let mut idx2col = vec![0_usize; a.idx2col.len()];
+    let mut idx2val = a.idx2val.clone();
+    let mut row2pos = row2idx[..num_blk].to_vec();
+    for i_row in 0..num_blk {
+        for idx in a.row2idx[i_row]..a.row2idx[i_row + 1] {
+            let j_col = a.idx2col[idx];
+            idx2col[row2pos[j_col]] = i_row;
+            idx2val[row2pos[j_col]] = a.idx2val[idx];
+            row2pos[j_col] += 1;
+        }
+    }
+    Matrix {
+        num_blk,
+        row2idx,
+        idx2col,
+        idx2val,
+        row2val: a.row2val.clone(),
+    }
+}
+
+/// factorized sparse approximate inverse (FSAI) of Kolotilina and Yeremin for the symmetric positive definite matrix.
+/// The lower triangular `[G]` with the pattern of the lower part of `A^power` minimizes `||[I] - [G][L]||_F`
+/// where `[A] = [L][L]^T`, which is computed independently for each row.
+/// * `power` - exponent of the matrix for the non-zero pattern of `[G]`
+/// * `num_thread` - number of the threads for the construction
+/// * return - the preconditioner with the factors `[G]` and `[G]^T`
+pub fn fsai<T>(a: &Matrix<T>, power: usize, num_thread: usize) -> Result<Preconditioner<T>, String>
+where
+    T: num_traits::Float + Send + Sync,
+{
+    let (pow_row2idx, pow_idx2col) =
+        crate::sparse_matrix_multiplication::symbolic_power(&a.row2idx, &a.idx2col, power);
+    // lower part of the pattern
+    let mut row2idx = vec![0_usize; a.num_blk + 1];
+    let mut idx2col = Vec::<usize>::with_capacity(pow_idx2col.len() / 2);
+    for i_row in 0..a.num_blk {
+        for &j_col in &pow_idx2col[pow_row2idx[i_row]..pow_row2idx[i_row + 1]] {
+            if j_col < i_row {
+                idx2col.push(j_col);
+            }
+        }
+        row2idx[i_row + 1] = idx2col.len();
+    }
+    let cols_of_row = |i_row: usize| &idx2col[row2idx[i_row]..row2idx[i_row + 1]];
+    let g_mat = par_fill_rows(
+        row2idx.clone(),
+        idx2col.clone(),
+        num_thread,
+        |i_row, g_row, g_ii, col2local| {
+            // the local pattern is the lower columns followed by the diagonal
+            let cols = cols_of_row(i_row);
+            let n = cols.len() + 1;
+            for (k, &j_col) in cols.iter().chain(std::iter::once(&i_row)).enumerate() {
+                col2local[j_col] = k;
+            }
+            let mut a_local = vec![T::zero(); n * n];
+            for (k, &j_row) in cols.iter().chain(std::iter::once(&i_row)).enumerate() {
+                a_local[k * n + k] = a.row2val[j_row];
+                for idx in a.row2idx[j_row]..a.row2idx[j_row + 1] {
+                    let l = col2local[a.idx2col[idx]];
+                    if l != usize::MAX {
+                        a_local[k * n + l] = a.idx2val[idx];
+                    }
+                }
+            }
+            for &j_col in cols.iter().chain(std::iter::once(&i_row)) {
+                col2local[j_col] = usize::MAX;
+            }
+            let mut y = vec![T::zero(); n];
+            y[n - 1] = T::one();
+            solve_dense_spd(&mut a_local, &mut y)?;
+            let scale = T::one() / y[n - 1].sqrt();
+            for (g, &v) in g_row.iter_mut().zip(&y) {
+                *g = v * scale;
+            }
+            *g_ii = y[n - 1] * scale;
+            Ok(())
+        },
+    )?;
+    let gt_mat = transpose(&g_mat);
+    Ok(Preconditioner {
+        factors: vec![g_mat, gt_mat],
+    })
+}
+
+/// left sparse approximate inverse (SPAI) with the pattern of `A^power`.
+/// Each row `{m_i}` minimizes `||[A]^T{m_i} - {e_i}||` by the QR factorization of the small least squares problem,
+/// which does not square the condition number as the normal equation does.
+/// * `power` - exponent of the matrix for the non-zero pattern of `[M]`
+/// * `num_thread` - number of the threads for the construction
+/// * return - the preconditioner with the factor `[M]`
+pub fn spai<T>(a: &Matrix<T>, power: usize, num_thread: usize) -> Result<Preconditioner<T>, String>
+where
+    T: num_traits::Float + Send + Sync,
+{
+    let (row2idx, idx2col) =
+        crate::sparse_matrix_multiplication::symbolic_power(&a.row2idx, &a.idx2col, power);
+    let cols_of_row = |i_row: usize| &idx2col[row2idx[i_row]..row2idx[i_row + 1]];
+    let m_mat = par_fill_rows(
+        row2idx.clone(),
+        idx2col.clone(),
+        num_thread,
+        |i_row, m_row, m_ii, col2local| {
+            // rows J of [A] combined by {m_i} and the columns I touched by them
+            let rows_j: Vec<usize> = cols_of_row(i_row)
+                .iter()
+                .cloned()
+                .chain(std::iter::once(i_row))
+                .collect();
+            let mut cols_i = Vec::<usize>::new();
+            for &j_row in &rows_j {
+                let cols = a.idx2col[a.row2idx[j_row]..a.row2idx[j_row + 1]].iter();
+                for &k_col in cols.chain(std::iter::once(&j_row)) {
+                    if col2local[k_col] == usize::MAX {
+                        col2local[k_col] = cols_i.len();
+                        cols_i.push(k_col);
+                    }
+                }
+            }
+            // dense [C] = [A](J, I)^T
+            let (nj, ni) = (rows_j.len(), cols_i.len());
+            let mut c_mat = vec![T::zero(); ni * nj];
+            for (k, &j_row) in rows_j.iter().enumerate() {
+                c_mat[col2local[j_row] * nj + k] = a.row2val[j_row];
+                for idx in a.row2idx[j_row]..a.row2idx[j_row + 1] {
+                    c_mat[col2local[a.idx2col[idx]] * nj + k] = a.idx2val[idx];
+                }
+            }
+            // least squares [C]{m} = {e_i}
+            let mut rhs = vec![T::zero(); ni];
+            rhs[col2local[i_row]] = T::one();
+            for &k_col in &cols_i {
+                col2local[k_col] = usize::MAX;
+            }
+            solve_dense_least_squares(&mut c_mat, &mut rhs, nj)?;
+            for (m, &v) in m_row.iter_mut().zip(&rhs) {
+                *m = v;
+            }
+            *m_ii = rhs[nj - 1];
+            Ok(())
+        },
+    )?;
+    Ok(Preconditioner {
+        factors: vec![m_mat],
+    })
+}
+
+/// `{vec} <- [F_{n-1}]...[F_1][F_0]{vec}` by the multi-threaded sparse matrix-vector products
+/// * `tmp_vec` - working buffer resized to the length of `vec`
+/// * `num_thread` - number of the threads. Computed on the current thread if it is one
+pub fn solve_preconditioning_vec<T>(
+    vec: &mut [T],
+    pre: &Preconditioner<T>,
+    tmp_vec: &mut Vec<T>,
+    num_thread: usize,
+) where
+    T: num_traits::Float + std::ops::MulAssign + std::ops::AddAssign + Send + Sync,
+{
+    tmp_vec.resize(vec.len(), T::zero());
+    for factor in &pre.factors {
+        crate::sparse_square::par_mult_vec(tmp_vec, T::zero(), T::one(), factor, vec, num_thread);
+        vec.copy_from_slice(tmp_vec);
+    }
+}
+
+#[test]
+fn test_sparse_approximate_inverse() {
+    let sparse = crate::sparse_square::laplacian_grid(14, 12, 0.1);
+    let num_blk = sparse.num_blk;
+    // || [P][A] - [I] ||_F computed column by column
+    let error = |pre: &Preconditioner<f64>| {
+        let mut tmp_vec = vec![];
+        let mut sum = 0.;
+        for j in 0..num_blk {
+            let mut e_vec = vec![0.; num_blk];
+            e_vec[j] = 1.;
+            let mut col = vec![0.; num_blk];
+            crate::sparse_square::mult_vec(&mut col, 0., 1., &sparse, &e_vec);
+            solve_preconditioning_vec(&mut col, pre, &mut tmp_vec, 1);
+            col[j] -= 1.;
+            sum += col.iter().map(|v| v * v).sum::<f64>();
+        }
+        sum.sqrt()
+    };
+    let jacobi = Preconditioner {
+        factors: vec![Matrix {
+            num_blk,
+            row2idx: vec![0; num_blk + 1],
+            idx2col: vec![],
+            idx2val: vec![],
+            row2val: sparse.row2val.iter().map(|d| 1. / d).collect(),
+        }],
+    };
+    let error_jacobi = error(&jacobi);
+    for (i_type, pre) in [fsai::<f64>, spai::<f64>].iter().enumerate() {
+        let pre1 = pre(&sparse, 1, 1).unwrap();
+        let pre2 = pre(&sparse, 2, 1).unwrap();
+        let (error1, error2) = (error(&pre1), error(&pre2));
+        // the larger pattern gives the better approximation
+        assert!(error1 < error_jacobi && error2 < error1);
+        // multi-threaded construction gives the same result
+        let pre3 = pre(&sparse, 2, 4).unwrap();
+        for (f2, f3) in pre2.factors.iter().zip(&pre3.factors) {
+            assert_eq!(f2.idx2val, f3.idx2val);
+            assert_eq!(f2.row2val, f3.row2val);
+        }
+        if i_type == 0 {
+            // [G] is lower triangular
+            let g_mat = &pre1.factors[0];
+            for i_row in 0..num_blk {
+                for &j_col in &g_mat.idx2col[g_mat.row2idx[i_row]..g_mat.row2idx[i_row + 1]] {
+                    assert!(j_col < i_row);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_sparse_approximate_inverse_irregular() {
+    use crate::solver_sparse::preconditioned_conjugate_gradient_with_operators;
+    use std::ops::ControlFlow;
+    // FSAI preconditioned CG for the badly scaled symmetric positive definite matrix
+    let sparse = crate::sparse_square::irregular_test_matrix(400, true, 100.);
+    let num_blk = sparse.num_blk;
+    let pre = fsai(&sparse, 2, 2).unwrap();
+    let b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    let criterion = crate::stopping_criterion::Criterion::RelativeResidual(1.0e-10);
+    let mut num_itrs = vec![];
+    for is_fsai in [true, false] {
+        let mut r_vec = b_vec.clone();
+        let (mut x_vec, mut pr_vec, mut p_vec) = (vec![], vec![], vec![]);
+        let mut tmp_vec = vec![];
+        let conv_hist = preconditioned_conjugate_gradient_with_operators(
+            &mut r_vec,
+            &mut x_vec,
+            &mut pr_vec,
+            &mut p_vec,
+            &criterion,
+            1000,
+            |y: &mut [f64], x: &[f64]| crate::sparse_square::mult_mat(y, 0., 1., &sparse, x),
+            |v: &mut [f64]| {
+                if is_fsai {
+                    solve_preconditioning_vec(v, &pre, &mut tmp_vec, 1);
+                } else {
+                    // Jacobi
+                    v.iter_mut().zip(&sparse.row2val).for_each(|(v, d)| *v /= d);
+                }
+            },
+            |_, _, _| ControlFlow::Continue(()),
+        );
+        let err = crate::sparse_square::componentwise_backward_error(&sparse, &x_vec, &b_vec);
+        assert!(err < 1.0e-8, "{}", err);
+        num_itrs.push(conv_hist.len());
+    }
+    assert!(num_itrs[0] < num_itrs[1]);
+    // SPAI for the badly scaled unsymmetric matrix
+    let sparse = crate::sparse_square::irregular_test_matrix(300, false, 100.);
+    let num_blk = sparse.num_blk;
+    let mut tmp_vec = vec![];
+    // || [M][A]{v} - {v} || / ||{v}|| for the vector whose entries have the same magnitude
+    let mut error = |pre: &Preconditioner<f64>| {
+        let v_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64 * 0.7).cos()).collect();
+        let mut w_vec = vec![0.; num_blk];
+        crate::sparse_square::mult_vec(&mut w_vec, 0., 1., &sparse, &v_vec);
+        solve_preconditioning_vec(&mut w_vec, pre, &mut tmp_vec, 1);
+        let num = w_vec
+            .iter()
+            .zip(&v_vec)
+            .map(|(w, v)| (w - v) * (w - v))
+            .sum::<f64>();
+        let den = v_vec.iter().map(|v| v * v).sum::<f64>();
+        (num / den).sqrt()
+    };
+    let jacobi = Preconditioner {
+        factors: vec![Matrix {
+            num_blk,
+            row2idx: vec![0; num_blk + 1],
+            idx2col: vec![],
+            idx2val: vec![],
+            row2val: sparse.row2val.iter().map(|d| 1. / d).collect(),
+        }],
+    };
+    let error_jacobi = error(&jacobi);
+    let error1 = error(&spai(&sparse, 1, 1).unwrap());
+    let error2 = error(&spai(&sparse, 2, 3).unwrap());
+    assert!(error2 < error1 && error1 < error_jacobi);
+    // the rank deficient local problem is reported
+    let mut singular = sparse.clone();
+    let i_row = 5;
+    singular.row2val[i_row] = 0.;
+    for idx in singular.row2idx[i_row]..singular.row2idx[i_row + 1] {
+        singular.idx2val[idx] = 0.;
+    }
+    for idx in 0..singular.idx2col.len() {
+        if singular.idx2col[idx] == i_row {
+            singular.idx2val[idx] = 0.;
+        }
+    }
+    assert!(spai(&singular, 1, 1).is_err());
+}
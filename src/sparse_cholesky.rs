@@ -0,0 +1,442 @@
+//! Sparse direct Cholesky factorization `P A P^T = L L^T` for symmetric positive definite matrices.
+//! The symbolic analysis (ordering and non-zero pattern of `L`) is separated from the numeric factorization,
+//! so that a matrix whose values change but pattern does not can be refactorized cheaply with `refactorize`.
+//! The numeric factorization is left-looking: each column of `L` is computed by gathering the column of `A`
+//! and subtracting the contributions of the columns to its left that have a non-zero in its row.
+//! Those columns are found without the search by the linked lists of the columns keyed by their next row.
+
+/// Cholesky factorization where the lower triangular factor `L` is stored in the Compressed Column Storage (CCS)
+/// * `new2old` - fill-reducing ordering (i.e., permutation `P`)
+/// * `parent` - elimination tree (`usize::MAX` for the roots)
+/// * `col2idx`, `idx2row`, `idx2val` - strictly lower triangular part of `L`
+/// * `col2val` - diagonal of `L`
+pub struct Factorization<T> {
+    pub num_blk: usize,
+    pub new2old: Vec<usize>,
+    pub old2new: Vec<usize>,
+    pub parent: Vec<usize>,
+    pub col2idx: Vec<usize>,
+    pub idx2row: Vec<usize>,
+    pub idx2val: Vec<T>,
+    pub col2val: Vec<T>,
+    // non-zero pattern of `A` given in the symbolic analysis
+    a_row2idx: Vec<usize>,
+    a_idx2col: Vec<usize>,
+    // strictly lower triangular part of `P A P^T` in the CCS format.
+    // The row indices are the new ones, and `a_idx2src` is the position in the `idx2val` of `A`
+    a_col2idx: Vec<usize>,
+    a_idx2row: Vec<usize>,
+    a_idx2src: Vec<usize>,
+}
+
+impl<T> Factorization<T>
+where
+    T: Copy + num_traits::Zero,
+{
+    pub fn new() -> Self {
+        Factorization {
+            num_blk: 0,
+            new2old: vec![],
+            old2new: vec![],
+            parent: vec![],
+            col2idx: vec![0],
+            idx2row: vec![],
+            idx2val: vec![],
+            col2val: vec![],
+            a_row2idx: vec![0],
+            a_idx2col: vec![],
+            a_col2idx: vec![0],
+            a_idx2row: vec![],
+            a_idx2src: vec![],
+        }
+    }
+
+    /// symbolic analysis using the minimum degree ordering
+    /// * `row2idx`, `idx2col` - non-zero pattern of the symmetric matrix (same as `sparse_square::Matrix`)
+    pub fn symbolic_initialization(&mut self, row2idx: &[usize], idx2col: &[usize]) {
+        let (sym_row2idx, sym_idx2col) = crate::ordering::symmetrize_pattern(row2idx, idx2col);
+        let new2old = crate::ordering::minimum_degree::minimum_degree(&sym_row2idx, &sym_idx2col);
+        self.symbolic_initialization_with_permutation(row2idx, idx2col, new2old);
+    }
+
+    /// symbolic analysis using the given ordering
+    /// * `new2old` - permutation of the rows and columns
+    pub fn symbolic_initialization_with_permutation(
+        &mut self,
+        row2idx: &[usize],
+        idx2col: &[usize],
+        new2old: Vec<usize>,
+    ) {
+        let num_blk = row2idx.len() - 1;
+        assert_eq!(new2old.len(), num_blk);
+        self.num_blk = num_blk;
+        self.old2new = crate::ordering::inverse_permutation(&new2old);
+        self.new2old = new2old;
+        self.parent = elimination_tree(row2idx, idx2col, &self.new2old, &self.old2new);
+        // count the entries in each column of L
+        let mut col2cnt = vec![0_usize; num_blk];
+        let mut reach = Reach::new(num_blk);
+        for k in 0..num_blk {
+            for &j in reach.row_pattern(k, row2idx, idx2col, self) {
+                col2cnt[j] += 1;
+            }
+        }
+        self.col2idx = vec![0_usize; num_blk + 1];
+        for (j, cnt) in col2cnt.iter().enumerate() {
+            self.col2idx[j + 1] = self.col2idx[j] + cnt;
+        }
+        // set the row indices. They are sorted in each column because the rows are visited in the increasing order.
+        self.idx2row = vec![0_usize; self.col2idx[num_blk]];
+        let mut col2pos = self.col2idx[..num_blk].to_vec();
+        for k in 0..num_blk {
+            for &j in reach.row_pattern(k, row2idx, idx2col, self) {
+                self.idx2row[col2pos[j]] = k;
+                col2pos[j] += 1;
+            }
+        }
+        self.idx2val = vec![T::zero(); self.idx2row.len()];
+        self.col2val = vec![T::zero(); num_blk];
+        self.a_row2idx = row2idx.to_vec();
+        self.a_idx2col = idx2col.to_vec();
+        // columns of the strictly lower triangular part of `P A P^T` with the rows in the increasing order
+        self.a_col2idx = vec![0_usize; num_blk + 1];
+        for (i, &i_old) in self.new2old.iter().enumerate() {
+            for &j_old in &idx2col[row2idx[i_old]..row2idx[i_old + 1]] {
+                let j = self.old2new[j_old];
+                if j < i {
+                    self.a_col2idx[j + 1] += 1;
+                }
+            }
+        }
+        for j in 0..num_blk {
+            self.a_col2idx[j + 1] += self.a_col2idx[j];
+        }
+        let num_idx = self.a_col2idx[num_blk];
+        self.a_idx2row = vec![0_usize; num_idx];
+        self.a_idx2src = vec![0_usize; num_idx];
+        let mut col2pos = self.a_col2idx[..num_blk].to_vec();
+        for (i, &i_old) in self.new2old.iter().enumerate() {
+            let idxs = row2idx[i_old]..row2idx[i_old + 1];
+            for (idx, &j_old) in idxs.clone().zip(&idx2col[idxs]) {
+                let j = self.old2new[j_old];
+                if j < i {
+                    self.a_idx2row[col2pos[j]] = i;
+                    self.a_idx2src[col2pos[j]] = idx;
+                    col2pos[j] += 1;
+                }
+            }
+        }
+    }
+
+    /// number of the non-zero entries in `L` including the diagonal
+    pub fn num_nonzero(&self) -> usize {
+        self.idx2row.len() + self.num_blk
+    }
+}
+
+impl<T> Default for Factorization<T>
+where
+    T: Copy + num_traits::Zero,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// elimination tree of `P A P^T`
+fn elimination_tree(
+    row2idx: &[usize],
+    idx2col: &[usize],
+    new2old: &[usize],
+    old2new: &[usize],
+) -> Vec<usize> {
+    let num_blk = new2old.len();
+    let mut parent = vec![usize::MAX; num_blk];
+    let mut ancestor = vec![usize::MAX; num_blk];
+    for (k, &k_old) in new2old.iter().enumerate() {
+        for &j_old in &idx2col[row2idx[k_old]..row2idx[k_old + 1]] {
+            let mut i = old2new[j_old];
+            // traverse from i to the root with the path compression
+            while i != usize::MAX && i < k {
+                let i_next = ancestor[i];
+                ancestor[i] = k;
+                if i_next == usize::MAX {
+                    parent[i] = k;
+                }
+                i = i_next;
+            }
+        }
+    }
+    parent
+}
+
+/// working buffer to compute the non-zero pattern of a row of `L`
+struct Reach {
+    flag: Vec<usize>,
+    stack: Vec<usize>,
+    path: Vec<usize>,
+}
+
+impl Reach {
+    fn new(num_blk: usize) -> Self {
+        Reach {
+            flag: vec![usize::MAX; num_blk],
+            stack: vec![0; num_blk],
+            path: Vec::with_capacity(num_blk),
+        }
+    }
+
+    /// non-zero pattern of the `k`-th row of `L` (excluding the diagonal) in the topological order,
+    /// which is the set of the nodes reachable from the entries of `k`-th row of `P A P^T` in the elimination tree
+    fn row_pattern<T>(
+        &mut self,
+        k: usize,
+        row2idx: &[usize],
+        idx2col: &[usize],
+        factor: &Factorization<T>,
+    ) -> &[usize] {
+        let num_blk = factor.num_blk;
+        let mut top = num_blk;
+        self.flag[k] = k;
+        let k_old = factor.new2old[k];
+        for &j_old in &idx2col[row2idx[k_old]..row2idx[k_old + 1]] {
+            let mut i = factor.old2new[j_old];
+            if i > k {
+                continue;
+            }
+            self.path.clear();
+            while self.flag[i] != k {
+                self.path.push(i);
+                self.flag[i] = k;
+                i = factor.parent[i];
+            }
+            while let Some(i) = self.path.pop() {
+                top -= 1;
+                self.stack[top] = i;
+            }
+        }
+        &self.stack[top..num_blk]
+    }
+}
+
+/// numeric factorization
+/// * `a` - symmetric positive definite matrix with the same non-zero pattern as the one used in the symbolic analysis
+pub fn decompose<T>(
+    factor: &mut Factorization<T>,
+    a: &crate::sparse_square::Matrix<T>,
+) -> Result<(), String>
+where
+    T: num_traits::Float + std::ops::SubAssign,
+{
+    assert_eq!(a.row2idx, factor.a_row2idx);
+    assert_eq!(a.idx2col, factor.a_idx2col);
+    refactorize(factor, &a.row2val, &a.idx2val)
+}
+
+/// numeric factorization from the values of the matrix only, reusing the symbolic analysis
+/// * `row2val` - diagonal of `A`
+/// * `idx2val` - off-diagonal values of `A` in the order of `idx2col` given in the symbolic analysis
+pub fn refactorize<T>(
+    factor: &mut Factorization<T>,
+    row2val: &[T],
+    idx2val: &[T],
+) -> Result<(), String>
+where
+    T: num_traits::Float + std::ops::SubAssign,
+{
+    let num_blk = factor.num_blk;
+    assert_eq!(row2val.len(), num_blk);
+    assert_eq!(idx2val.len(), factor.a_idx2col.len());
+    let mut x = vec![T::zero(); num_blk];
+    // position in the column `k` of `L` of the next row to be updated by the column `k`
+    let mut col2next = factor.col2idx[..num_blk].to_vec();
+    // linked lists of the columns whose next row is the key
+    let mut row2head = vec![usize::MAX; num_blk];
+    let mut col2link = vec![usize::MAX; num_blk];
+    for j in 0..num_blk {
+        let j_old = factor.new2old[j];
+        // scatter the j-th column of P A P^T below the diagonal
+        for idx in factor.a_col2idx[j]..factor.a_col2idx[j + 1] {
+            x[factor.a_idx2row[idx]] = idx2val[factor.a_idx2src[idx]];
+        }
+        let mut d = row2val[j_old];
+        // subtract the columns `k < j` where `L(j, k)` is non-zero
+        let mut k = row2head[j];
+        while k != usize::MAX {
+            let k_next = col2link[k];
+            let pos = col2next[k];
+            debug_assert_eq!(factor.idx2row[pos], j);
+            let l_jk = factor.idx2val[pos];
+            d -= l_jk * l_jk;
+            for idx in pos + 1..factor.col2idx[k + 1] {
+                x[factor.idx2row[idx]] -= factor.idx2val[idx] * l_jk;
+            }
+            // move the column `k` to the list of its next row
+            col2next[k] = pos + 1;
+            if pos + 1 < factor.col2idx[k + 1] {
+                let i = factor.idx2row[pos + 1];
+                col2link[k] = row2head[i];
+                row2head[i] = k;
+            }
+            k = k_next;
+        }
+        if d <= T::zero() || d.is_nan() {
+            return Err(format!(
+                "the matrix is not positive definite (row {})",
+                j_old
+            ));
+        }
+        let l_jj = d.sqrt();
+        factor.col2val[j] = l_jj;
+        // gather the j-th column of L
+        for idx in factor.col2idx[j]..factor.col2idx[j + 1] {
+            let i = factor.idx2row[idx];
+            factor.idx2val[idx] = x[i] / l_jj;
+            x[i] = T::zero();
+        }
+        if factor.col2idx[j] < factor.col2idx[j + 1] {
+            let i = factor.idx2row[factor.col2idx[j]];
+            col2link[j] = row2head[i];
+            row2head[i] = j;
+        }
+    }
+    Ok(())
+}
+
+/// solve `A x = b` using the factorization
+/// * `vec` - `b` as input and `x` as output
+pub fn solve_vec<T>(factor: &Factorization<T>, vec: &mut [T])
+where
+    T: num_traits::Float + std::ops::SubAssign,
+{
+    assert_eq!(vec.len(), factor.num_blk);
+    solve_mat(factor, vec);
+}
+
+/// solve `A X = B` for multiple right-hand sides using the factorization
+/// * `mat` - `B` as input and `X` as output. Row-major `num_blk x num_rhs` matrix (same layout as `sparse_square::mult_mat`)
+pub fn solve_mat<T>(factor: &Factorization<T>, mat: &mut [T])
+where
+    T: num_traits::Float + std::ops::SubAssign,
+{
+    let num_blk = factor.num_blk;
+    if num_blk == 0 {
+        assert!(mat.is_empty());
+        return;
+    }
+    let num_rhs = mat.len() / num_blk;
+    assert_eq!(mat.len(), num_blk * num_rhs);
+    // y = P b
+    let mut y = vec![T::zero(); mat.len()];
+    for (i_new, &i_old) in factor.new2old.iter().enumerate() {
+        y[i_new * num_rhs..(i_new + 1) * num_rhs]
+            .copy_from_slice(&mat[i_old * num_rhs..(i_old + 1) * num_rhs]);
+    }
+    // forward substitution L y = y
+    for j in 0..num_blk {
+        let inv_l_jj = T::one() / factor.col2val[j];
+        for i_rhs in 0..num_rhs {
+            y[j * num_rhs + i_rhs] = y[j * num_rhs + i_rhs] * inv_l_jj;
+        }
+        for idx in factor.col2idx[j]..factor.col2idx[j + 1] {
+            let i = factor.idx2row[idx];
+            let l_ij = factor.idx2val[idx];
+            for i_rhs in 0..num_rhs {
+                let v = l_ij * y[j * num_rhs + i_rhs];
+                y[i * num_rhs + i_rhs] -= v;
+            }
+        }
+    }
+    // backward substitution L^T y = y
+    for j in (0..num_blk).rev() {
+        for idx in factor.col2idx[j]..factor.col2idx[j + 1] {
+            let i = factor.idx2row[idx];
+            let l_ij = factor.idx2val[idx];
+            for i_rhs in 0..num_rhs {
+                let v = l_ij * y[i * num_rhs + i_rhs];
+                y[j * num_rhs + i_rhs] -= v;
+            }
+        }
+        let inv_l_jj = T::one() / factor.col2val[j];
+        for i_rhs in 0..num_rhs {
+            y[j * num_rhs + i_rhs] = y[j * num_rhs + i_rhs] * inv_l_jj;
+        }
+    }
+    // x = P^T y
+    for (i_new, &i_old) in factor.new2old.iter().enumerate() {
+        mat[i_old * num_rhs..(i_old + 1) * num_rhs]
+            .copy_from_slice(&y[i_new * num_rhs..(i_new + 1) * num_rhs]);
+    }
+}
+
+#[test]
+fn test_cholesky() {
+    let sparse = crate::sparse_square::laplacian_grid(12, 10, 0.01);
+    let num_blk = sparse.num_blk;
+    // natural ordering
+    let mut factor0 = Factorization::<f64>::new();
+    factor0.symbolic_initialization_with_permutation(
+        &sparse.row2idx,
+        &sparse.idx2col,
+        (0..num_blk).collect(),
+    );
+    decompose(&mut factor0, &sparse).unwrap();
+    // fill-reducing ordering
+    let mut factor1 = Factorization::<f64>::new();
+    factor1.symbolic_initialization(&sparse.row2idx, &sparse.idx2col);
+    decompose(&mut factor1, &sparse).unwrap();
+    assert!(factor1.num_nonzero() < factor0.num_nonzero());
+    //
+    let num_rhs = 3;
+    let b_mat: Vec<f64> = (0..num_blk * num_rhs).map(|i| (i as f64).sin()).collect();
+    for factor in [&factor0, &factor1] {
+        let mut x_mat = b_mat.clone();
+        solve_mat(factor, &mut x_mat);
+        let mut r_mat = b_mat.clone();
+        crate::sparse_square::mult_mat(&mut r_mat, 1., -1., &sparse, &x_mat);
+        assert!(r_mat.iter().all(|v| v.abs() < 1.0e-8));
+        //
+        let mut x_vec: Vec<f64> = (0..num_blk).map(|i| b_mat[i * num_rhs]).collect();
+        solve_vec(factor, &mut x_vec);
+        for i_blk in 0..num_blk {
+            assert!((x_vec[i_blk] - x_mat[i_blk * num_rhs]).abs() < 1.0e-10);
+        }
+    }
+    // refactorization with the same pattern
+    let mut sparse1 = sparse.clone();
+    sparse1.row2val.iter_mut().for_each(|v| *v += 0.5);
+    refactorize(&mut factor1, &sparse1.row2val, &sparse1.idx2val).unwrap();
+    let mut x_vec = b_mat[..num_blk].to_vec();
+    solve_vec(&factor1, &mut x_vec);
+    let mut r_vec = b_mat[..num_blk].to_vec();
+    crate::sparse_square::mult_vec(&mut r_vec, 1., -1., &sparse1, &x_vec);
+    assert!(r_vec.iter().all(|v| v.abs() < 1.0e-8));
+    // not positive definite
+    sparse1.row2val[5] = -1.;
+    assert!(decompose(&mut factor1, &sparse1).is_err());
+    // empty factorization
+    let empty = Factorization::<f64>::new();
+    solve_vec(&empty, &mut []);
+    solve_mat(&empty, &mut []);
+}
+
+#[test]
+fn test_cholesky_irregular() {
+    let sparse = crate::sparse_square::irregular_test_matrix(300, true, 100.);
+    let mut factor = Factorization::<f64>::new();
+    factor.symbolic_initialization(&sparse.row2idx, &sparse.idx2col);
+    decompose(&mut factor, &sparse).unwrap();
+    let b_vec: Vec<f64> = (0..sparse.num_blk).map(|i| (i as f64).sin()).collect();
+    let mut x_vec = b_vec.clone();
+    solve_vec(&factor, &mut x_vec);
+    let err = crate::sparse_square::componentwise_backward_error(&sparse, &x_vec, &b_vec);
+    assert!(err < 1.0e-12, "{}", err);
+    // refactorization with the same pattern
+    let mut sparse1 = sparse.clone();
+    sparse1.idx2val.iter_mut().for_each(|v| *v *= 0.7);
+    refactorize(&mut factor, &sparse1.row2val, &sparse1.idx2val).unwrap();
+    let mut x_vec = b_vec.clone();
+    solve_vec(&factor, &mut x_vec);
+    let err = crate::sparse_square::componentwise_backward_error(&sparse1, &x_vec, &b_vec);
+    assert!(err < 1.0e-12, "{}", err);
+}
@@ -143,10 +143,30 @@ where
     }
 }
 
+/// numerical factorization without checking the pivots.
+/// The zero or tiny pivots silently give the infinite or NaN values, so use `try_decompose`
+/// or `decompose_with_shift` unless the matrix is known to be safe (e.g., an M-matrix)
 pub fn decompose<T>(ilu: &mut Preconditioner<T>)
 where
     T: 'static + Copy + std::ops::Mul<Output = T> + std::ops::SubAssign + std::ops::Div<Output = T>,
     f32: AsPrimitive<T>,
+{
+    let _ = decompose_with_pivot_check(ilu, None, |_a_ii, _pivot| true);
+}
+
+/// numerical factorization that stops at the first pivot rejected by `is_pivot_acceptable`
+/// * `omega` - relaxation parameter of the modified ILU. The dropped fill-ins are not lumped if `None`
+/// * `is_pivot_acceptable` - function of the diagonal entry before the elimination and the pivot
+/// * return - the row of the rejected pivot as the error
+fn decompose_with_pivot_check<T, F>(
+    ilu: &mut Preconditioner<T>,
+    omega: Option<T>,
+    is_pivot_acceptable: F,
+) -> Result<(), usize>
+where
+    T: 'static + Copy + std::ops::Mul<Output = T> + std::ops::SubAssign + std::ops::Div<Output = T>,
+    f32: AsPrimitive<T>,
+    F: Fn(T, T) -> bool,
 {
     let num_row = ilu.num_blk;
     let mut col2idx = vec![usize::MAX; num_row];
@@ -157,6 +177,7 @@ where
             assert!(j_col < num_row);
             col2idx[j_col] = ij_idx;
         }
+        let a_ii = ilu.row2val[i_row];
         // [L] * [D^-1*U]
         for ik_idx in ilu.row2idx[i_row]..ilu.row2idx_dia[i_row] {
             let k_colrow = ilu.idx2col[ik_idx];
@@ -169,6 +190,9 @@ where
                 if j_col != i_row {
                     let ij_idx = col2idx[j_col];
                     if ij_idx == usize::MAX {
+                        if let Some(omega) = omega {
+                            ilu.row2val[i_row] -= omega * ik_val * kj_val; // lump the dropped fill-in
+                        }
                         continue;
                     }
                     ilu.idx2val[ij_idx] -= ik_val * kj_val;
@@ -177,6 +201,9 @@ where
                 }
             }
         }
+        if !is_pivot_acceptable(a_ii, ilu.row2val[i_row]) {
+            return Err(i_row);
+        }
         // invserse diagonal
         ilu.row2val[i_row] = 1_f32.as_() / ilu.row2val[i_row];
         // [U] = [1/D][U]
@@ -191,9 +218,95 @@ where
             col2idx[j_col] = usize::MAX;
         }
     } // end iblk
+    Ok(())
 }
 
-pub fn solve_preconditioning_vec<T>(vec: &mut Vec<T>, ilu: &Preconditioner<T>)
+/// numerical factorization with the monitoring of the pivots.
+/// The pivot is rejected if it is not finite, its sign differs from the diagonal entry of the matrix,
+/// or its magnitude is smaller than `sqrt(epsilon)` times the diagonal entry.
+/// * return - error with the row of the rejected pivot. The factorization is incomplete in that case
+pub fn try_decompose<T>(ilu: &mut Preconditioner<T>) -> Result<(), String>
+where
+    T: 'static + num_traits::Float + std::ops::SubAssign,
+    f32: AsPrimitive<T>,
+{
+    decompose_with_pivot_check(ilu, None, is_pivot_acceptable)
+        .map_err(|i_row| format!("breakdown of the incomplete factorization at row {}", i_row))
+}
+
+fn is_pivot_acceptable<T>(a_ii: T, pivot: T) -> bool
+where
+    T: num_traits::Float,
+{
+    pivot.is_finite() && pivot * a_ii > T::zero() && pivot.abs() > T::epsilon().sqrt() * a_ii.abs()
+}
+
+/// modified ILU (MILU) factorization, where the fill-ins dropped from the non-zero pattern
+/// (e.g., the one given by `initialize_iluk`) are added to the diagonal after multiplied by `omega`.
+/// With `omega = 1`, the row sums of `[L][U]` are the same as `[A]`,
+/// which improves the condition number for the elliptic problems such as the Poisson equation.
+/// `omega` slightly less than 1 (e.g., 0.95) is more robust against the small pivots.
+/// The lumping is not invariant to the diagonal scaling, so the badly scaled matrix should be
+/// scaled to the unit diagonal beforehand.
+/// * `omega` - relaxation parameter in `[0, 1]`. It panics otherwise.
+///   `omega = 0` is the same as `try_decompose` (not `decompose`, which does not check the pivots)
+/// * return - error with the row of the pivot rejected as in `try_decompose`
+pub fn decompose_modified<T>(ilu: &mut Preconditioner<T>, omega: T) -> Result<(), String>
+where
+    T: 'static + num_traits::Float + std::ops::SubAssign,
+    f32: AsPrimitive<T>,
+{
+    assert!(
+        omega >= T::zero() && omega <= T::one(),
+        "omega must be in [0, 1]"
+    );
+    decompose_with_pivot_check(ilu, Some(omega), is_pivot_acceptable).map_err(|i_row| {
+        format!(
+            "breakdown of the modified incomplete factorization at row {}",
+            i_row
+        )
+    })
+}
+
+/// numerical factorization of `[A] + alpha diag([A])` with the Manteuffel shift `alpha`.
+/// The factorization is first tried without the shift, and then retried with the shift doubled from `initial_shift`
+/// until all the pivots are accepted by `try_decompose`.
+/// * `a` - matrix whose values are copied to `ilu`
+/// * `max_retry` - maximum number of the retries with the shift
+/// * return - the shift `alpha` used for the factorization
+pub fn decompose_with_shift<T>(
+    ilu: &mut Preconditioner<T>,
+    a: &crate::sparse_square::Matrix<T>,
+    initial_shift: T,
+    max_retry: usize,
+) -> Result<T, String>
+where
+    T: 'static + num_traits::Float + std::ops::SubAssign,
+    f32: AsPrimitive<T>,
+{
+    assert!(initial_shift > T::zero());
+    let mut shift = T::zero();
+    for i_retry in 0..=max_retry {
+        copy_value(ilu, a);
+        let scale = T::one() + shift;
+        ilu.row2val.iter_mut().for_each(|v| *v = *v * scale);
+        match try_decompose(ilu) {
+            Ok(()) => return Ok(shift),
+            Err(msg) if i_retry == max_retry => {
+                return Err(format!("{} after {} retries of the shift", msg, max_retry));
+            }
+            Err(_) => {}
+        }
+        shift = if shift == T::zero() {
+            initial_shift
+        } else {
+            shift + shift
+        };
+    }
+    unreachable!()
+}
+
+pub fn solve_preconditioning_vec<T>(vec: &mut [T], ilu: &Preconditioner<T>)
 where
     T: Copy + std::ops::Mul<Output = T> + std::ops::SubAssign,
 {
@@ -224,6 +337,166 @@ where
     }
 }
 
+/// rows of the triangular factor grouped by the levels.
+/// The rows in the same level do not depend on each other, so that they can be solved concurrently
+/// after all the rows of the previous levels are solved
+pub struct LevelSchedule {
+    pub level2idx: Vec<usize>,
+    pub idx2row: Vec<usize>,
+}
+
+impl LevelSchedule {
+    /// level of the rows in the CRS format
+    /// * `row2level` - level of each row
+    fn from_row2level(row2level: &[usize]) -> Self {
+        let num_level = row2level.iter().map(|&l| l + 1).max().unwrap_or(0);
+        let mut level2idx = vec![0_usize; num_level + 1];
+        for &i_level in row2level {
+            level2idx[i_level + 1] += 1;
+        }
+        for i_level in 0..num_level {
+            level2idx[i_level + 1] += level2idx[i_level];
+        }
+        let mut idx2row = vec![0_usize; row2level.len()];
+        let mut level2pos = level2idx[..num_level].to_vec();
+        for (i_row, &i_level) in row2level.iter().enumerate() {
+            idx2row[level2pos[i_level]] = i_row;
+            level2pos[i_level] += 1;
+        }
+        LevelSchedule { level2idx, idx2row }
+    }
+
+    pub fn num_level(&self) -> usize {
+        self.level2idx.len() - 1
+    }
+}
+
+/// level scheduling of the forward substitution with the lower triangle `[L]`
+pub fn level_schedule_forward<T>(ilu: &Preconditioner<T>) -> LevelSchedule {
+    let num_row = ilu.num_blk;
+    let mut row2level = vec![0_usize; num_row];
+    for i_row in 0..num_row {
+        row2level[i_row] = ilu.idx2col[ilu.row2idx[i_row]..ilu.row2idx_dia[i_row]]
+            .iter()
+            .map(|&j_col| row2level[j_col] + 1)
+            .max()
+            .unwrap_or(0);
+    }
+    LevelSchedule::from_row2level(&row2level)
+}
+
+/// level scheduling of the backward substitution with the upper triangle `[U]`
+pub fn level_schedule_backward<T>(ilu: &Preconditioner<T>) -> LevelSchedule {
+    let num_row = ilu.num_blk;
+    let mut row2level = vec![0_usize; num_row];
+    for i_row in (0..num_row).rev() {
+        row2level[i_row] = ilu.idx2col[ilu.row2idx_dia[i_row]..ilu.row2idx[i_row + 1]]
+            .iter()
+            .map(|&j_col| row2level[j_col] + 1)
+            .max()
+            .unwrap_or(0);
+    }
+    LevelSchedule::from_row2level(&row2level)
+}
+
+/// minimum number of the rows per thread to solve a level concurrently
+pub const NUM_ROW_PER_THREAD_LEVEL: usize = 64;
+
+/// solve the rows in each level of `schedule` concurrently.
+/// `row_value(i_row, val, idx2val, row2idx)` computes the new value of the row from its current value `val`
+/// and the values of the rows in the previous levels, stored in `idx2val` in the order of the schedule
+/// (the value of the row `j_row` is `idx2val[row2idx[j_row]]`).
+/// The values are permuted so that each level is contiguous and each thread writes to its own chunk of the level.
+/// The threads are spawned for each level with many rows; the levels with few rows are solved by the calling thread.
+fn par_solve_levels<T, F>(vec: &mut [T], schedule: &LevelSchedule, num_thread: usize, row_value: F)
+where
+    T: Copy + Send + Sync,
+    F: Fn(usize, T, &[T], &[usize]) -> T + Sync,
+{
+    let mut row2idx = vec![0_usize; vec.len()];
+    for (idx, &i_row) in schedule.idx2row.iter().enumerate() {
+        row2idx[i_row] = idx;
+    }
+    let mut idx2val: Vec<T> = schedule.idx2row.iter().map(|&i_row| vec[i_row]).collect();
+    let solve_rows = |vals: &mut [T], rows: &[usize], prev: &[T]| {
+        for (val, &i_row) in vals.iter_mut().zip(rows) {
+            *val = row_value(i_row, *val, prev, &row2idx);
+        }
+    };
+    for i_level in 0..schedule.num_level() {
+        let (idx0, idx1) = (schedule.level2idx[i_level], schedule.level2idx[i_level + 1]);
+        let rows = &schedule.idx2row[idx0..idx1];
+        // the rows of the previous levels are only read, the rows of this level are only written
+        let (prev, vals) = idx2val.split_at_mut(idx0);
+        let (prev, vals) = (&*prev, &mut vals[..idx1 - idx0]);
+        let num_part = num_thread.min(rows.len() / NUM_ROW_PER_THREAD_LEVEL);
+        if num_part <= 1 {
+            solve_rows(vals, rows, prev);
+            continue;
+        }
+        let size_chunk = rows.len().div_ceil(num_part);
+        std::thread::scope(|s| {
+            let mut chunks = vals.chunks_mut(size_chunk).zip(rows.chunks(size_chunk));
+            let (vals0, rows0) = chunks.next().unwrap();
+            for (vals, rows) in chunks {
+                let solve_rows = &solve_rows;
+                s.spawn(move || solve_rows(vals, rows, prev));
+            }
+            solve_rows(vals0, rows0, prev);
+        });
+    }
+    for (&i_row, &val) in schedule.idx2row.iter().zip(&idx2val) {
+        vec[i_row] = val;
+    }
+}
+
+/// multi-threaded version of `solve_preconditioning_vec` using the level scheduling
+/// * `forward` - schedule computed by `level_schedule_forward`
+/// * `backward` - schedule computed by `level_schedule_backward`
+/// * `num_thread` - number of the threads. The levels with few rows are solved by one thread.
+///   If it is one, `solve_preconditioning_vec` is called because the row order has the better memory locality
+pub fn par_solve_preconditioning_vec<T>(
+    vec: &mut [T],
+    ilu: &Preconditioner<T>,
+    forward: &LevelSchedule,
+    backward: &LevelSchedule,
+    num_thread: usize,
+) where
+    T: Copy + std::ops::Mul<Output = T> + std::ops::SubAssign + Send + Sync,
+{
+    assert_eq!(vec.len(), ilu.row2val.len());
+    assert_eq!(forward.idx2row.len(), ilu.num_blk);
+    assert_eq!(backward.idx2row.len(), ilu.num_blk);
+    if num_thread <= 1 {
+        solve_preconditioning_vec(vec, ilu);
+        return;
+    }
+    par_solve_levels(
+        vec,
+        forward,
+        num_thread,
+        |i_row, mut v, idx2val, row2idx| {
+            let idxs = ilu.row2idx[i_row]..ilu.row2idx_dia[i_row];
+            for (&a, &j_col) in ilu.idx2val[idxs.clone()].iter().zip(&ilu.idx2col[idxs]) {
+                v -= a * idx2val[row2idx[j_col]];
+            }
+            ilu.row2val[i_row] * v
+        },
+    );
+    par_solve_levels(
+        vec,
+        backward,
+        num_thread,
+        |i_row, mut v, idx2val, row2idx| {
+            let idxs = ilu.row2idx_dia[i_row]..ilu.row2idx[i_row + 1];
+            for (&a, &j_col) in ilu.idx2val[idxs.clone()].iter().zip(&ilu.idx2col[idxs]) {
+                v -= a * idx2val[row2idx[j_col]];
+            }
+            v
+        },
+    );
+}
+
 fn symbolic_iluk(
     a_row2idx: &Vec<usize>,
     mut a_idx2col: Vec<usize>,
@@ -316,3 +589,285 @@ fn symbolic_iluk(
     }
     (row2idx, idx2col, row2idx_dia)
 }
+
+#[test]
+fn test_par_solve_preconditioning() {
+    let sparse = crate::sparse_square::laplacian_grid(150, 140, 0.1);
+    let num_blk = sparse.num_blk;
+    for lev_fill in [0, 2] {
+        let mut ilu = Preconditioner::<f64>::new();
+        ilu.initialize_iluk(&sparse, lev_fill);
+        copy_value(&mut ilu, &sparse);
+        decompose(&mut ilu);
+        let forward = level_schedule_forward(&ilu);
+        let backward = level_schedule_backward(&ilu);
+        // the rows in the same level are independent
+        for (schedule, is_forward) in [(&forward, true), (&backward, false)] {
+            let mut row2level = vec![0; num_blk];
+            for i_level in 0..schedule.num_level() {
+                for idx in schedule.level2idx[i_level]..schedule.level2idx[i_level + 1] {
+                    row2level[schedule.idx2row[idx]] = i_level;
+                }
+            }
+            for i_row in 0..num_blk {
+                let idxs = if is_forward {
+                    ilu.row2idx[i_row]..ilu.row2idx_dia[i_row]
+                } else {
+                    ilu.row2idx_dia[i_row]..ilu.row2idx[i_row + 1]
+                };
+                for &j_col in &ilu.idx2col[idxs] {
+                    assert!(row2level[j_col] < row2level[i_row]);
+                }
+            }
+        }
+        // the grid has the levels along the anti-diagonals
+        assert!(forward.num_level() < num_blk / 10);
+        let vec0: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+        let mut vec1 = vec0.clone();
+        solve_preconditioning_vec(&mut vec1, &ilu);
+        for num_thread in [1, 4] {
+            let mut vec2 = vec0.clone();
+            par_solve_preconditioning_vec(&mut vec2, &ilu, &forward, &backward, num_thread);
+            for i_blk in 0..num_blk {
+                assert!((vec1[i_blk] - vec2[i_blk]).abs() < 1.0e-12);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_decompose_with_shift() {
+    use crate::slice::dot;
+    // the squared Laplacian is positive definite but not an M-matrix, and ILU(0) breaks down
+    let lap = crate::sparse_square::laplacian_grid(20, 20, 0.);
+    let mut sparse = crate::sparse_matrix_multiplication::mult_square_matrices(&lap, &lap);
+    sparse.row2val.iter_mut().for_each(|v| *v += 0.1);
+    let mut ilu = Preconditioner::<f64>::new();
+    ilu.initialize_iluk(&sparse, 0);
+    copy_value(&mut ilu, &sparse);
+    assert!(try_decompose(&mut ilu).is_err());
+    assert!(decompose_with_shift(&mut ilu, &sparse, 1.0e-3, 0).is_err());
+    let shift = decompose_with_shift(&mut ilu, &sparse, 1.0e-3, 20).unwrap();
+    assert!(shift > 0.);
+    assert!(ilu.row2val.iter().all(|&v| v > 0. && v.is_finite()));
+    // the shifted factorization works as a preconditioner
+    let b_vec: Vec<f64> = (0..sparse.num_blk).map(|i| (i as f64).sin()).collect();
+    let mut x_vec = b_vec.clone();
+    solve_preconditioning_vec(&mut x_vec, &ilu);
+    assert!(x_vec.iter().all(|v| v.is_finite()));
+    let mut r_vec = b_vec.clone();
+    crate::sparse_square::mult_vec(&mut r_vec, 1., -1., &sparse, &x_vec);
+    assert!(dot(&r_vec, &r_vec) < dot(&b_vec, &b_vec));
+    // no shift is used if the factorization succeeds
+    let lap = crate::sparse_square::laplacian_grid(20, 20, 0.1);
+    assert_eq!(decompose_with_shift(&mut ilu, &lap, 1.0e-3, 20), Ok(0.));
+}
+
+#[test]
+fn test_decompose_modified() {
+    use crate::slice::dot;
+    let sparse = crate::sparse_square::laplacian_grid(64, 64, 1.0e-4);
+    let num_blk = sparse.num_blk;
+    // the row sums are preserved with omega = 1
+    {
+        let mut ilu = Preconditioner::<f64>::new();
+        ilu.initialize_iluk(&sparse, 0);
+        copy_value(&mut ilu, &sparse);
+        decompose_modified(&mut ilu, 1.).unwrap();
+        let mut a_one = vec![0.; num_blk];
+        crate::sparse_square::mult_vec(&mut a_one, 0., 1., &sparse, &vec![1.; num_blk]);
+        let mut lu_inv_a_one = a_one.clone();
+        solve_preconditioning_vec(&mut lu_inv_a_one, &ilu);
+        assert!(lu_inv_a_one.iter().all(|&v| (v - 1.).abs() < 1.0e-8));
+    }
+    // omega = 0 is the same as try_decompose
+    {
+        let mut ilu0 = Preconditioner::<f64>::new();
+        ilu0.initialize_iluk(&sparse, 0);
+        copy_value(&mut ilu0, &sparse);
+        let mut ilu1 = ilu0.clone();
+        decompose_modified(&mut ilu0, 0.).unwrap();
+        try_decompose(&mut ilu1).unwrap();
+        assert_eq!(ilu0.row2val, ilu1.row2val);
+        assert_eq!(ilu0.idx2val, ilu1.idx2val);
+    }
+    // MILU converges faster than ILU with the same pattern
+    let b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    for lev_fill in [0, 1] {
+        let mut num_itrs = vec![];
+        for omega in [0., 0.95] {
+            let mut ilu = Preconditioner::<f64>::new();
+            ilu.initialize_iluk(&sparse, lev_fill);
+            copy_value(&mut ilu, &sparse);
+            decompose_modified(&mut ilu, omega).unwrap();
+            let mut r_vec = b_vec.clone();
+            let (mut x_vec, mut pr_vec, mut p_vec) = (vec![], vec![], vec![]);
+            let conv_hist = crate::solver_sparse::preconditioned_conjugate_gradient(
+                &mut r_vec,
+                &mut x_vec,
+                &mut pr_vec,
+                &mut p_vec,
+                1.0e-8,
+                1000,
+                &sparse,
+                &ilu,
+            );
+            let mut y_vec = b_vec.clone();
+            crate::sparse_square::mult_vec(&mut y_vec, 1., -1., &sparse, &x_vec);
+            assert!(dot(&y_vec, &y_vec).sqrt() < dot(&b_vec, &b_vec).sqrt() * 1.0e-7);
+            num_itrs.push(conv_hist.len());
+        }
+        assert!(num_itrs[1] < num_itrs[0]);
+    }
+}
+
+#[test]
+#[should_panic(expected = "omega must be in [0, 1]")]
+fn test_decompose_modified_invalid_omega() {
+    let sparse = crate::sparse_square::laplacian_grid(4, 4, 0.1);
+    let mut ilu = Preconditioner::<f64>::new();
+    ilu.initialize_iluk(&sparse, 0);
+    copy_value(&mut ilu, &sparse);
+    let _ = decompose_modified(&mut ilu, 1.5);
+}
+
+#[test]
+fn test_par_solve_preconditioning_irregular() {
+    // the ring in the natural ordering makes one row per level, and the scattered ordering has the wide levels
+    let sparse0 = crate::sparse_square::irregular_test_matrix(5000, false, 100.);
+    let num_blk = sparse0.num_blk;
+    let sparse1 = sparse0.permute(
+        &(0..num_blk)
+            .map(|i| (i * 7919) % num_blk)
+            .collect::<Vec<_>>(),
+    );
+    for (sparse, lev_fill) in [(&sparse0, 0), (&sparse1, 0), (&sparse1, 1)] {
+        let mut ilu = Preconditioner::<f64>::new();
+        ilu.initialize_iluk(sparse, lev_fill);
+        copy_value(&mut ilu, sparse);
+        decompose(&mut ilu);
+        let forward = level_schedule_forward(&ilu);
+        let backward = level_schedule_backward(&ilu);
+        if std::ptr::eq(sparse, &sparse0) {
+            assert_eq!(forward.num_level(), num_blk);
+        } else {
+            assert!(forward.num_level() < 50 && backward.num_level() < 50);
+        }
+        let vec0: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+        let mut vec1 = vec0.clone();
+        solve_preconditioning_vec(&mut vec1, &ilu);
+        for num_thread in [1, 2, 4, 7] {
+            let mut vec2 = vec0.clone();
+            par_solve_preconditioning_vec(&mut vec2, &ilu, &forward, &backward, num_thread);
+            for i_blk in 0..num_blk {
+                assert!((vec1[i_blk] - vec2[i_blk]).abs() < 1.0e-12 * vec1[i_blk].abs().max(1.));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_decompose_with_shift_irregular() {
+    use crate::solver_sparse::preconditioned_conjugate_gradient_with_operators;
+    use std::ops::ControlFlow;
+    // square of the badly scaled irregular matrix is positive definite but not diagonally dominant
+    let a = crate::sparse_square::irregular_test_matrix(300, true, 10.);
+    let sparse = crate::sparse_matrix_multiplication::mult_square_matrices(&a, &a);
+    let num_blk = sparse.num_blk;
+    let mut ilu = Preconditioner::<f64>::new();
+    ilu.initialize_iluk(&sparse, 0);
+    copy_value(&mut ilu, &sparse);
+    assert!(try_decompose(&mut ilu).is_err());
+    let shift = decompose_with_shift(&mut ilu, &sparse, 1.0e-3, 20).unwrap();
+    assert!(shift > 0.);
+    assert!(ilu.row2val.iter().all(|&v| v > 0. && v.is_finite()));
+    let b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    let criterion = crate::stopping_criterion::Criterion::RelativeResidual(1.0e-10);
+    let mut num_itrs = vec![];
+    for is_ilu in [true, false] {
+        let mut r_vec = b_vec.clone();
+        let (mut x_vec, mut pr_vec, mut p_vec) = (vec![], vec![], vec![]);
+        let conv_hist = preconditioned_conjugate_gradient_with_operators(
+            &mut r_vec,
+            &mut x_vec,
+            &mut pr_vec,
+            &mut p_vec,
+            &criterion,
+            2000,
+            |y: &mut [f64], x: &[f64]| crate::sparse_square::mult_mat(y, 0., 1., &sparse, x),
+            |v: &mut [f64]| {
+                if is_ilu {
+                    solve_preconditioning_vec(v, &ilu);
+                } else {
+                    v.iter_mut().zip(&sparse.row2val).for_each(|(v, d)| *v /= d);
+                }
+            },
+            |_, _, _| ControlFlow::Continue(()),
+        );
+        let err = crate::sparse_square::componentwise_backward_error(&sparse, &x_vec, &b_vec);
+        assert!(err < 1.0e-8, "{}", err);
+        num_itrs.push(conv_hist.len());
+    }
+    // the shifted ILU(0) converges faster than Jacobi
+    assert!(num_itrs[0] < num_itrs[1]);
+}
+
+#[test]
+fn test_decompose_modified_irregular() {
+    use crate::solver_sparse::preconditioned_conjugate_gradient_with_operators;
+    use std::ops::ControlFlow;
+    // the row sums are preserved with omega = 1 for the irregular unsymmetric matrix
+    let sparse = crate::sparse_square::irregular_test_matrix(400, false, 1.);
+    let num_blk = sparse.num_blk;
+    for lev_fill in [0, 1] {
+        let mut ilu = Preconditioner::<f64>::new();
+        ilu.initialize_iluk(&sparse, lev_fill);
+        copy_value(&mut ilu, &sparse);
+        decompose_modified(&mut ilu, 1.).unwrap();
+        let mut a_one = vec![0.; num_blk];
+        crate::sparse_square::mult_vec(&mut a_one, 0., 1., &sparse, &vec![1.; num_blk]);
+        solve_preconditioning_vec(&mut a_one, &ilu);
+        assert!(a_one.iter().all(|&v| (v - 1.).abs() < 1.0e-8));
+    }
+    // the lumped fill-ins are not invariant to the diagonal scaling,
+    // and the breakdown for the badly scaled matrix is reported
+    let sparse0 = crate::sparse_square::irregular_test_matrix(400, true, 100.);
+    let mut ilu = Preconditioner::<f64>::new();
+    ilu.initialize_iluk(&sparse0, 0);
+    copy_value(&mut ilu, &sparse0);
+    assert!(decompose_modified(&mut ilu, 0.95).is_err());
+    copy_value(&mut ilu, &sparse0);
+    decompose_modified(&mut ilu, 0.).unwrap();
+    // MILU preconditioned CG after the symmetric scaling to the unit diagonal
+    let mut sparse = sparse0.clone();
+    let row2scale: Vec<f64> = sparse0.row2val.iter().map(|d| 1. / d.sqrt()).collect();
+    for i_row in 0..num_blk {
+        sparse.row2val[i_row] = 1.;
+        for idx in sparse.row2idx[i_row]..sparse.row2idx[i_row + 1] {
+            let j_col = sparse.idx2col[idx];
+            sparse.idx2val[idx] *= row2scale[i_row] * row2scale[j_col];
+        }
+    }
+    let b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    let criterion = crate::stopping_criterion::Criterion::RelativeResidual(1.0e-10);
+    for omega in [0.5, 0.95, 1.] {
+        copy_value(&mut ilu, &sparse);
+        decompose_modified(&mut ilu, omega).unwrap();
+        let mut r_vec = b_vec.clone();
+        let (mut x_vec, mut pr_vec, mut p_vec) = (vec![], vec![], vec![]);
+        let conv_hist = preconditioned_conjugate_gradient_with_operators(
+            &mut r_vec,
+            &mut x_vec,
+            &mut pr_vec,
+            &mut p_vec,
+            &criterion,
+            1000,
+            |y: &mut [f64], x: &[f64]| crate::sparse_square::mult_mat(y, 0., 1., &sparse, x),
+            |v: &mut [f64]| solve_preconditioning_vec(v, &ilu),
+            |_, _, _| ControlFlow::Continue(()),
+        );
+        assert!(conv_hist.len() < 1000);
+        let err = crate::sparse_square::componentwise_backward_error(&sparse, &x_vec, &b_vec);
+        assert!(err < 1.0e-8, "{}", err);
+    }
+}
@@ -0,0 +1,630 @@
+//! Sparse direct `L D L^T` factorization `P A P^T = L D L^T` for symmetric indefinite matrices.
+//! The pivots are chosen by the Bunch-Kaufman strategy with `1x1` and `2x2` blocks of `D`,
+//! where the candidates of the pivot are visited in a fill-reducing ordering.
+//! Once factorized, a matrix with the same pattern can be refactorized cheaply with `refactorize`,
+//! which reuses the pivots and the pattern of `L` without the pivot search.
+
+/// `L D L^T` factorization where the unit lower triangular factor `L` is stored in the Compressed Column Storage (CCS)
+/// * `new2old` - permutation `P` (elimination order of the rows)
+/// * `col2idx`, `idx2row`, `idx2val` - strictly lower triangular part of `L`
+/// * `d_dia` - diagonal of the block diagonal matrix `D`
+/// * `d_sub` - sub-diagonal of `D` (`d_sub[k]` is `D(k+1, k)`). It is non-zero only at the first column of the `2x2` blocks
+pub struct Factorization<T> {
+    pub num_blk: usize,
+    pub new2old: Vec<usize>,
+    pub old2new: Vec<usize>,
+    pub col2idx: Vec<usize>,
+    pub idx2row: Vec<usize>,
+    pub idx2val: Vec<T>,
+    pub d_dia: Vec<T>,
+    pub d_sub: Vec<T>,
+    /// candidates of the pivot in the fill-reducing order
+    candidates: Vec<usize>,
+    /// first column of each block of `D` chosen in the last `decompose`
+    blk2col: Vec<usize>,
+    // non-zero pattern of `A` given in the symbolic analysis
+    a_row2idx: Vec<usize>,
+    a_idx2col: Vec<usize>,
+}
+
+impl<T> Factorization<T>
+where
+    T: num_traits::Float,
+{
+    pub fn new() -> Self {
+        Factorization {
+            num_blk: 0,
+            new2old: vec![],
+            old2new: vec![],
+            col2idx: vec![0],
+            idx2row: vec![],
+            idx2val: vec![],
+            d_dia: vec![],
+            d_sub: vec![],
+            candidates: vec![],
+            blk2col: vec![0],
+            a_row2idx: vec![0],
+            a_idx2col: vec![],
+        }
+    }
+
+    /// symbolic analysis that computes the minimum degree ordering for the pivot candidates
+    /// * `row2idx`, `idx2col` - non-zero pattern of the symmetric matrix (same as `sparse_square::Matrix`)
+    pub fn symbolic_initialization(&mut self, row2idx: &[usize], idx2col: &[usize]) {
+        let (sym_row2idx, sym_idx2col) = crate::ordering::symmetrize_pattern(row2idx, idx2col);
+        let new2old = crate::ordering::minimum_degree::minimum_degree(&sym_row2idx, &sym_idx2col);
+        self.symbolic_initialization_with_permutation(row2idx, idx2col, new2old);
+    }
+
+    /// symbolic analysis with the given ordering of the pivot candidates
+    pub fn symbolic_initialization_with_permutation(
+        &mut self,
+        row2idx: &[usize],
+        idx2col: &[usize],
+        new2old: Vec<usize>,
+    ) {
+        assert_eq!(new2old.len() + 1, row2idx.len());
+        self.num_blk = new2old.len();
+        self.candidates = new2old;
+        self.a_row2idx = row2idx.to_vec();
+        self.a_idx2col = idx2col.to_vec();
+    }
+
+    /// number of the non-zero entries in `L` including the diagonal
+    pub fn num_nonzero(&self) -> usize {
+        self.idx2row.len() + self.num_blk
+    }
+}
+
+impl<T> Default for Factorization<T>
+where
+    T: num_traits::Float,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// active sub-matrix during the elimination
+struct ActiveMatrix<T> {
+    row2val: Vec<T>,
+    row2adj: Vec<std::collections::BTreeMap<usize, T>>,
+}
+
+impl<T> ActiveMatrix<T>
+where
+    T: num_traits::Float,
+{
+    /// `a_ij -= v` for the off-diagonal entry, or `a_ii -= v` for the diagonal
+    fn sub(&mut self, i: usize, j: usize, v: T) {
+        if i == j {
+            self.row2val[i] = self.row2val[i] - v;
+            return;
+        }
+        let a_ij = self.row2adj[i].entry(j).or_insert(T::zero());
+        *a_ij = *a_ij - v;
+        let a_ji = self.row2adj[j].entry(i).or_insert(T::zero());
+        *a_ji = *a_ji - v;
+    }
+
+    /// remove the node from the active matrix and return the off-diagonal entries of its column
+    fn remove(&mut self, i: usize) -> Vec<(usize, T)> {
+        let adj = std::mem::take(&mut self.row2adj[i]);
+        for &j in adj.keys() {
+            self.row2adj[j].remove(&i);
+        }
+        adj.into_iter().collect()
+    }
+
+    /// largest magnitude of the off-diagonal entries in the column and its row
+    fn max_offdiagonal(&self, i: usize) -> (T, usize) {
+        let mut max = (T::zero(), usize::MAX);
+        for (&j, &v) in self.row2adj[i].iter() {
+            if v.abs() > max.0 {
+                max = (v.abs(), j);
+            }
+        }
+        max
+    }
+}
+
+/// numeric factorization with the Bunch-Kaufman pivoting
+/// * `a` - symmetric matrix (the upper and lower triangular parts are both stored)
+///   with the same non-zero pattern as the one used in the symbolic analysis
+/// * return - number of the zero `1x1` pivots, which is non-zero if the matrix is singular.
+///   The factorization is still usable and `solve_vec` sets the corresponding components zero
+pub fn decompose<T>(factor: &mut Factorization<T>, a: &crate::sparse_square::Matrix<T>) -> usize
+where
+    T: num_traits::Float,
+{
+    let num_blk = factor.num_blk;
+    assert_eq!(a.num_blk, num_blk);
+    assert_eq!(a.row2idx, factor.a_row2idx);
+    assert_eq!(a.idx2col, factor.a_idx2col);
+    let alpha = (T::one() + T::from(17).unwrap().sqrt()) / T::from(8).unwrap();
+    let mut active = ActiveMatrix {
+        row2val: a.row2val.clone(),
+        row2adj: (0..num_blk)
+            .map(|i_row| {
+                (a.row2idx[i_row]..a.row2idx[i_row + 1])
+                    .map(|idx| (a.idx2col[idx], a.idx2val[idx]))
+                    .collect()
+            })
+            .collect(),
+    };
+    let mut is_eliminated = vec![false; num_blk];
+    factor.new2old.clear();
+    factor.d_dia.clear();
+    factor.d_sub.clear();
+    factor.blk2col = vec![0];
+    // columns of L with the original row indices
+    let mut cols = Vec::<Vec<(usize, T)>>::with_capacity(num_blk);
+    let mut num_zero_pivot = 0;
+    let mut i_candidate = 0;
+    while i_candidate < num_blk {
+        let k = factor.candidates[i_candidate];
+        if is_eliminated[k] {
+            i_candidate += 1;
+            continue;
+        }
+        // the candidate `k` is visited again if another node is chosen as the pivot
+        let a_kk = active.row2val[k];
+        let (lambda, r) = active.max_offdiagonal(k);
+        let pivot = if lambda == T::zero() || a_kk.abs() >= alpha * lambda {
+            [k, usize::MAX]
+        } else {
+            let (sigma, _) = active.max_offdiagonal(r);
+            if a_kk.abs() * sigma >= alpha * lambda * lambda {
+                [k, usize::MAX]
+            } else if active.row2val[r].abs() >= alpha * sigma {
+                [r, usize::MAX]
+            } else {
+                [k, r]
+            }
+        };
+        if pivot[1] == usize::MAX {
+            // 1x1 pivot
+            let p = pivot[0];
+            let d = active.row2val[p];
+            let col = active.remove(p);
+            if d == T::zero() {
+                num_zero_pivot += 1;
+            } else {
+                for (i0, &(i, a_ip)) in col.iter().enumerate() {
+                    for &(j, a_jp) in &col[i0..] {
+                        active.sub(i, j, a_ip * a_jp / d);
+                    }
+                }
+            }
+            let l_col = col
+                .into_iter()
+                .map(|(i, a_ip)| (i, if d == T::zero() { T::zero() } else { a_ip / d }))
+                .collect();
+            is_eliminated[p] = true;
+            factor.new2old.push(p);
+            factor.d_dia.push(d);
+            factor.d_sub.push(T::zero());
+            factor.blk2col.push(factor.new2old.len());
+            cols.push(l_col);
+        } else {
+            // 2x2 pivot
+            let (p, q) = (pivot[0], pivot[1]);
+            let (d_pp, d_qq) = (active.row2val[p], active.row2val[q]);
+            let d_qp = *active.row2adj[p].get(&q).unwrap();
+            let det = d_pp * d_qq - d_qp * d_qp;
+            let mut col2 = std::collections::BTreeMap::<usize, [T; 2]>::new();
+            for (i, a_ip) in active.remove(p) {
+                if i != q {
+                    col2.entry(i).or_insert([T::zero(); 2])[0] = a_ip;
+                }
+            }
+            for (i, a_iq) in active.remove(q) {
+                col2.entry(i).or_insert([T::zero(); 2])[1] = a_iq;
+            }
+            // [l_ip, l_iq] = [a_ip, a_iq] * E^-1
+            let l_cols: Vec<(usize, [T; 2])> = col2
+                .iter()
+                .map(|(&i, &[a_ip, a_iq])| {
+                    let l_ip = (a_ip * d_qq - a_iq * d_qp) / det;
+                    let l_iq = (a_iq * d_pp - a_ip * d_qp) / det;
+                    (i, [l_ip, l_iq])
+                })
+                .collect();
+            for &(i, [l_ip, l_iq]) in &l_cols {
+                for (&j, &[a_jp, a_jq]) in col2.range(i..) {
+                    active.sub(i, j, l_ip * a_jp + l_iq * a_jq);
+                }
+            }
+            is_eliminated[p] = true;
+            is_eliminated[q] = true;
+            factor.new2old.push(p);
+            factor.new2old.push(q);
+            factor.d_dia.push(d_pp);
+            factor.d_dia.push(d_qq);
+            factor.d_sub.push(d_qp);
+            factor.d_sub.push(T::zero());
+            factor.blk2col.push(factor.new2old.len());
+            cols.push(l_cols.iter().map(|&(i, l)| (i, l[0])).collect());
+            cols.push(l_cols.iter().map(|&(i, l)| (i, l[1])).collect());
+        }
+    }
+    factor.old2new = crate::ordering::inverse_permutation(&factor.new2old);
+    // store L with the new row indices
+    factor.col2idx = vec![0; num_blk + 1];
+    factor.idx2row.clear();
+    factor.idx2val.clear();
+    for (k, col) in cols.iter().enumerate() {
+        let mut col: Vec<(usize, T)> = col.iter().map(|&(i, v)| (factor.old2new[i], v)).collect();
+        col.sort_by_key(|v| v.0);
+        for (i, v) in col {
+            assert!(i > k);
+            factor.idx2row.push(i);
+            factor.idx2val.push(v);
+        }
+        factor.col2idx[k + 1] = factor.idx2row.len();
+    }
+    num_zero_pivot
+}
+
+/// position of the entry `(i, j)` (`i > j`) of the strictly lower triangular part of `L`
+fn position(col2idx: &[usize], idx2row: &[usize], i: usize, j: usize) -> Option<usize> {
+    let idx0 = col2idx[j];
+    idx2row[idx0..col2idx[j + 1]]
+        .binary_search(&i)
+        .ok()
+        .map(|p| idx0 + p)
+}
+
+/// numeric factorization reusing the pivots and the pattern of `L` of the last `decompose`.
+/// The stability of the pivots is not checked again. It fails if a block of `D` becomes singular
+/// or the pattern of `L` is not enough, and `decompose` needs to be called again in that case.
+/// * `row2val` - diagonal of `A`
+/// * `idx2val` - off-diagonal values of `A` in the order of `idx2col` given in the symbolic analysis
+pub fn refactorize<T>(
+    factor: &mut Factorization<T>,
+    row2val: &[T],
+    idx2val: &[T],
+) -> Result<(), String>
+where
+    T: num_traits::Float + std::ops::SubAssign,
+{
+    let num_blk = factor.num_blk;
+    assert_eq!(row2val.len(), num_blk);
+    assert_eq!(idx2val.len(), factor.a_idx2col.len());
+    assert_eq!(factor.new2old.len(), num_blk, "decompose is not called");
+    let num_d = factor.blk2col.len() - 1;
+    // is `k` the first column of a 2x2 block
+    let mut is_2x2 = vec![false; num_blk];
+    for i_d in 0..num_d {
+        if factor.blk2col[i_d + 1] - factor.blk2col[i_d] == 2 {
+            is_2x2[factor.blk2col[i_d]] = true;
+        }
+    }
+    // `a_ij -= v` for the entry in the lower triangular part of the active matrix
+    let sub = |factor: &mut Factorization<T>, i: usize, j: usize, v: T| -> Result<(), String> {
+        if i == j {
+            factor.d_dia[i] -= v;
+        } else if i == j + 1 && is_2x2[j] {
+            factor.d_sub[j] -= v;
+        } else {
+            let idx = position(&factor.col2idx, &factor.idx2row, i, j)
+                .ok_or("the pattern of the previous factorization is not enough")?;
+            factor.idx2val[idx] -= v;
+        }
+        Ok(())
+    };
+    // scatter the lower triangular part of P A P^T
+    factor.idx2val.iter_mut().for_each(|v| *v = T::zero());
+    factor.d_sub.iter_mut().for_each(|v| *v = T::zero());
+    for (i_old, &a_ii) in row2val.iter().enumerate() {
+        let i = factor.old2new[i_old];
+        factor.d_dia[i] = a_ii;
+        let idxs = factor.a_row2idx[i_old]..factor.a_row2idx[i_old + 1];
+        for (&a_ij, idx) in idx2val[idxs.clone()].iter().zip(idxs) {
+            let j = factor.old2new[factor.a_idx2col[idx]];
+            if i > j {
+                sub(factor, i, j, -a_ij)?;
+            }
+        }
+    }
+    // right-looking elimination with the pivots of the last factorization
+    for i_d in 0..num_d {
+        let k = factor.blk2col[i_d];
+        let idxs_k = factor.col2idx[k]..factor.col2idx[k + 1];
+        let rows = factor.idx2row[idxs_k.clone()].to_vec();
+        if !is_2x2[k] {
+            let d = factor.d_dia[k];
+            if d == T::zero() || d.is_nan() {
+                return Err(format!("the pivot is zero (row {})", factor.new2old[k]));
+            }
+            let col = factor.idx2val[idxs_k.clone()].to_vec();
+            for (i0, (&i, &a_ik)) in rows.iter().zip(col.iter()).enumerate() {
+                for (&j, &a_jk) in rows[..=i0].iter().zip(col.iter()) {
+                    sub(factor, i, j, a_ik * a_jk / d)?;
+                }
+            }
+            for v in factor.idx2val[idxs_k].iter_mut() {
+                *v = *v / d;
+            }
+        } else {
+            let (d_pp, d_qq, d_qp) = (factor.d_dia[k], factor.d_dia[k + 1], factor.d_sub[k]);
+            let det = d_pp * d_qq - d_qp * d_qp;
+            if det == T::zero() || det.is_nan() {
+                return Err(format!(
+                    "the 2x2 pivot is singular (row {})",
+                    factor.new2old[k]
+                ));
+            }
+            let idxs_q = factor.col2idx[k + 1]..factor.col2idx[k + 2];
+            assert_eq!(&factor.idx2row[idxs_q.clone()], &rows[..]);
+            let col_p = factor.idx2val[idxs_k.clone()].to_vec();
+            let col_q = factor.idx2val[idxs_q.clone()].to_vec();
+            // [l_ip, l_iq] = [a_ip, a_iq] * E^-1
+            let l_p: Vec<T> = (0..rows.len())
+                .map(|i0| (col_p[i0] * d_qq - col_q[i0] * d_qp) / det)
+                .collect();
+            let l_q: Vec<T> = (0..rows.len())
+                .map(|i0| (col_q[i0] * d_pp - col_p[i0] * d_qp) / det)
+                .collect();
+            for (i0, &i) in rows.iter().enumerate() {
+                for (j0, &j) in rows[..=i0].iter().enumerate() {
+                    sub(factor, i, j, l_p[i0] * col_p[j0] + l_q[i0] * col_q[j0])?;
+                }
+            }
+            factor.idx2val[idxs_k].copy_from_slice(&l_p);
+            factor.idx2val[idxs_q].copy_from_slice(&l_q);
+        }
+    }
+    Ok(())
+}
+
+/// inertia of the matrix, i.e., the numbers of the positive, negative and zero eigenvalues
+/// computed from the eigenvalues of the blocks of `D` (Sylvester's law of inertia)
+/// * `zero_tolerance` - eigenvalues whose magnitude is not larger than this value are counted as zero
+pub fn inertia<T>(factor: &Factorization<T>, zero_tolerance: T) -> (usize, usize, usize)
+where
+    T: num_traits::Float,
+{
+    let mut res = (0, 0, 0);
+    let mut count = |lambda: T| {
+        if lambda.abs() <= zero_tolerance {
+            res.2 += 1;
+        } else if lambda > T::zero() {
+            res.0 += 1;
+        } else {
+            res.1 += 1;
+        }
+    };
+    for cols in factor.blk2col.windows(2) {
+        let k = cols[0];
+        if cols[1] - k == 1 {
+            count(factor.d_dia[k]);
+        } else {
+            // eigenvalues of the symmetric 2x2 block
+            let (a, b, c) = (factor.d_dia[k], factor.d_sub[k], factor.d_dia[k + 1]);
+            let half = T::from(0.5).unwrap();
+            let mean = (a + c) * half;
+            let radius = (((a - c) * half).powi(2) + b * b).sqrt();
+            count(mean + radius);
+            count(mean - radius);
+        }
+    }
+    res
+}
+
+/// solve `A x = b` using the factorization
+/// * `vec` - `b` as input and `x` as output
+pub fn solve_vec<T>(factor: &Factorization<T>, vec: &mut [T])
+where
+    T: num_traits::Float + std::ops::SubAssign,
+{
+    assert_eq!(vec.len(), factor.num_blk);
+    solve_mat(factor, vec);
+}
+
+/// solve `A X = B` for multiple right-hand sides using the factorization.
+/// The components corresponding to the zero pivots are set zero.
+/// * `mat` - `B` as input and `X` as output. Row-major `num_blk x num_rhs` matrix (same layout as `sparse_square::mult_mat`)
+pub fn solve_mat<T>(factor: &Factorization<T>, mat: &mut [T])
+where
+    T: num_traits::Float + std::ops::SubAssign,
+{
+    let num_blk = factor.num_blk;
+    if num_blk == 0 {
+        assert!(mat.is_empty());
+        return;
+    }
+    let num_rhs = mat.len() / num_blk;
+    assert_eq!(mat.len(), num_blk * num_rhs);
+    // y = P b
+    let mut y = vec![T::zero(); mat.len()];
+    for (i_new, &i_old) in factor.new2old.iter().enumerate() {
+        y[i_new * num_rhs..(i_new + 1) * num_rhs]
+            .copy_from_slice(&mat[i_old * num_rhs..(i_old + 1) * num_rhs]);
+    }
+    // forward substitution L y = y
+    for j in 0..num_blk {
+        for idx in factor.col2idx[j]..factor.col2idx[j + 1] {
+            let i = factor.idx2row[idx];
+            let l_ij = factor.idx2val[idx];
+            for i_rhs in 0..num_rhs {
+                let v = l_ij * y[j * num_rhs + i_rhs];
+                y[i * num_rhs + i_rhs] -= v;
+            }
+        }
+    }
+    // D y = y
+    for cols in factor.blk2col.windows(2) {
+        let k = cols[0];
+        if cols[1] - k == 1 {
+            let d = factor.d_dia[k];
+            for i_rhs in 0..num_rhs {
+                let v = &mut y[k * num_rhs + i_rhs];
+                *v = if d == T::zero() { T::zero() } else { *v / d };
+            }
+        } else {
+            let (a, b, c) = (factor.d_dia[k], factor.d_sub[k], factor.d_dia[k + 1]);
+            let det = a * c - b * b;
+            for i_rhs in 0..num_rhs {
+                let y0 = y[k * num_rhs + i_rhs];
+                let y1 = y[(k + 1) * num_rhs + i_rhs];
+                y[k * num_rhs + i_rhs] = (c * y0 - b * y1) / det;
+                y[(k + 1) * num_rhs + i_rhs] = (a * y1 - b * y0) / det;
+            }
+        }
+    }
+    // backward substitution L^T y = y
+    for j in (0..num_blk).rev() {
+        for idx in factor.col2idx[j]..factor.col2idx[j + 1] {
+            let i = factor.idx2row[idx];
+            let l_ij = factor.idx2val[idx];
+            for i_rhs in 0..num_rhs {
+                let v = l_ij * y[i * num_rhs + i_rhs];
+                y[j * num_rhs + i_rhs] -= v;
+            }
+        }
+    }
+    // x = P^T y
+    for (i_new, &i_old) in factor.new2old.iter().enumerate() {
+        mat[i_old * num_rhs..(i_old + 1) * num_rhs]
+            .copy_from_slice(&y[i_new * num_rhs..(i_new + 1) * num_rhs]);
+    }
+}
+
+/// saddle-point matrix `[A B^T; B 0]` where `A` is the Laplacian of a grid and `B` ties pairs of nodes
+#[cfg(test)]
+pub(crate) fn saddle_point_matrix(
+    num_x: usize,
+    num_y: usize,
+    num_constraint: usize,
+) -> crate::sparse_square::Matrix<f64> {
+    let a = crate::sparse_square::laplacian_grid(num_x, num_y, 0.1);
+    let num_node = a.num_blk;
+    let num_blk = num_node + num_constraint;
+    let mut row2vals = vec![Vec::<(usize, f64)>::new(); num_blk];
+    for (i_row, vals) in row2vals.iter_mut().enumerate().take(num_node) {
+        for idx in a.row2idx[i_row]..a.row2idx[i_row + 1] {
+            vals.push((a.idx2col[idx], a.idx2val[idx]));
+        }
+    }
+    for i_constraint in 0..num_constraint {
+        let i_row = num_node + i_constraint;
+        for (i_node, v) in [(i_constraint * 3, 10.), (i_constraint * 3 + 1, -20.)] {
+            row2vals[i_row].push((i_node, v));
+            row2vals[i_node].push((i_row, v));
+        }
+    }
+    let mut row2idx = vec![0; num_blk + 1];
+    let mut idx2col = vec![];
+    for (i_row, vals) in row2vals.iter().enumerate() {
+        idx2col.extend(vals.iter().map(|v| v.0));
+        row2idx[i_row + 1] = idx2col.len();
+    }
+    let mut sparse = crate::sparse_square::Matrix::<f64>::new();
+    sparse.symbolic_initialization(&row2idx, &idx2col);
+    sparse.idx2val = row2vals
+        .iter()
+        .flat_map(|v| v.iter().map(|v| v.1))
+        .collect();
+    sparse.row2val[..num_node].copy_from_slice(&a.row2val);
+    sparse
+}
+
+#[test]
+fn test_ldlt() {
+    let (num_x, num_y, num_constraint) = (6, 5, 7);
+    let mut sparse = saddle_point_matrix(num_x, num_y, num_constraint);
+    let num_blk = sparse.num_blk;
+    let mut factor = Factorization::<f64>::new();
+    factor.symbolic_initialization(&sparse.row2idx, &sparse.idx2col);
+    assert_eq!(decompose(&mut factor, &sparse), 0);
+    assert!(factor.d_sub.iter().any(|&v| v != 0.)); // 2x2 pivot is used
+    assert_eq!(
+        inertia(&factor, 1.0e-10),
+        (num_x * num_y, num_constraint, 0)
+    );
+    let num_rhs = 2;
+    let b_mat: Vec<f64> = (0..num_blk * num_rhs).map(|i| (i as f64).sin()).collect();
+    let mut x_mat = b_mat.clone();
+    solve_mat(&factor, &mut x_mat);
+    let mut r_mat = b_mat.clone();
+    crate::sparse_square::mult_mat(&mut r_mat, 1., -1., &sparse, &x_mat);
+    assert!(r_mat.iter().all(|v| v.abs() < 1.0e-8));
+    // refactorization with the same pattern
+    sparse.row2val[..num_x * num_y]
+        .iter_mut()
+        .for_each(|v| *v += 0.2);
+    sparse.idx2val.iter_mut().for_each(|v| *v *= 0.9);
+    refactorize(&mut factor, &sparse.row2val, &sparse.idx2val).unwrap();
+    assert_eq!(
+        inertia(&factor, 1.0e-10),
+        (num_x * num_y, num_constraint, 0)
+    );
+    let mut x_mat = b_mat.clone();
+    solve_mat(&factor, &mut x_mat);
+    let mut r_mat = b_mat.clone();
+    crate::sparse_square::mult_mat(&mut r_mat, 1., -1., &sparse, &x_mat);
+    assert!(r_mat.iter().all(|v| v.abs() < 1.0e-8));
+    // the zero pivot of the singular matrix is counted. The last constraint is removed by the zero values
+    let i_zero = num_blk - 1;
+    for i_row in 0..num_blk {
+        for idx in sparse.row2idx[i_row]..sparse.row2idx[i_row + 1] {
+            if i_row == i_zero || sparse.idx2col[idx] == i_zero {
+                sparse.idx2val[idx] = 0.;
+            }
+        }
+    }
+    assert_eq!(decompose(&mut factor, &sparse), 1);
+    assert_eq!(
+        inertia(&factor, 1.0e-10),
+        (num_x * num_y, num_constraint - 1, 1)
+    );
+    // the consistent right-hand side is solved with the zero component
+    let mut b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).cos()).collect();
+    b_vec[i_zero] = 0.;
+    let mut x_vec = b_vec.clone();
+    solve_vec(&factor, &mut x_vec);
+    assert_eq!(x_vec[i_zero], 0.);
+    let mut r_vec = b_vec.clone();
+    crate::sparse_square::mult_vec(&mut r_vec, 1., -1., &sparse, &x_vec);
+    assert!(r_vec.iter().all(|v| v.abs() < 1.0e-8));
+    // empty factorization
+    let empty = Factorization::<f64>::new();
+    solve_vec(&empty, &mut []);
+    solve_mat(&empty, &mut []);
+}
+
+#[test]
+fn test_ldlt_irregular() {
+    // symmetric indefinite and badly scaled. The inertia is the signs of the diagonal
+    // because the matrix stays non-singular while the off-diagonal entries are scaled down to zero
+    let mut sparse = crate::sparse_square::irregular_test_matrix(300, true, 100.);
+    for i_row in (0..sparse.num_blk).step_by(3) {
+        sparse.row2val[i_row] *= -1.;
+    }
+    let num_negative = sparse.row2val.iter().filter(|&&v| v < 0.).count();
+    let mut factor = Factorization::<f64>::new();
+    factor.symbolic_initialization(&sparse.row2idx, &sparse.idx2col);
+    assert_eq!(decompose(&mut factor, &sparse), 0);
+    assert_eq!(
+        inertia(&factor, 0.),
+        (sparse.num_blk - num_negative, num_negative, 0)
+    );
+    let b_vec: Vec<f64> = (0..sparse.num_blk).map(|i| (i as f64).sin()).collect();
+    let mut x_vec = b_vec.clone();
+    solve_vec(&factor, &mut x_vec);
+    let err = crate::sparse_square::componentwise_backward_error(&sparse, &x_vec, &b_vec);
+    assert!(err < 1.0e-10, "{}", err);
+    // refactorization with the same pattern
+    let mut sparse1 = sparse.clone();
+    sparse1.idx2val.iter_mut().for_each(|v| *v *= 0.7);
+    refactorize(&mut factor, &sparse1.row2val, &sparse1.idx2val).unwrap();
+    let mut x_vec = b_vec.clone();
+    solve_vec(&factor, &mut x_vec);
+    let err = crate::sparse_square::componentwise_backward_error(&sparse1, &x_vec, &b_vec);
+    assert!(err < 1.0e-10, "{}", err);
+    assert_eq!(
+        inertia(&factor, 0.),
+        (sparse.num_blk - num_negative, num_negative, 0)
+    );
+}
@@ -0,0 +1,497 @@
+//! Sparse direct LU factorization `P A Q = L U` with the threshold partial pivoting for general square matrices.
+//! The factorization is left-looking (Gilbert-Peierls), where the column ordering `Q` is computed in the symbolic analysis
+//! and the row permutation `P` is chosen during the numeric factorization.
+//! Once factorized, a matrix with the same pattern can be refactorized cheaply with `refactorize`,
+//! which reuses `P` and the patterns of `L` and `U` without the pivot search.
+
+/// LU factorization where the factors are stored in the Compressed Column Storage (CCS).
+/// * `col_new2old` - column ordering `Q` for the fill reduction
+/// * `row_new2old`, `row_old2new` - row permutation `P` chosen by the pivoting
+/// * `l_col2idx`, `l_idx2row`, `l_idx2val` - strictly lower triangular part of `L`. The diagonal of `L` is one
+/// * `u_col2idx`, `u_idx2row`, `u_idx2val` - strictly upper triangular part of `U`
+/// * `u_col2val` - diagonal of `U`
+/// * `pivot_tolerance` - the diagonal entry is chosen as the pivot if its magnitude is larger than
+///   `pivot_tolerance` times the largest magnitude in the column. `1` means the standard partial pivoting
+pub struct Factorization<T> {
+    pub num_blk: usize,
+    pub col_new2old: Vec<usize>,
+    pub row_new2old: Vec<usize>,
+    pub row_old2new: Vec<usize>,
+    pub l_col2idx: Vec<usize>,
+    pub l_idx2row: Vec<usize>,
+    pub l_idx2val: Vec<T>,
+    pub u_col2idx: Vec<usize>,
+    pub u_idx2row: Vec<usize>,
+    pub u_idx2val: Vec<T>,
+    pub u_col2val: Vec<T>,
+    pub pivot_tolerance: T,
+    // pattern of the input matrix in the CCS including the diagonal.
+    // `a_idx2src` points `idx2val` of the input matrix, or its `row2val` if it is larger than `idx2val.len()`
+    a_col2idx: Vec<usize>,
+    a_idx2row: Vec<usize>,
+    a_idx2src: Vec<usize>,
+}
+
+impl<T> Factorization<T>
+where
+    T: num_traits::Float,
+{
+    pub fn new() -> Self {
+        Factorization {
+            num_blk: 0,
+            col_new2old: vec![],
+            row_new2old: vec![],
+            row_old2new: vec![],
+            l_col2idx: vec![0],
+            l_idx2row: vec![],
+            l_idx2val: vec![],
+            u_col2idx: vec![0],
+            u_idx2row: vec![],
+            u_idx2val: vec![],
+            u_col2val: vec![],
+            pivot_tolerance: T::from(0.1).unwrap(),
+            a_col2idx: vec![0],
+            a_idx2row: vec![],
+            a_idx2src: vec![],
+        }
+    }
+
+    /// symbolic analysis using the minimum degree ordering of `A + A^T`
+    /// * `row2idx`, `idx2col` - non-zero pattern of the matrix (same as `sparse_square::Matrix`)
+    pub fn symbolic_initialization(&mut self, row2idx: &[usize], idx2col: &[usize]) {
+        let (sym_row2idx, sym_idx2col) = crate::ordering::symmetrize_pattern(row2idx, idx2col);
+        let new2old = crate::ordering::minimum_degree::minimum_degree(&sym_row2idx, &sym_idx2col);
+        self.symbolic_initialization_with_permutation(row2idx, idx2col, new2old);
+    }
+
+    /// symbolic analysis using the given column ordering
+    /// * `col_new2old` - column ordering `Q`
+    pub fn symbolic_initialization_with_permutation(
+        &mut self,
+        row2idx: &[usize],
+        idx2col: &[usize],
+        col_new2old: Vec<usize>,
+    ) {
+        let num_blk = row2idx.len() - 1;
+        assert_eq!(col_new2old.len(), num_blk);
+        self.num_blk = num_blk;
+        self.col_new2old = col_new2old;
+        // transpose the pattern to the CCS
+        let num_idx = idx2col.len();
+        let mut col2cnt = vec![1_usize; num_blk]; // diagonal
+        for &j_col in idx2col {
+            col2cnt[j_col] += 1;
+        }
+        self.a_col2idx = vec![0_usize; num_blk + 1];
+        for (j_col, cnt) in col2cnt.iter().enumerate() {
+            self.a_col2idx[j_col + 1] = self.a_col2idx[j_col] + cnt;
+        }
+        self.a_idx2row = vec![0_usize; self.a_col2idx[num_blk]];
+        self.a_idx2src = vec![0_usize; self.a_col2idx[num_blk]];
+        let mut col2pos = self.a_col2idx[..num_blk].to_vec();
+        for i_row in 0..num_blk {
+            let idxs = row2idx[i_row]..row2idx[i_row + 1];
+            for (&j_col, idx) in idx2col[idxs.clone()].iter().zip(idxs) {
+                self.a_idx2row[col2pos[j_col]] = i_row;
+                self.a_idx2src[col2pos[j_col]] = idx;
+                col2pos[j_col] += 1;
+            }
+            self.a_idx2row[col2pos[i_row]] = i_row;
+            self.a_idx2src[col2pos[i_row]] = num_idx + i_row;
+            col2pos[i_row] += 1;
+        }
+    }
+
+    /// number of the non-zero entries in `L` and `U` including the diagonal
+    pub fn num_nonzero(&self) -> usize {
+        self.l_idx2row.len() + self.u_idx2row.len() + self.num_blk * 2
+    }
+}
+
+impl<T> Default for Factorization<T>
+where
+    T: num_traits::Float,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// non-zero pattern of `L^-1 b` for the column `b` of the input matrix, in the topological order.
+/// The columns of `L` computed so far are traversed by the depth-first search.
+/// * `row_old2new` - step when the row became the pivot (`usize::MAX` if not yet)
+/// * `mark`, `stack` - working buffers. `mark` is set `stamp` for the visited rows
+#[allow(clippy::too_many_arguments)]
+fn reach(
+    rows: &[usize],
+    l_col2idx: &[usize],
+    l_idx2row: &[usize],
+    row_old2new: &[usize],
+    stamp: usize,
+    mark: &mut [usize],
+    stack: &mut Vec<(usize, usize)>,
+    pattern: &mut Vec<usize>,
+) {
+    pattern.clear();
+    for &i_row0 in rows {
+        if mark[i_row0] == stamp {
+            continue;
+        }
+        mark[i_row0] = stamp;
+        stack.push((i_row0, 0));
+        while let Some((i_row, i_next)) = stack.pop() {
+            let j_col = row_old2new[i_row];
+            let idxs = if j_col == usize::MAX {
+                0..0
+            } else {
+                l_col2idx[j_col] + i_next..l_col2idx[j_col + 1]
+            };
+            let mut is_done = true;
+            for idx in idxs {
+                let k_row = l_idx2row[idx];
+                if mark[k_row] == stamp {
+                    continue;
+                }
+                mark[k_row] = stamp;
+                // revisit `i_row` after `k_row`
+                stack.push((i_row, idx + 1 - l_col2idx[j_col]));
+                stack.push((k_row, 0));
+                is_done = false;
+                break;
+            }
+            if is_done {
+                pattern.push(i_row);
+            }
+        }
+    }
+    pattern.reverse();
+}
+
+/// numeric factorization with the threshold partial pivoting
+/// * `a` - matrix with the same non-zero pattern as the one used in the symbolic analysis
+pub fn decompose<T>(
+    factor: &mut Factorization<T>,
+    a: &crate::sparse_square::Matrix<T>,
+) -> Result<(), String>
+where
+    T: num_traits::Float + std::ops::SubAssign,
+{
+    let num_blk = factor.num_blk;
+    assert_eq!(a.num_blk, num_blk);
+    let num_idx = a.idx2val.len();
+    // the pattern must be the one given in the symbolic analysis
+    assert_eq!(a.idx2col.len(), num_idx);
+    assert_eq!(factor.a_idx2row.len(), num_idx + num_blk);
+    for j_col in 0..num_blk {
+        for a_idx in factor.a_col2idx[j_col]..factor.a_col2idx[j_col + 1] {
+            let (i_row, src) = (factor.a_idx2row[a_idx], factor.a_idx2src[a_idx]);
+            if src >= num_idx {
+                assert_eq!(i_row, j_col);
+                continue;
+            }
+            assert!(
+                a.row2idx[i_row] <= src && src < a.row2idx[i_row + 1] && a.idx2col[src] == j_col,
+                "the pattern differs from the symbolic analysis"
+            );
+        }
+    }
+    factor.row_old2new = vec![usize::MAX; num_blk];
+    factor.row_new2old.clear();
+    factor.l_col2idx = vec![0];
+    factor.l_idx2row.clear();
+    factor.l_idx2val.clear();
+    factor.u_col2idx = vec![0];
+    factor.u_idx2row.clear();
+    factor.u_idx2val.clear();
+    factor.u_col2val = vec![T::zero(); num_blk];
+    let mut x = vec![T::zero(); num_blk];
+    let mut mark = vec![usize::MAX; num_blk];
+    let mut stack = Vec::<(usize, usize)>::new();
+    let mut pattern = Vec::<usize>::new();
+    for k in 0..num_blk {
+        let k_col = factor.col_new2old[k];
+        let a_idxs = factor.a_col2idx[k_col]..factor.a_col2idx[k_col + 1];
+        reach(
+            &factor.a_idx2row[a_idxs.clone()],
+            &factor.l_col2idx,
+            &factor.l_idx2row,
+            &factor.row_old2new,
+            k,
+            &mut mark,
+            &mut stack,
+            &mut pattern,
+        );
+        // sparse triangular solve x = L^-1 a
+        for a_idx in a_idxs {
+            let src = factor.a_idx2src[a_idx];
+            x[factor.a_idx2row[a_idx]] = if src < num_idx {
+                a.idx2val[src]
+            } else {
+                a.row2val[src - num_idx]
+            };
+        }
+        for &i_row in &pattern {
+            let j_col = factor.row_old2new[i_row];
+            if j_col == usize::MAX {
+                continue;
+            }
+            let x_i = x[i_row];
+            for idx in factor.l_col2idx[j_col]..factor.l_col2idx[j_col + 1] {
+                x[factor.l_idx2row[idx]] -= factor.l_idx2val[idx] * x_i;
+            }
+        }
+        // choose the pivot
+        let mut i_row_pivot = usize::MAX;
+        let mut max_abs = T::zero();
+        for &i_row in &pattern {
+            if factor.row_old2new[i_row] != usize::MAX {
+                // entry of U
+                factor.u_idx2row.push(factor.row_old2new[i_row]);
+                factor.u_idx2val.push(x[i_row]);
+            } else if x[i_row].abs() > max_abs {
+                max_abs = x[i_row].abs();
+                i_row_pivot = i_row;
+            }
+        }
+        if i_row_pivot == usize::MAX || max_abs.is_nan() {
+            return Err(format!("the matrix is singular (column {})", k_col));
+        }
+        if factor.row_old2new[k_col] == usize::MAX
+            && mark[k_col] == k
+            && x[k_col].abs() >= factor.pivot_tolerance * max_abs
+        {
+            i_row_pivot = k_col; // prefer the diagonal
+        }
+        let pivot = x[i_row_pivot];
+        factor.u_col2val[k] = pivot;
+        factor.u_col2idx.push(factor.u_idx2row.len());
+        factor.row_old2new[i_row_pivot] = k;
+        // entries of L with the original row indices (renumbered at the end)
+        for &i_row in &pattern {
+            if factor.row_old2new[i_row] == usize::MAX {
+                factor.l_idx2row.push(i_row);
+                factor.l_idx2val.push(x[i_row] / pivot);
+            }
+            x[i_row] = T::zero();
+        }
+        factor.l_col2idx.push(factor.l_idx2row.len());
+    }
+    for i_row in factor.l_idx2row.iter_mut() {
+        *i_row = factor.row_old2new[*i_row];
+    }
+    factor.row_new2old = crate::ordering::inverse_permutation(&factor.row_old2new);
+    Ok(())
+}
+
+/// numeric factorization reusing the row permutation and the patterns of `L` and `U` of the last `decompose`.
+/// It fails if the pivot becomes zero or smaller than `pivot_tolerance` times the largest magnitude in the column.
+/// Call `decompose` again in that case.
+/// * `row2val` - diagonal of `A`
+/// * `idx2val` - off-diagonal values of `A` in the order of `idx2col` given in the symbolic analysis
+pub fn refactorize<T>(
+    factor: &mut Factorization<T>,
+    row2val: &[T],
+    idx2val: &[T],
+) -> Result<(), String>
+where
+    T: num_traits::Float + std::ops::SubAssign,
+{
+    let num_blk = factor.num_blk;
+    let num_idx = idx2val.len();
+    assert_eq!(row2val.len(), num_blk);
+    assert_eq!(factor.a_idx2row.len(), num_idx + num_blk);
+    assert_eq!(factor.row_new2old.len(), num_blk, "decompose is not called");
+    // dense column in the pivoted row ordering
+    let mut x = vec![T::zero(); num_blk];
+    for k in 0..num_blk {
+        let k_col = factor.col_new2old[k];
+        for a_idx in factor.a_col2idx[k_col]..factor.a_col2idx[k_col + 1] {
+            let src = factor.a_idx2src[a_idx];
+            x[factor.row_old2new[factor.a_idx2row[a_idx]]] = if src < num_idx {
+                idx2val[src]
+            } else {
+                row2val[src - num_idx]
+            };
+        }
+        // the entries of U are stored in the topological order
+        for idx in factor.u_col2idx[k]..factor.u_col2idx[k + 1] {
+            let j = factor.u_idx2row[idx];
+            let x_j = x[j];
+            x[j] = T::zero();
+            factor.u_idx2val[idx] = x_j;
+            for l_idx in factor.l_col2idx[j]..factor.l_col2idx[j + 1] {
+                x[factor.l_idx2row[l_idx]] -= factor.l_idx2val[l_idx] * x_j;
+            }
+        }
+        let pivot = x[k];
+        x[k] = T::zero();
+        let l_idxs = factor.l_col2idx[k]..factor.l_col2idx[k + 1];
+        let max_abs = factor.l_idx2row[l_idxs.clone()]
+            .iter()
+            .fold(T::zero(), |m, &i| m.max(x[i].abs()));
+        if pivot == T::zero()
+            || pivot.is_nan()
+            || pivot.abs() < factor.pivot_tolerance.min(T::one()) * max_abs
+        {
+            return Err(format!(
+                "the pivot of the previous factorization is not stable (column {})",
+                k_col
+            ));
+        }
+        factor.u_col2val[k] = pivot;
+        for l_idx in l_idxs {
+            let i = factor.l_idx2row[l_idx];
+            factor.l_idx2val[l_idx] = x[i] / pivot;
+            x[i] = T::zero();
+        }
+    }
+    Ok(())
+}
+
+/// solve `A x = b` using the factorization
+/// * `vec` - `b` as input and `x` as output
+pub fn solve_vec<T>(factor: &Factorization<T>, vec: &mut [T])
+where
+    T: num_traits::Float + std::ops::SubAssign,
+{
+    assert_eq!(vec.len(), factor.num_blk);
+    solve_mat(factor, vec);
+}
+
+/// solve `A X = B` for multiple right-hand sides using the factorization
+/// * `mat` - `B` as input and `X` as output. Row-major `num_blk x num_rhs` matrix (same layout as `sparse_square::mult_mat`)
+pub fn solve_mat<T>(factor: &Factorization<T>, mat: &mut [T])
+where
+    T: num_traits::Float + std::ops::SubAssign,
+{
+    let num_blk = factor.num_blk;
+    if num_blk == 0 {
+        assert!(mat.is_empty());
+        return;
+    }
+    let num_rhs = mat.len() / num_blk;
+    assert_eq!(mat.len(), num_blk * num_rhs);
+    // y = P b
+    let mut y = vec![T::zero(); mat.len()];
+    for (i_new, &i_old) in factor.row_new2old.iter().enumerate() {
+        y[i_new * num_rhs..(i_new + 1) * num_rhs]
+            .copy_from_slice(&mat[i_old * num_rhs..(i_old + 1) * num_rhs]);
+    }
+    // forward substitution L y = y
+    for j in 0..num_blk {
+        for idx in factor.l_col2idx[j]..factor.l_col2idx[j + 1] {
+            let i = factor.l_idx2row[idx];
+            let l_ij = factor.l_idx2val[idx];
+            for i_rhs in 0..num_rhs {
+                let v = l_ij * y[j * num_rhs + i_rhs];
+                y[i * num_rhs + i_rhs] -= v;
+            }
+        }
+    }
+    // backward substitution U y = y
+    for j in (0..num_blk).rev() {
+        let inv_u_jj = T::one() / factor.u_col2val[j];
+        for i_rhs in 0..num_rhs {
+            y[j * num_rhs + i_rhs] = y[j * num_rhs + i_rhs] * inv_u_jj;
+        }
+        for idx in factor.u_col2idx[j]..factor.u_col2idx[j + 1] {
+            let i = factor.u_idx2row[idx];
+            let u_ij = factor.u_idx2val[idx];
+            for i_rhs in 0..num_rhs {
+                let v = u_ij * y[j * num_rhs + i_rhs];
+                y[i * num_rhs + i_rhs] -= v;
+            }
+        }
+    }
+    // x = Q y
+    for (i_new, &i_old) in factor.col_new2old.iter().enumerate() {
+        mat[i_old * num_rhs..(i_old + 1) * num_rhs]
+            .copy_from_slice(&y[i_new * num_rhs..(i_new + 1) * num_rhs]);
+    }
+}
+
+#[test]
+fn test_lu() {
+    // non-symmetric matrix with zero diagonal entries
+    let mut sparse = crate::sparse_square::laplacian_grid(11, 9, 0.01);
+    let num_blk = sparse.num_blk;
+    for i_row in 0..num_blk {
+        for idx in sparse.row2idx[i_row]..sparse.row2idx[i_row + 1] {
+            if sparse.idx2col[idx] > i_row {
+                sparse.idx2val[idx] += 0.5;
+            }
+        }
+        if i_row % 5 == 0 {
+            sparse.row2val[i_row] = 0.;
+        }
+    }
+    let mut factor = Factorization::<f64>::new();
+    factor.symbolic_initialization(&sparse.row2idx, &sparse.idx2col);
+    decompose(&mut factor, &sparse).unwrap();
+    let num_rhs = 2;
+    let b_mat: Vec<f64> = (0..num_blk * num_rhs).map(|i| (i as f64).sin()).collect();
+    let mut x_mat = b_mat.clone();
+    solve_mat(&factor, &mut x_mat);
+    let mut r_mat = b_mat.clone();
+    crate::sparse_square::mult_mat(&mut r_mat, 1., -1., &sparse, &x_mat);
+    assert!(r_mat.iter().all(|v| v.abs() < 1.0e-8));
+    // refactorization with the same pattern
+    sparse.idx2val.iter_mut().for_each(|v| *v *= 1.1);
+    refactorize(&mut factor, &sparse.row2val, &sparse.idx2val).unwrap();
+    let mut x_mat = b_mat.clone();
+    solve_mat(&factor, &mut x_mat);
+    let mut r_mat = b_mat.clone();
+    crate::sparse_square::mult_mat(&mut r_mat, 1., -1., &sparse, &x_mat);
+    assert!(r_mat.iter().all(|v| v.abs() < 1.0e-8));
+    // singular matrix
+    sparse.set_zero();
+    assert!(decompose(&mut factor, &sparse).is_err());
+    // empty factorization
+    let empty = Factorization::<f64>::new();
+    solve_vec(&empty, &mut []);
+    solve_mat(&empty, &mut []);
+}
+
+#[test]
+#[should_panic(expected = "the pattern differs from the symbolic analysis")]
+fn test_lu_pattern_mismatch() {
+    let sparse0 = crate::sparse_square::laplacian_grid(5, 4, 0.1);
+    let sparse1 = crate::sparse_square::laplacian_grid(4, 5, 0.1);
+    assert_eq!(sparse0.idx2col.len(), sparse1.idx2col.len());
+    let mut factor = Factorization::<f64>::new();
+    factor.symbolic_initialization(&sparse0.row2idx, &sparse0.idx2col);
+    let _ = decompose(&mut factor, &sparse1);
+}
+
+#[test]
+fn test_lu_irregular() {
+    // unsymmetric values, badly scaled and zero diagonal entries
+    let mut sparse = crate::sparse_square::irregular_test_matrix(300, false, 100.);
+    for i_row in (0..sparse.num_blk).step_by(7) {
+        sparse.row2val[i_row] = 0.;
+    }
+    let mut factor = Factorization::<f64>::new();
+    factor.symbolic_initialization(&sparse.row2idx, &sparse.idx2col);
+    decompose(&mut factor, &sparse).unwrap();
+    let b_vec: Vec<f64> = (0..sparse.num_blk).map(|i| (i as f64).sin()).collect();
+    let mut x_vec = b_vec.clone();
+    solve_vec(&factor, &mut x_vec);
+    let err = crate::sparse_square::componentwise_backward_error(&sparse, &x_vec, &b_vec);
+    assert!(err < 1.0e-10, "{}", err);
+    // refactorization with the same pattern. The column scaling keeps the pivots stable
+    let mut sparse1 = sparse.clone();
+    let col2scale: Vec<f64> = (0..sparse.num_blk)
+        .map(|i| 1.5 + (i as f64).cos())
+        .collect();
+    for i_row in 0..sparse.num_blk {
+        sparse1.row2val[i_row] *= col2scale[i_row];
+        for idx in sparse.row2idx[i_row]..sparse.row2idx[i_row + 1] {
+            sparse1.idx2val[idx] *= col2scale[sparse.idx2col[idx]];
+        }
+    }
+    refactorize(&mut factor, &sparse1.row2val, &sparse1.idx2val).unwrap();
+    let mut x_vec = b_vec.clone();
+    solve_vec(&factor, &mut x_vec);
+    let err = crate::sparse_square::componentwise_backward_error(&sparse1, &x_vec, &b_vec);
+    assert!(err < 1.0e-10, "{}", err);
+}
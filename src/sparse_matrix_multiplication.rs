@@ -107,6 +107,29 @@ fn symbolic_multiplication(
     (c_row2idx, c_idx2col)
 }
 
+/// non-zero pattern of the power `A^power` of the square matrix without the diagonal entries, with sorted columns
+/// * `row2idx`, `idx2col` - non-zero pattern of `A` (the diagonal entry is implicitly non-zero)
+/// * `power` - exponent larger than zero
+pub fn symbolic_power(
+    row2idx: &[usize],
+    idx2col: &[usize],
+    power: usize,
+) -> (Vec<usize>, Vec<usize>) {
+    assert!(power > 0);
+    let num_row = row2idx.len() - 1;
+    let mut c_row2idx = row2idx.to_vec();
+    let mut c_idx2col = idx2col.to_vec();
+    for _ in 1..power {
+        (c_row2idx, c_idx2col) = symbolic_multiplication(
+            &c_row2idx, &c_idx2col, true, row2idx, idx2col, true, num_row, true,
+        );
+    }
+    for i_row in 0..num_row {
+        c_idx2col[c_row2idx[i_row]..c_row2idx[i_row + 1]].sort();
+    }
+    (c_row2idx, c_idx2col)
+}
+
 pub fn mult_square_matrices<T>(
     m0: &crate::sparse_square::Matrix<T>,
     m1: &crate::sparse_square::Matrix<T>,
@@ -51,6 +51,35 @@ where
         self.row2val.resize_with(self.num_blk, Default::default);
     }
 
+    /// symmetric permutation `P A P^T` of the matrix.
+    /// The columns are sorted in each row of the new matrix.
+    /// * `new2old` - permutation of the rows and columns (see `crate::ordering`)
+    pub fn permute(&self, new2old: &[usize]) -> Self {
+        assert_eq!(new2old.len(), self.num_blk);
+        let old2new = crate::ordering::inverse_permutation(new2old);
+        let mut row2idx = vec![0_usize; self.num_blk + 1];
+        let mut idx2col = Vec::<usize>::with_capacity(self.idx2col.len());
+        let mut idx2val = Vec::<MAT>::with_capacity(self.idx2val.len());
+        let mut row = Vec::<(usize, MAT)>::new();
+        for (i_new, &i_old) in new2old.iter().enumerate() {
+            row.clear();
+            for idx in self.row2idx[i_old]..self.row2idx[i_old + 1] {
+                row.push((old2new[self.idx2col[idx]], self.idx2val[idx]));
+            }
+            row.sort_by_key(|v| v.0);
+            idx2col.extend(row.iter().map(|v| v.0));
+            idx2val.extend(row.iter().map(|v| v.1));
+            row2idx[i_new + 1] = idx2col.len();
+        }
+        Matrix {
+            num_blk: self.num_blk,
+            row2idx,
+            idx2col,
+            idx2val,
+            row2val: new2old.iter().map(|&i_old| self.row2val[i_old]).collect(),
+        }
+    }
+
     /// set zero to all the values
     pub fn set_zero(&mut self) {
         assert_eq!(self.idx2val.len(), self.idx2col.len());
@@ -70,11 +99,39 @@ where
         emat: &[MAT],
         merge_buffer: &mut Vec<usize>,
     ) {
+        self.merge_with_map(node2row, node2col, emat, merge_buffer, |i| i);
+    }
+
+    /// merge element-wise matrix to the sparse matrix permuted by `permute`.
+    /// The nodes of the element are given by the original indices and mapped to the new ones.
+    /// * `old2new` - inverse of the permutation `new2old` used in `permute`
+    pub fn merge_permuted(
+        &mut self,
+        node2row: &[usize],
+        node2col: &[usize],
+        emat: &[MAT],
+        old2new: &[usize],
+        merge_buffer: &mut Vec<usize>,
+    ) {
+        assert_eq!(old2new.len(), self.num_blk);
+        self.merge_with_map(node2row, node2col, emat, merge_buffer, |i| old2new[i]);
+    }
+
+    fn merge_with_map<F>(
+        &mut self,
+        node2row: &[usize],
+        node2col: &[usize],
+        emat: &[MAT],
+        merge_buffer: &mut Vec<usize>,
+        map: F,
+    ) where
+        F: Fn(usize) -> usize,
+    {
         assert_eq!(emat.len(), node2row.len() * node2col.len());
         merge_buffer.resize(self.num_blk, usize::MAX);
         let col2idx = merge_buffer;
         for inode in 0..node2row.len() {
-            let i_row = node2row[inode];
+            let i_row = map(node2row[inode]);
             assert!(i_row < self.num_blk);
             for ij_idx in self.row2idx[i_row]..self.row2idx[i_row + 1] {
                 assert!(ij_idx < self.idx2col.len());
@@ -82,7 +139,7 @@ where
                 col2idx[j_col] = ij_idx;
             }
             for jnode in 0..node2col.len() {
-                let j_col = node2col[jnode];
+                let j_col = map(node2col[jnode]);
                 assert!(j_col < self.num_blk);
                 if i_row == j_col {
                     // Marge Diagonal
@@ -136,12 +193,13 @@ where
     T: std::ops::MulAssign // *=
         + std::ops::Mul<Output = T> // *
         + std::ops::AddAssign // +=
-        + 'static
         + Copy, // =
-    f32: num_traits::AsPrimitive<T>,
 {
     let num_row = a_mat.row2idx.len() - 1;
     assert_eq!(y_mat.len(), x_mat.len());
+    if num_row == 0 {
+        return;
+    }
     let num_dim = y_mat.len() / num_row;
     assert_eq!(y_mat.len(), num_dim * num_row);
     for val_y in y_mat.iter_mut() {
@@ -161,6 +219,131 @@ where
     }
 }
 
+/// split the rows into `num_thread` contiguous ranges with the similar number of the non-zero blocks
+/// * return - first row of each range (length is the number of the ranges + 1)
+pub fn partition_rows(row2idx: &[usize], num_thread: usize) -> Vec<usize> {
+    let num_row = row2idx.len() - 1;
+    let num_thread = num_thread.clamp(1, num_row.max(1));
+    let work = |i_row: usize| row2idx[i_row] - row2idx[0] + i_row; // off-diagonal and diagonal blocks
+    let work_total = work(num_row);
+    let mut part2row = vec![0_usize; num_thread + 1];
+    let mut i_row = 0;
+    for (i_part, row) in part2row.iter_mut().enumerate().skip(1) {
+        let target = work_total * i_part / num_thread;
+        while i_row < num_row && work(i_row) < target {
+            i_row += 1;
+        }
+        *row = i_row;
+    }
+    part2row
+}
+
+/// `{y_rows} <- \alpha * [a_mat] * {x_mat} + \beta * {y_rows}` for the rows starting from `i_row0`
+fn mult_mat_rows<T>(
+    y_rows: &mut [T],
+    i_row0: usize,
+    beta: T,
+    alpha: T,
+    a_mat: &Matrix<T>,
+    x_mat: &[T],
+) where
+    T: std::ops::MulAssign + std::ops::Mul<Output = T> + std::ops::AddAssign + Copy,
+{
+    if a_mat.num_blk == 0 {
+        return;
+    }
+    let num_dim = x_mat.len() / a_mat.num_blk;
+    for (i0, y_row) in y_rows.chunks_mut(num_dim).enumerate() {
+        let i_row = i_row0 + i0;
+        for y in y_row.iter_mut() {
+            *y *= beta;
+        }
+        for idx in a_mat.row2idx[i_row]..a_mat.row2idx[i_row + 1] {
+            let j_col = a_mat.idx2col[idx];
+            let a_ij = alpha * a_mat.idx2val[idx];
+            for (y, &x) in y_row
+                .iter_mut()
+                .zip(&x_mat[j_col * num_dim..(j_col + 1) * num_dim])
+            {
+                *y += a_ij * x;
+            }
+        }
+        let a_ii = alpha * a_mat.row2val[i_row];
+        for (y, &x) in y_row
+            .iter_mut()
+            .zip(&x_mat[i_row * num_dim..(i_row + 1) * num_dim])
+        {
+            *y += a_ii * x;
+        }
+    }
+}
+
+/// multi-threaded version of `mult_mat`, where the rows are partitioned by `partition_rows`
+/// * `num_thread` - number of the threads. Computed on the current thread if it is one
+pub fn par_mult_mat<T>(
+    y_mat: &mut [T],
+    beta: T,
+    alpha: T,
+    a_mat: &Matrix<T>,
+    x_mat: &[T],
+    num_thread: usize,
+) where
+    T: std::ops::MulAssign + std::ops::Mul<Output = T> + std::ops::AddAssign + Copy + Send + Sync,
+{
+    let num_row = a_mat.num_blk;
+    assert_eq!(y_mat.len(), x_mat.len());
+    let num_dim = y_mat.len() / num_row.max(1);
+    assert_eq!(y_mat.len(), num_dim * num_row);
+    if num_thread <= 1 {
+        mult_mat_rows(y_mat, 0, beta, alpha, a_mat, x_mat);
+        return;
+    }
+    let part2row = partition_rows(&a_mat.row2idx, num_thread);
+    std::thread::scope(|scope| {
+        let mut y_rest = y_mat;
+        for rows in part2row.windows(2) {
+            let (y_rows, tail) = y_rest.split_at_mut((rows[1] - rows[0]) * num_dim);
+            y_rest = tail;
+            let i_row0 = rows[0];
+            scope.spawn(move || mult_mat_rows(y_rows, i_row0, beta, alpha, a_mat, x_mat));
+        }
+    });
+}
+
+/// minimum number of the non-zero entries per thread for which `par_mult_mat` spawns the threads.
+/// Spawning a scoped thread costs a few tens of microseconds while the product costs a few nanoseconds
+/// per entry, so that the spawn is below about 10% of the work of each thread
+pub const NUM_NNZ_PER_THREAD_MULT: usize = 100_000;
+
+/// number of the threads for `par_mult_mat` with the matrix and `num_dim` values per block.
+/// It is one if the matrix has less than `2 * NUM_NNZ_PER_THREAD_MULT` entries,
+/// and it is at most `std::thread::available_parallelism()` otherwise
+pub fn num_thread_for_mult<T>(a_mat: &Matrix<T>, num_dim: usize) -> usize {
+    let nnz = (a_mat.idx2col.len() + a_mat.num_blk) * num_dim;
+    let num_thread = nnz / NUM_NNZ_PER_THREAD_MULT;
+    if num_thread <= 1 {
+        return 1;
+    }
+    let num_core = std::thread::available_parallelism().map_or(1, |n| n.get());
+    num_thread.min(num_core)
+}
+
+/// multi-threaded version of `mult_vec`
+/// * `num_thread` - number of the threads. Computed on the current thread if it is one
+pub fn par_mult_vec<T>(
+    y_vec: &mut [T],
+    beta: T,
+    alpha: T,
+    a_mat: &Matrix<T>,
+    x_vec: &[T],
+    num_thread: usize,
+) where
+    T: std::ops::MulAssign + std::ops::Mul<Output = T> + std::ops::AddAssign + Copy + Send + Sync,
+{
+    assert_eq!(y_vec.len(), a_mat.num_blk);
+    par_mult_mat(y_vec, beta, alpha, a_mat, x_vec, num_thread);
+}
+
 #[test]
 fn test_scalar() {
     let mut sparse = crate::sparse_square::Matrix::<f32>::new();
@@ -180,3 +363,259 @@ fn test_scalar() {
     lhs.resize(nblk, Default::default());
     mult_vec(&mut lhs, 1.0, 1.0, &sparse, &rhs);
 }
+
+#[test]
+fn test_permute() {
+    let (num_x, num_y) = (7, 5);
+    let sparse0 = laplacian_grid(num_x, num_y, 0.);
+    let num_blk = sparse0.num_blk;
+    let new2old: Vec<usize> = (0..num_blk).map(|i| (i * 3) % num_blk).collect();
+    let old2new = crate::ordering::inverse_permutation(&new2old);
+    let sparse1 = sparse0.permute(&new2old);
+    // merge the elements to the permuted matrix with the original node indices
+    let mut sparse2 = sparse1.clone();
+    sparse2.set_zero();
+    let mut merge_buffer = Vec::<usize>::new();
+    let emat = [1., -1., -1., 1.];
+    for i0 in 0..num_blk {
+        if (i0 + 1) % num_x != 0 {
+            let nodes = [i0, i0 + 1];
+            sparse2.merge_permuted(&nodes, &nodes, &emat, &old2new, &mut merge_buffer);
+        }
+        if i0 + num_x < num_blk {
+            let nodes = [i0, i0 + num_x];
+            sparse2.merge_permuted(&nodes, &nodes, &emat, &old2new, &mut merge_buffer);
+        }
+    }
+    assert_eq!(sparse1.idx2val, sparse2.idx2val);
+    assert_eq!(sparse1.row2val, sparse2.row2val);
+    for (i_new, &i_old) in new2old.iter().enumerate() {
+        assert_eq!(sparse1.row2val[i_new], sparse0.row2val[i_old]);
+        for idx in sparse0.row2idx[i_old]..sparse0.row2idx[i_old + 1] {
+            let j_new = old2new[sparse0.idx2col[idx]];
+            let cols = &sparse1.idx2col[sparse1.row2idx[i_new]..sparse1.row2idx[i_new + 1]];
+            let pos = cols.binary_search(&j_new).unwrap();
+            assert_eq!(
+                sparse1.idx2val[sparse1.row2idx[i_new] + pos],
+                sparse0.idx2val[idx]
+            );
+        }
+    }
+}
+
+#[test]
+fn test_permute_irregular() {
+    // unsymmetric values merged one row at a time as `1 x n` elements
+    let sparse0 = irregular_test_matrix(200, false, 100.);
+    let new2old = crate::ordering::reverse_cuthill_mckee::reverse_cuthill_mckee(
+        &sparse0.row2idx,
+        &sparse0.idx2col,
+    );
+    let old2new = crate::ordering::inverse_permutation(&new2old);
+    let sparse1 = sparse0.permute(&new2old);
+    let mut sparse2 = sparse1.clone();
+    sparse2.set_zero();
+    let mut merge_buffer = Vec::<usize>::new();
+    for i_row in 0..sparse0.num_blk {
+        let idxs = sparse0.row2idx[i_row]..sparse0.row2idx[i_row + 1];
+        let mut cols = vec![i_row];
+        cols.extend_from_slice(&sparse0.idx2col[idxs.clone()]);
+        let mut emat = vec![sparse0.row2val[i_row]];
+        emat.extend_from_slice(&sparse0.idx2val[idxs]);
+        sparse2.merge_permuted(&[i_row], &cols, &emat, &old2new, &mut merge_buffer);
+    }
+    assert_eq!(sparse1.idx2val, sparse2.idx2val);
+    assert_eq!(sparse1.row2val, sparse2.row2val);
+    // P A P^T P x = P A x
+    let x_vec: Vec<f64> = (0..sparse0.num_blk).map(|i| (i as f64).sin()).collect();
+    let mut y_vec = vec![0.; sparse0.num_blk];
+    mult_vec(&mut y_vec, 0., 1., &sparse0, &x_vec);
+    let mut y_vec_new = vec![0.; sparse0.num_blk];
+    let x_vec_new = crate::ordering::permute_vec(&new2old, &x_vec);
+    mult_vec(&mut y_vec_new, 0., 1., &sparse1, &x_vec_new);
+    let y_vec1 = crate::ordering::unpermute_vec(&new2old, &y_vec_new);
+    for i_blk in 0..sparse0.num_blk {
+        assert!((y_vec[i_blk] - y_vec1[i_blk]).abs() < 1.0e-10 * y_vec[i_blk].abs().max(1.));
+    }
+}
+
+/// Laplacian of a `num_x` by `num_y` grid graph with `shift` added to the diagonal
+#[cfg(test)]
+pub(crate) fn laplacian_grid(num_x: usize, num_y: usize, shift: f64) -> Matrix<f64> {
+    let num_blk = num_x * num_y;
+    let mut row2idx = vec![0_usize; num_blk + 1];
+    let mut idx2col = Vec::<usize>::new();
+    for iy in 0..num_y {
+        for ix in 0..num_x {
+            if iy > 0 {
+                idx2col.push((iy - 1) * num_x + ix);
+            }
+            if ix > 0 {
+                idx2col.push(iy * num_x + ix - 1);
+            }
+            if ix + 1 < num_x {
+                idx2col.push(iy * num_x + ix + 1);
+            }
+            if iy + 1 < num_y {
+                idx2col.push((iy + 1) * num_x + ix);
+            }
+            row2idx[iy * num_x + ix + 1] = idx2col.len();
+        }
+    }
+    let mut sparse = Matrix::<f64>::new();
+    sparse.symbolic_initialization(&row2idx, &idx2col);
+    sparse.set_zero();
+    let mut merge_buffer = Vec::<usize>::new();
+    let emat = [1., -1., -1., 1.];
+    for iy in 0..num_y {
+        for ix in 0..num_x {
+            let i0 = iy * num_x + ix;
+            if ix + 1 < num_x {
+                sparse.merge(&[i0, i0 + 1], &[i0, i0 + 1], &emat, &mut merge_buffer);
+            }
+            if iy + 1 < num_y {
+                let i1 = i0 + num_x;
+                sparse.merge(&[i0, i1], &[i0, i1], &emat, &mut merge_buffer);
+            }
+        }
+    }
+    sparse.row2val.iter_mut().for_each(|v| *v += shift);
+    sparse
+}
+
+/// diagonally dominant matrix with an irregular (non-grid) pattern for the tests.
+/// Each row has the ring neighbors and a few pseudo-random far neighbors, so the number of the non-zeros varies per row.
+/// The pattern is symmetric, and the values are `D * A0 * D` with the diagonal scaling `D_ii` in `[1/scale, scale]`
+/// * `is_symmetric` - if false, the off-diagonal values of `A0` are unsymmetric
+/// * `scale` - range of the row and column scaling (`1.` for no scaling)
+#[cfg(test)]
+pub(crate) fn irregular_test_matrix(num_blk: usize, is_symmetric: bool, scale: f64) -> Matrix<f64> {
+    let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+    let mut random = || {
+        // xorshift
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    let mut row2cols = vec![std::collections::BTreeSet::<usize>::new(); num_blk];
+    let mut add_edge = |i: usize, j: usize| {
+        if i != j {
+            row2cols[i].insert(j);
+            row2cols[j].insert(i);
+        }
+    };
+    for i_row in 0..num_blk {
+        add_edge(i_row, (i_row + 1) % num_blk);
+        for _ in 0..(random() % 3) {
+            add_edge(i_row, (random() % num_blk as u64) as usize);
+        }
+    }
+    let mut row2idx = vec![0_usize; num_blk + 1];
+    let mut idx2col = Vec::<usize>::new();
+    for i_row in 0..num_blk {
+        idx2col.extend(row2cols[i_row].iter());
+        row2idx[i_row + 1] = idx2col.len();
+    }
+    let mut sparse = Matrix::<f64>::new();
+    sparse.symbolic_initialization(&row2idx, &idx2col);
+    sparse.set_zero();
+    let mut random_unit = || (random() % 1000) as f64 / 1000.;
+    let row2scale: Vec<f64> = (0..num_blk)
+        .map(|_| scale.powf(2. * random_unit() - 1.))
+        .collect();
+    for i_row in 0..num_blk {
+        for idx in row2idx[i_row]..row2idx[i_row + 1] {
+            let j_col = idx2col[idx];
+            if is_symmetric && j_col < i_row {
+                continue;
+            }
+            let a_ij = -0.2 - random_unit();
+            sparse.idx2val[idx] = a_ij;
+            if is_symmetric {
+                let idx_ji = row2idx[j_col]
+                    + idx2col[row2idx[j_col]..row2idx[j_col + 1]]
+                        .iter()
+                        .position(|&k| k == i_row)
+                        .unwrap();
+                sparse.idx2val[idx_ji] = a_ij;
+            }
+        }
+    }
+    // strictly diagonally dominant in both the rows and the columns
+    let mut row2sum = vec![0.1; num_blk];
+    for i_row in 0..num_blk {
+        for idx in row2idx[i_row]..row2idx[i_row + 1] {
+            row2sum[i_row] += sparse.idx2val[idx].abs();
+            row2sum[idx2col[idx]] += sparse.idx2val[idx].abs();
+        }
+    }
+    for i_row in 0..num_blk {
+        sparse.row2val[i_row] = row2sum[i_row] * row2scale[i_row] * row2scale[i_row];
+        for idx in row2idx[i_row]..row2idx[i_row + 1] {
+            sparse.idx2val[idx] *= row2scale[i_row] * row2scale[idx2col[idx]];
+        }
+    }
+    sparse
+}
+
+#[test]
+fn test_par_mult() {
+    let sparse = laplacian_grid(23, 17, 0.1);
+    let num_blk = sparse.num_blk;
+    let part2row = partition_rows(&sparse.row2idx, 4);
+    assert_eq!(part2row.len(), 5);
+    assert!(part2row.windows(2).all(|v| v[0] < v[1]));
+    for num_dim in [1, 3] {
+        let x_mat: Vec<f64> = (0..num_blk * num_dim).map(|i| (i as f64).sin()).collect();
+        let y_mat0: Vec<f64> = (0..num_blk * num_dim).map(|i| (i as f64).cos()).collect();
+        let mut y_mat1 = y_mat0.clone();
+        mult_mat(&mut y_mat1, 0.5, 2., &sparse, &x_mat);
+        for num_thread in [1, 4] {
+            let mut y_mat2 = y_mat0.clone();
+            par_mult_mat(&mut y_mat2, 0.5, 2., &sparse, &x_mat, num_thread);
+            assert_eq!(y_mat1, y_mat2);
+        }
+        if num_dim == 1 {
+            let mut y_vec = y_mat0.clone();
+            par_mult_vec(&mut y_vec, 0.5, 2., &sparse, &x_mat, 3);
+            assert_eq!(y_mat1, y_vec);
+        }
+    }
+    // the threads are used only for the large number of the entries
+    let nnz = sparse.idx2col.len() + num_blk;
+    assert_eq!(num_thread_for_mult(&sparse, 1), 1);
+    let num_dim = 4 * NUM_NNZ_PER_THREAD_MULT / nnz + 1;
+    let num_core = std::thread::available_parallelism().unwrap().get();
+    assert_eq!(num_thread_for_mult(&sparse, num_dim), num_core.min(4));
+    // empty matrix
+    let sparse = Matrix::<f64>::new();
+    assert_eq!(num_thread_for_mult(&sparse, 1), 1);
+    for num_thread in [1, 4] {
+        par_mult_mat(&mut [], 0.5, 2., &sparse, &[], num_thread);
+    }
+    mult_mat(&mut [], 0.5, 2., &sparse, &[]);
+}
+
+/// component-wise backward error `max_i |b - A x|_i / (|A| |x| + |b|)_i` for the tests on the badly scaled matrices
+#[cfg(test)]
+pub(crate) fn componentwise_backward_error(
+    a_mat: &Matrix<f64>,
+    x_vec: &[f64],
+    b_vec: &[f64],
+) -> f64 {
+    let mut err = 0_f64;
+    for i_row in 0..a_mat.num_blk {
+        let mut res = b_vec[i_row] - a_mat.row2val[i_row] * x_vec[i_row];
+        let mut den = b_vec[i_row].abs() + (a_mat.row2val[i_row] * x_vec[i_row]).abs();
+        for idx in a_mat.row2idx[i_row]..a_mat.row2idx[i_row + 1] {
+            let j_col = a_mat.idx2col[idx];
+            res -= a_mat.idx2val[idx] * x_vec[j_col];
+            den += (a_mat.idx2val[idx] * x_vec[j_col]).abs();
+        }
+        if den > 0. {
+            err = err.max(res.abs() / den);
+        }
+    }
+    err
+}
@@ -0,0 +1,176 @@
+//! stationary iterative methods (Jacobi, Gauss-Seidel, SOR and SSOR) for `[A]{x} = {b}`.
+//! They are used as the smoothers of the multigrid and as the cheap preconditioners,
+//! where one iteration from `{x} = 0` gives `{x} = [M]^{-1}{b}`.
+
+use crate::sparse_square::Matrix;
+
+/// order of the rows visited in the Gauss-Seidel and SOR iterations
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Sweep {
+    Forward,
+    Backward,
+    /// forward sweep followed by backward sweep
+    Symmetric,
+}
+
+/// weighted Jacobi iterations `{x} <- {x} + \omega [D]^{-1} ({b} - [A]{x})`
+/// * `x_vec` - initial guess and the result
+/// * `omega` - weight (`2/3` is typical for the smoother)
+pub fn jacobi<T>(x_vec: &mut [T], a: &Matrix<T>, b_vec: &[T], omega: T, num_iteration: usize)
+where
+    T: num_traits::Float,
+{
+    let num_row = a.num_blk;
+    assert_eq!(x_vec.len(), num_row);
+    assert_eq!(b_vec.len(), num_row);
+    let mut r_vec = vec![T::zero(); num_row];
+    for _ in 0..num_iteration {
+        for (i_row, r) in r_vec.iter_mut().enumerate() {
+            let mut v = b_vec[i_row] - a.row2val[i_row] * x_vec[i_row];
+            for idx in a.row2idx[i_row]..a.row2idx[i_row + 1] {
+                v = v - a.idx2val[idx] * x_vec[a.idx2col[idx]];
+            }
+            *r = v;
+        }
+        for (i_row, x) in x_vec.iter_mut().enumerate() {
+            *x = *x + omega * r_vec[i_row] / a.row2val[i_row];
+        }
+    }
+}
+
+/// relax the `i_row`-th row with the latest values of the other rows
+fn relax_row<T>(x_vec: &mut [T], a: &Matrix<T>, b_vec: &[T], omega: T, i_row: usize)
+where
+    T: num_traits::Float,
+{
+    let mut v = b_vec[i_row];
+    for idx in a.row2idx[i_row]..a.row2idx[i_row + 1] {
+        let j_col = a.idx2col[idx];
+        assert_ne!(j_col, i_row);
+        v = v - a.idx2val[idx] * x_vec[j_col];
+    }
+    let x_gs = v / a.row2val[i_row];
+    x_vec[i_row] = x_vec[i_row] + omega * (x_gs - x_vec[i_row]);
+}
+
+/// successive over-relaxation (SOR) iterations. The symmetric sweep gives the SSOR.
+/// * `x_vec` - initial guess and the result
+/// * `omega` - relaxation parameter in `(0, 2)`. Gauss-Seidel if it is one
+pub fn sor<T>(
+    x_vec: &mut [T],
+    a: &Matrix<T>,
+    b_vec: &[T],
+    omega: T,
+    sweep: Sweep,
+    num_iteration: usize,
+) where
+    T: num_traits::Float,
+{
+    let num_row = a.num_blk;
+    assert_eq!(x_vec.len(), num_row);
+    assert_eq!(b_vec.len(), num_row);
+    for _ in 0..num_iteration {
+        if sweep != Sweep::Backward {
+            for i_row in 0..num_row {
+                relax_row(x_vec, a, b_vec, omega, i_row);
+            }
+        }
+        if sweep != Sweep::Forward {
+            for i_row in (0..num_row).rev() {
+                relax_row(x_vec, a, b_vec, omega, i_row);
+            }
+        }
+    }
+}
+
+/// Gauss-Seidel iterations
+/// * `x_vec` - initial guess and the result
+pub fn gauss_seidel<T>(
+    x_vec: &mut [T],
+    a: &Matrix<T>,
+    b_vec: &[T],
+    sweep: Sweep,
+    num_iteration: usize,
+) where
+    T: num_traits::Float,
+{
+    sor(x_vec, a, b_vec, T::one(), sweep, num_iteration);
+}
+
+/// symmetric successive over-relaxation (SSOR) iterations
+/// * `x_vec` - initial guess and the result
+/// * `omega` - relaxation parameter in `(0, 2)`
+pub fn ssor<T>(x_vec: &mut [T], a: &Matrix<T>, b_vec: &[T], omega: T, num_iteration: usize)
+where
+    T: num_traits::Float,
+{
+    sor(x_vec, a, b_vec, omega, Sweep::Symmetric, num_iteration);
+}
+
+#[test]
+fn test_stationary() {
+    let sparse = crate::sparse_square::laplacian_grid(20, 15, 0.1);
+    let num_blk = sparse.num_blk;
+    let b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    let norm_res = |x_vec: &Vec<f64>| {
+        let mut r_vec = b_vec.clone();
+        crate::sparse_square::mult_vec(&mut r_vec, 1., -1., &sparse, x_vec);
+        r_vec.iter().map(|r| r * r).sum::<f64>().sqrt()
+    };
+    let norm_b = norm_res(&vec![0.; num_blk]);
+    let num_iteration = 30;
+    let mut x_jacobi = vec![0.; num_blk];
+    jacobi(&mut x_jacobi, &sparse, &b_vec, 2. / 3., num_iteration);
+    let mut x_gs = vec![];
+    for sweep in [Sweep::Forward, Sweep::Backward, Sweep::Symmetric] {
+        let mut x_vec = vec![0.; num_blk];
+        gauss_seidel(&mut x_vec, &sparse, &b_vec, sweep, num_iteration);
+        x_gs.push(x_vec);
+    }
+    let mut x_sor = vec![0.; num_blk];
+    sor(
+        &mut x_sor,
+        &sparse,
+        &b_vec,
+        1.5,
+        Sweep::Forward,
+        num_iteration,
+    );
+    let mut x_ssor = vec![0.; num_blk];
+    ssor(&mut x_ssor, &sparse, &b_vec, 1., num_iteration);
+    assert_eq!(x_ssor, x_gs[2]);
+    // all converge, where Gauss-Seidel is faster than Jacobi and SOR is faster than Gauss-Seidel
+    let res_jacobi = norm_res(&x_jacobi);
+    let res_gs: Vec<f64> = x_gs.iter().map(norm_res).collect();
+    assert!(res_jacobi < norm_b * 0.5);
+    assert!(res_gs[0] < res_jacobi && res_gs[1] < res_jacobi);
+    assert!(res_gs[2] < res_gs[0]);
+    assert!(norm_res(&x_sor) < res_gs[0] * 0.1);
+}
+
+#[test]
+fn test_stationary_irregular() {
+    // the iterations on `D A D` are similar to those on `A`, so they converge for the diagonally dominant `A`
+    // regardless of the bad scaling
+    let sparse = crate::sparse_square::irregular_test_matrix(500, false, 100.);
+    let num_blk = sparse.num_blk;
+    let b_vec: Vec<f64> = (0..num_blk).map(|i| (i as f64).sin()).collect();
+    let error =
+        |x_vec: &[f64]| crate::sparse_square::componentwise_backward_error(&sparse, x_vec, &b_vec);
+    let num_iteration = 20;
+    let mut x_jacobi = vec![0.; num_blk];
+    jacobi(&mut x_jacobi, &sparse, &b_vec, 1., num_iteration);
+    let mut x_gs = vec![];
+    for sweep in [Sweep::Forward, Sweep::Backward, Sweep::Symmetric] {
+        let mut x_vec = vec![0.; num_blk];
+        gauss_seidel(&mut x_vec, &sparse, &b_vec, sweep, num_iteration);
+        x_gs.push(x_vec);
+    }
+    let mut x_ssor = vec![0.; num_blk];
+    ssor(&mut x_ssor, &sparse, &b_vec, 1., num_iteration);
+    assert_eq!(x_ssor, x_gs[2]);
+    assert!(error(&x_jacobi) < 1.0e-3);
+    for x_vec in &x_gs {
+        assert!(error(x_vec) < error(&x_jacobi));
+    }
+}
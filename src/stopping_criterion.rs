@@ -0,0 +1,159 @@
+//! stopping criteria for the Krylov solvers
+
+/// quantities of the iterative solver used to judge the convergence
+/// * `iteration` - number of iterations done so far
+/// * `sqnorm_rhs` - squared norm of the right-hand side vector `||b||^2`
+/// * `res_hist` - history of the residual norm `||r_k||` (index 0 is the initial residual)
+/// * `rpr_hist` - history of the preconditioned residual norm `(r_k, M^-1 r_k)`
+/// * `energy_hist` - history of the squared A-norm of the updates, which sums up to the squared A-norm of the iterate in CG
+pub struct Status<T> {
+    pub iteration: usize,
+    pub sqnorm_rhs: T,
+    pub res_hist: Vec<T>,
+    pub rpr_hist: Vec<T>,
+    pub energy_hist: Vec<T>,
+}
+
+impl<T> Status<T>
+where
+    T: num_traits::Float,
+{
+    /// status before the first iteration
+    /// * `sqnorm_res` - squared norm of the initial residual
+    /// * `rpr` - `(r_0, M^-1 r_0)`. Set `sqnorm_res` if there is no preconditioner
+    pub fn new(sqnorm_rhs: T, sqnorm_res: T, rpr: T) -> Self {
+        Status {
+            iteration: 0,
+            sqnorm_rhs,
+            res_hist: vec![sqnorm_res.sqrt()],
+            rpr_hist: vec![rpr],
+            energy_hist: vec![],
+        }
+    }
+
+    /// record the quantities after one iteration
+    /// * `energy` - squared A-norm of the update of the solution, i.e., `alpha * (r, M^-1 r)` in CG
+    pub fn push(&mut self, sqnorm_res: T, rpr: T, energy: T) {
+        self.iteration += 1;
+        self.res_hist.push(sqnorm_res.sqrt());
+        self.rpr_hist.push(rpr);
+        self.energy_hist.push(energy);
+    }
+
+    /// norm of the current residual
+    pub fn norm_res(&self) -> T {
+        *self.res_hist.last().unwrap()
+    }
+}
+
+/// criterion to terminate the iteration
+pub enum Criterion<T> {
+    /// `||r_k|| < tol * ||r_0||`
+    RelativeResidual(T),
+    /// `||r_k|| < tol`
+    AbsoluteResidual(T),
+    /// `||r_k|| < tol * ||b||`
+    RhsRelativeResidual(T),
+    /// `sqrt(r_k, M^-1 r_k) < tol * sqrt(r_0, M^-1 r_0)`
+    PreconditionedResidual(T),
+    /// estimate of the relative error in the energy norm for CG (Hestenes-Stiefel estimate).
+    /// `||x - x_{k-d}||_A^2` is approximated by the sum of the last `delay` terms of `alpha_j (r_j, M^-1 r_j)`
+    /// and compared with `tol^2 * ||x_k||_A^2`
+    EnergyNormError { tol: T, delay: usize },
+    /// the residual does not decrease by the factor `ratio` during the last `window` iterations
+    Stagnation { window: usize, ratio: T },
+    /// satisfied if any of the criteria is satisfied
+    Any(Vec<Criterion<T>>),
+    /// satisfied if all of the criteria are satisfied
+    All(Vec<Criterion<T>>),
+}
+
+impl<T> Criterion<T>
+where
+    T: num_traits::Float,
+{
+    /// if the criterion is judged only by the residual norm,
+    /// i.e., it does not use the preconditioned residual nor the energy of the updates
+    pub fn is_residual_based(&self) -> bool {
+        match self {
+            Criterion::PreconditionedResidual(_) | Criterion::EnergyNormError { .. } => false,
+            Criterion::Any(criteria) | Criterion::All(criteria) => {
+                criteria.iter().all(|c| c.is_residual_based())
+            }
+            _ => true,
+        }
+    }
+
+    pub fn is_satisfied(&self, status: &Status<T>) -> bool {
+        match self {
+            Criterion::RelativeResidual(tol) => status.norm_res() < *tol * status.res_hist[0],
+            Criterion::AbsoluteResidual(tol) => status.norm_res() < *tol,
+            Criterion::RhsRelativeResidual(tol) => {
+                status.norm_res() < *tol * status.sqnorm_rhs.sqrt()
+            }
+            Criterion::PreconditionedResidual(tol) => {
+                let rpr = *status.rpr_hist.last().unwrap();
+                rpr.abs().sqrt() < *tol * status.rpr_hist[0].abs().sqrt()
+            }
+            Criterion::EnergyNormError { tol, delay } => {
+                let num_hist = status.energy_hist.len();
+                if *delay == 0 || num_hist < *delay {
+                    return false;
+                }
+                let sqnorm_x = status.energy_hist.iter().fold(T::zero(), |sum, &v| sum + v);
+                let sqnorm_err = status.energy_hist[num_hist - delay..]
+                    .iter()
+                    .fold(T::zero(), |sum, &v| sum + v);
+                sqnorm_err <= *tol * *tol * sqnorm_x
+            }
+            Criterion::Stagnation { window, ratio } => {
+                let num_hist = status.res_hist.len();
+                if *window == 0 || num_hist <= *window {
+                    return false;
+                }
+                status.norm_res() > *ratio * status.res_hist[num_hist - 1 - window]
+            }
+            Criterion::Any(criteria) => criteria.iter().any(|c| c.is_satisfied(status)),
+            Criterion::All(criteria) => criteria.iter().all(|c| c.is_satisfied(status)),
+        }
+    }
+}
+
+#[test]
+fn test_criterion() {
+    // number of the pushes until the criterion is satisfied for the first time
+    let first_satisfied = |criterion: &Criterion<f64>| {
+        let res_hist = [0.5, 0.1, 0.05, 0.049, 0.0489, 0.001];
+        let rpr_hist = [1.0, 0.04, 0.0004, 0.0003, 0.0002, 0.0001];
+        let energy_hist = [1.0, 0.1, 0.01, 0.001, 0.0001, 0.00001];
+        let mut status = Status::new(4., 1., 4.);
+        if criterion.is_satisfied(&status) {
+            return 0;
+        }
+        for i in 0..res_hist.len() {
+            status.push(res_hist[i] * res_hist[i], rpr_hist[i], energy_hist[i]);
+            if criterion.is_satisfied(&status) {
+                return status.iteration;
+            }
+        }
+        usize::MAX
+    };
+    assert_eq!(first_satisfied(&Criterion::RelativeResidual(0.04)), 6);
+    assert_eq!(first_satisfied(&Criterion::AbsoluteResidual(0.04)), 6);
+    assert_eq!(first_satisfied(&Criterion::AbsoluteResidual(2.)), 0);
+    assert_eq!(first_satisfied(&Criterion::RhsRelativeResidual(0.04)), 3);
+    assert_eq!(first_satisfied(&Criterion::PreconditionedResidual(0.05)), 3);
+    let energy = Criterion::EnergyNormError { tol: 0.1, delay: 2 };
+    assert_eq!(first_satisfied(&energy), 4);
+    let stagnation = Criterion::Stagnation {
+        window: 2,
+        ratio: 0.9,
+    };
+    assert_eq!(first_satisfied(&stagnation), 5);
+    let any = Criterion::Any(vec![Criterion::AbsoluteResidual(0.04), stagnation]);
+    assert_eq!(first_satisfied(&any), 5);
+    let all = Criterion::All(vec![Criterion::RhsRelativeResidual(0.04), energy]);
+    assert_eq!(first_satisfied(&all), 4);
+    assert!(any.is_residual_based());
+    assert!(!all.is_residual_based());
+}